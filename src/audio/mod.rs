@@ -0,0 +1,265 @@
+use crate::world::{Event, Side};
+
+pub mod music;
+pub mod offline;
+#[cfg(feature = "web")]
+pub mod web;
+
+// Speed of a served ball in pixels per millisecond, which plays sounds at their base pitch
+const SERVE_SPEED: f32 = 0.6;
+
+// Faster balls raise the pitch up to this many times the base pitch
+const MAX_PITCH: f32 = 2.5;
+
+// Serves wait for the scoring sound to finish
+const SERVE_DELAY_MS: f32 = 350.0;
+
+/// The shape of an oscillator's wave, from the softest to the harshest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wave {
+    Sine,
+    Triangle,
+    Square,
+    Sawtooth
+}
+
+/// A synthesized blip: one oscillator sliding from `frequency` to `end_frequency` (in Hz) while it fades out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tone {
+    pub wave: Wave,
+    pub frequency: f32,
+    pub end_frequency: f32,
+    /// How long to wait before playing, in milliseconds.
+    pub delay_ms: f32,
+    pub duration_ms: f32,
+    /// From 0 (silent) to 1.
+    pub volume: f32
+}
+
+/// Where tones are played.
+pub trait AudioBackend {
+    fn play(&mut self, tone: &Tone) -> Result<(), String>;
+
+    /// Called on every key press, click or tap, for backends that may only start making sound after one, like
+    /// browsers.
+    fn resume(&mut self) {}
+}
+
+/// Plays nothing, for headless builds and hosts without sound.
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, _tone: &Tone) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The sound effects of a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sfx {
+    PaddleHit,
+    WallBounce,
+    Scored(Side),
+    Serve
+}
+
+impl Sfx {
+    /// The tones of the effect with the ball moving at `speed`, in pixels per millisecond. Hits and bounces get
+    /// higher as the ball speeds up.
+    pub fn get_tones(self, speed: f32) -> Vec<Tone> {
+        let pitch = (speed / SERVE_SPEED).clamp(1.0, MAX_PITCH);
+        let tone = |wave, frequency, end_frequency, duration_ms| Tone { wave, frequency, end_frequency, delay_ms: 0.0, duration_ms, volume: 1.0 };
+        match self {
+            Sfx::PaddleHit => vec![tone(Wave::Square, 440.0 * pitch, 440.0 * pitch, 60.0)],
+            Sfx::WallBounce => vec![tone(Wave::Square, 220.0 * pitch, 220.0 * pitch, 40.0)],
+            // Rising for the player, falling for the AI
+            Sfx::Scored(Side::P1) => vec![
+                tone(Wave::Triangle, 523.25, 523.25, 100.0),
+                Tone { delay_ms: 100.0, ..tone(Wave::Triangle, 783.99, 1046.5, 200.0) }
+            ],
+            Sfx::Scored(Side::P2) => vec![
+                tone(Wave::Triangle, 392.0, 392.0, 100.0),
+                Tone { delay_ms: 100.0, ..tone(Wave::Triangle, 261.63, 130.81, 250.0) }
+            ],
+            Sfx::Serve => vec![Tone { delay_ms: SERVE_DELAY_MS, volume: 0.5, ..tone(Wave::Sine, 880.0, 880.0, 50.0) }]
+        }
+    }
+
+    /// The effect that plays for `event`.
+    pub fn from_event(event: Event) -> Sfx {
+        match event {
+            Event::PaddleHit(_) => Sfx::PaddleHit,
+            Event::WallBounce => Sfx::WallBounce,
+            Event::Scored(side) => Sfx::Scored(side),
+            Event::Served => Sfx::Serve
+        }
+    }
+}
+
+/// Plays sound effects and music through a backend, with a master volume over the volume of each.
+pub struct Mixer {
+    backend: Box<dyn AudioBackend>,
+    master_volume: f32,
+    sfx_volume: f32,
+    music_volume: f32,
+    muted: bool
+}
+
+impl Mixer {
+    pub fn new(backend: Box<dyn AudioBackend>) -> Mixer {
+        Mixer {
+            backend,
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 1.0,
+            muted: false
+        }
+    }
+
+    /// From 0 (silent) to 1.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn get_master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    /// From 0 (silent) to 1.
+    pub fn set_sfx_volume(&mut self, volume: f32) {
+        self.sfx_volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn get_sfx_volume(&self) -> f32 {
+        self.sfx_volume
+    }
+
+    /// From 0 (silent) to 1.
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn get_music_volume(&self) -> f32 {
+        self.music_volume
+    }
+
+    /// Muting keeps the volumes for when the sound is back on.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn resume(&mut self) {
+        self.backend.resume();
+    }
+
+    /// Plays `sfx` with the ball moving at `speed`, in pixels per millisecond.
+    pub fn play(&mut self, sfx: Sfx, speed: f32) -> Result<(), String> {
+        self.play_tones(&sfx.get_tones(speed), self.sfx_volume)
+    }
+
+    /// Plays the effects of everything in `events`, with the ball moving at `speed`.
+    pub fn play_events(&mut self, events: &[Event], speed: f32) -> Result<(), String> {
+        for event in events {
+            self.play(Sfx::from_event(*event), speed)?;
+        }
+        Ok(())
+    }
+
+    /// Plays tones of the music, like the ones `music::Sequencer::advance` returns.
+    pub fn play_music(&mut self, tones: &[Tone]) -> Result<(), String> {
+        self.play_tones(tones, self.music_volume)
+    }
+
+    fn play_tones(&mut self, tones: &[Tone], volume: f32) -> Result<(), String> {
+        let volume = self.master_volume * volume;
+        if self.muted || volume == 0.0 {
+            return Ok(());
+        }
+
+        for tone in tones {
+            self.backend.play(&Tone { volume: tone.volume * volume, ..*tone })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    // Keeps what it is asked to play where the test can see it
+    struct RecordingBackend(Rc<RefCell<Vec<Tone>>>);
+
+    impl AudioBackend for RecordingBackend {
+        fn play(&mut self, tone: &Tone) -> Result<(), String> {
+            self.0.borrow_mut().push(*tone);
+            Ok(())
+        }
+    }
+
+    fn recording_mixer() -> (Mixer, Rc<RefCell<Vec<Tone>>>) {
+        let played = Rc::new(RefCell::new(Vec::new()));
+        (Mixer::new(Box::new(RecordingBackend(Rc::clone(&played)))), played)
+    }
+
+    fn volumes(played: &Rc<RefCell<Vec<Tone>>>) -> Vec<f32> {
+        played.borrow_mut().drain(..).map(|tone| tone.volume).collect()
+    }
+
+    #[test]
+    fn scales_effects_and_music_by_the_master_volume() {
+        let (mut mixer, played) = recording_mixer();
+        mixer.set_master_volume(0.5);
+        mixer.set_sfx_volume(0.5);
+        mixer.set_music_volume(0.8);
+
+        mixer.play(Sfx::PaddleHit, SERVE_SPEED).unwrap();
+        assert_eq!(volumes(&played), [0.25]);
+        // The serve is quieter than the other effects to begin with
+        mixer.play(Sfx::Serve, SERVE_SPEED).unwrap();
+        assert_eq!(volumes(&played), [0.125]);
+        mixer.play_music(&Sfx::PaddleHit.get_tones(SERVE_SPEED)).unwrap();
+        assert_eq!(volumes(&played), [0.4]);
+
+        // Out of range volumes are clamped
+        mixer.set_master_volume(2.0);
+        mixer.set_sfx_volume(-1.0);
+        assert_eq!((mixer.get_master_volume(), mixer.get_sfx_volume()), (1.0, 0.0));
+        mixer.play(Sfx::PaddleHit, SERVE_SPEED).unwrap();
+        assert!(volumes(&played).is_empty());
+    }
+
+    #[test]
+    fn plays_nothing_while_muted_and_keeps_the_volumes() {
+        let (mut mixer, played) = recording_mixer();
+        mixer.set_master_volume(0.5);
+        mixer.set_muted(true);
+        mixer.play_events(&[Event::WallBounce, Event::Scored(Side::P1)], SERVE_SPEED).unwrap();
+        mixer.play_music(&Sfx::Serve.get_tones(SERVE_SPEED)).unwrap();
+        assert!(played.borrow().is_empty());
+
+        mixer.set_muted(false);
+        mixer.play(Sfx::WallBounce, SERVE_SPEED).unwrap();
+        assert_eq!(volumes(&played), [0.5]);
+    }
+
+    #[test]
+    fn raises_the_pitch_of_hits_with_the_ball_speed_up_to_a_limit() {
+        let frequency = |sfx: Sfx, speed: f32| sfx.get_tones(speed)[0].frequency;
+        // No lower than the base pitch, even for slower balls
+        assert_eq!(frequency(Sfx::PaddleHit, 0.0), 440.0);
+        assert_eq!(frequency(Sfx::PaddleHit, SERVE_SPEED), 440.0);
+        assert_eq!(frequency(Sfx::PaddleHit, SERVE_SPEED * 2.0), 880.0);
+        assert_eq!(frequency(Sfx::WallBounce, SERVE_SPEED * 2.0), 440.0);
+        assert_eq!(frequency(Sfx::PaddleHit, SERVE_SPEED * 100.0), 440.0 * MAX_PITCH);
+        // Scoring sounds the same at any speed
+        assert_eq!(Sfx::Scored(Side::P1).get_tones(SERVE_SPEED * 2.0), Sfx::Scored(Side::P1).get_tones(SERVE_SPEED));
+    }
+}
@@ -0,0 +1,247 @@
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use crate::audio::{AudioBackend, Tone, Wave, offline::OfflineBackend};
+
+/// Tempo of the music before the first hit of a rally, in beats per minute.
+pub const BASE_BPM: f32 = 120.0;
+
+/// How much faster the music gets with every hit of a rally.
+pub const BPM_PER_HIT: f32 = 4.0;
+
+/// Fastest the music gets, however long the rally.
+pub const MAX_BPM: f32 = 200.0;
+
+// Sixteenth notes, four to a beat, and four bars of sixteen steps to a song before it loops
+const STEPS_PER_BEAT: u32 = 4;
+const STEPS_PER_BAR: u32 = 16;
+const BARS: u32 = 4;
+
+// Notes are scheduled this far ahead of time, so that they play on time between frames
+const LOOKAHEAD_MS: f32 = 100.0;
+
+// A natural minor, from A3
+const ROOT: i32 = 57;
+const SCALE: [i32; 7] = [0, 2, 3, 5, 7, 8, 10];
+
+// Chord progressions, as the scale degree of the root of each bar's chord
+const PROGRESSIONS: [[i32; 4]; 4] = [[0, 5, 3, 4], [0, 3, 4, 4], [0, 5, 6, 4], [0, 2, 5, 4]];
+
+/// The tempo of the music during a rally of `rally` hits.
+pub fn get_bpm(rally: u32) -> f32 {
+    (BASE_BPM + rally as f32 * BPM_PER_HIT).min(MAX_BPM)
+}
+
+/// One note of a song.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Note {
+    /// The step the note starts on.
+    pub step: u32,
+    /// How many steps the note lasts.
+    pub length: u32,
+    pub wave: Wave,
+    /// As a MIDI note number, 69 being A4 at 440 Hz.
+    pub pitch: i32,
+    /// How far the pitch slides by the end of the note, as a frequency ratio: 1 does not slide.
+    pub slide: f32,
+    pub volume: f32
+}
+
+/// A little chiptune tracker: a loop of bass, arpeggio, lead and kick drum generated from a seed, played faster as
+/// rallies get longer. A seed and the same calls always play the same notes at the same times.
+pub struct Sequencer {
+    seed: u64,
+    notes: Vec<Note>,
+    rally: u32,
+    // Milliseconds since the start, and when the next step that was not scheduled yet starts
+    time_ms: f32,
+    next_step_ms: f32,
+    next_step: u32
+}
+
+impl Sequencer {
+    pub fn new(seed: u64) -> Sequencer {
+        Sequencer {
+            seed,
+            notes: Self::compose(seed),
+            rally: 0,
+            time_ms: 0.0,
+            next_step_ms: 0.0,
+            next_step: 0
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    /// Sets the length of the rally being played, which sets the tempo of the steps that were not scheduled yet.
+    pub fn set_rally(&mut self, rally: u32) {
+        self.rally = rally;
+    }
+
+    pub fn get_bpm(&self) -> f32 {
+        get_bpm(self.rally)
+    }
+
+    /// Moves the music forwards and returns the tones of the notes to schedule, delayed to when they start.
+    pub fn advance(&mut self, elapsed_ms: f32) -> Vec<Tone> {
+        self.time_ms += elapsed_ms;
+
+        // After a long frame, like coming back to a background tab, the steps missed are skipped rather than all
+        // played at once
+        let step_ms = 60_000.0 / self.get_bpm() / STEPS_PER_BEAT as f32;
+        let missed = ((self.time_ms - self.next_step_ms) / step_ms).floor();
+        if missed >= 1.0 {
+            self.next_step += missed as u32;
+            self.next_step_ms += missed * step_ms;
+        }
+
+        let mut tones = Vec::new();
+        while self.next_step_ms < self.time_ms + LOOKAHEAD_MS {
+            let step_ms = 60_000.0 / self.get_bpm() / STEPS_PER_BEAT as f32;
+            let step = self.next_step % (STEPS_PER_BAR * BARS);
+            for note in self.notes.iter().filter(|note| note.step == step) {
+                let frequency = 440.0 * 2f32.powf((note.pitch - 69) as f32 / 12.0);
+                tones.push(Tone {
+                    wave: note.wave,
+                    frequency,
+                    end_frequency: frequency * note.slide,
+                    delay_ms: (self.next_step_ms - self.time_ms).max(0.0),
+                    duration_ms: note.length as f32 * step_ms,
+                    volume: note.volume
+                });
+            }
+            self.next_step_ms += step_ms;
+            self.next_step += 1;
+        }
+        tones
+    }
+
+    /// Plays `duration_ms` of the music at a fixed rally length without a sound card, into a 16 bit mono WAV file.
+    pub fn render_wav(seed: u64, rally: u32, duration_ms: f32, sample_rate: u32) -> Vec<u8> {
+        // Frames of a 60 Hz display
+        const FRAME_MS: f32 = 1000.0 / 60.0;
+
+        let mut sequencer = Sequencer::new(seed);
+        sequencer.set_rally(rally);
+        let mut backend = OfflineBackend::new();
+        // Both clocks have to read the same when the tones are played, or they all start a frame off
+        let mut elapsed_ms = 0.0;
+        while backend.get_time_ms() < duration_ms {
+            for tone in sequencer.advance(elapsed_ms) {
+                backend.play(&tone).expect("Offline tones always play");
+            }
+            backend.advance(FRAME_MS);
+            elapsed_ms = FRAME_MS;
+        }
+        backend.render_wav(sample_rate, duration_ms)
+    }
+
+    fn compose(seed: u64) -> Vec<Note> {
+        let mut rng = Pcg32::seed_from_u64(seed);
+        let progression = PROGRESSIONS[rng.gen_range(0, PROGRESSIONS.len())];
+        // The pitch of a degree of the scale, which may go past the octave
+        let pitch = |degree: i32| ROOT + SCALE[degree.rem_euclid(7) as usize] + 12 * degree.div_euclid(7);
+        let note = |step, length, wave, pitch, volume| Note { step, length, wave, pitch, slide: 1.0, volume };
+
+        let mut notes = Vec::new();
+        for (bar, chord) in progression.iter().enumerate() {
+            let first = bar as u32 * STEPS_PER_BAR;
+            let chord_tones = [*chord, chord + 2, chord + 4];
+
+            // Kick on every beat, the root an octave down on the off beats
+            for beat in 0..STEPS_PER_BAR / STEPS_PER_BEAT {
+                let step = first + beat * STEPS_PER_BEAT;
+                notes.push(Note { slide: 0.25, ..note(step, 1, Wave::Sine, 45, 0.6) });
+                notes.push(note(step + 2, 2, Wave::Triangle, pitch(*chord) - 12, 0.5));
+            }
+
+            // Arpeggio up the chord, every other step
+            for i in 0..STEPS_PER_BAR / 2 {
+                let degree = chord_tones[i as usize % chord_tones.len()] + 7;
+                notes.push(note(first + i * 2, 1, Wave::Square, pitch(degree), 0.1));
+            }
+
+            // Lead wandering around the scale from a note of the chord at the start of the bar
+            let mut lead_degree = chord_tones[rng.gen_range(0, 3)] + 7;
+            let mut step = 0;
+            while step < STEPS_PER_BAR {
+                let length = *[1, 2, 2, 4].choose(&mut rng).unwrap();
+                if step > 0 {
+                    lead_degree = (lead_degree + rng.gen_range(-2, 3)).clamp(5, 14);
+                }
+                if step == 0 || rng.gen_bool(0.7) {
+                    notes.push(note(first + step, length, Wave::Square, pitch(lead_degree), 0.25));
+                }
+                step += length;
+            }
+        }
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // When each kick drum starts over the first two seconds of music
+    fn kick_starts(rally: u32) -> Vec<f32> {
+        let mut sequencer = Sequencer::new(1);
+        sequencer.set_rally(rally);
+        let mut time_ms = 0.0;
+        let mut starts = Vec::new();
+        for frame in 0..120 {
+            let elapsed_ms = if frame == 0 { 0.0 } else { 1000.0 / 60.0 };
+            time_ms += elapsed_ms;
+            for tone in sequencer.advance(elapsed_ms) {
+                if tone.wave == Wave::Sine {
+                    starts.push(time_ms + tone.delay_ms);
+                }
+            }
+        }
+        starts
+    }
+
+    #[test]
+    fn skips_the_steps_missed_during_a_long_frame() {
+        let mut sequencer = Sequencer::new(2);
+        sequencer.advance(0.0);
+        let tones = sequencer.advance(10_000.0);
+
+        // No more steps than fit in the lookahead, the one playing now included
+        let step_ms = 60_000.0 / BASE_BPM / STEPS_PER_BEAT as f32;
+        let steps = (LOOKAHEAD_MS / step_ms).ceil() as usize + 1;
+        let most_per_step = (0..STEPS_PER_BAR * BARS)
+            .map(|step| sequencer.get_notes().iter().filter(|note| note.step == step).count())
+            .max()
+            .unwrap();
+        assert!(!tones.is_empty());
+        assert!(tones.len() <= steps * most_per_step, "{} tones at once", tones.len());
+        assert!(tones.iter().all(|tone| tone.delay_ms < LOOKAHEAD_MS));
+    }
+
+    #[test]
+    fn renders_the_same_wav_from_the_same_seed() {
+        let wav = Sequencer::render_wav(5, 3, 2000.0, 8000);
+        assert_eq!(wav, Sequencer::render_wav(5, 3, 2000.0, 8000));
+        assert_ne!(wav, Sequencer::render_wav(6, 3, 2000.0, 8000));
+        assert_eq!(wav.len(), 44 + 2000 / 1000 * 8000 * 2);
+    }
+
+    #[test]
+    fn plays_faster_as_the_rally_goes_on() {
+        let beat_ms = |rally| {
+            let starts = kick_starts(rally);
+            starts[1] - starts[0]
+        };
+        assert!((beat_ms(0) - 60_000.0 / BASE_BPM).abs() < 0.01);
+        assert!(beat_ms(10) < beat_ms(0));
+        assert!((beat_ms(10) - 60_000.0 / get_bpm(10)).abs() < 0.01);
+        assert_eq!(beat_ms(100), beat_ms(1000), "The tempo went past the fastest");
+    }
+}
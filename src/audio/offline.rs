@@ -0,0 +1,139 @@
+use crate::audio::{AudioBackend, Tone, Wave};
+
+// Exponential fades cannot reach 0, so tones fade out to this instead, like in Web Audio
+const SILENCE: f32 = 0.0001;
+
+/// Records tones against a clock of its own and mixes them into samples, for listening to the game's sound without
+/// a sound card. The same tones always mix into the same samples.
+pub struct OfflineBackend {
+    time_ms: f32,
+    // Start in milliseconds and tone
+    tones: Vec<(f32, Tone)>
+}
+
+impl OfflineBackend {
+    pub fn new() -> OfflineBackend {
+        OfflineBackend {
+            time_ms: 0.0,
+            tones: Vec::new()
+        }
+    }
+
+    /// Moves the clock forwards. Tones played next start this much later.
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        self.time_ms += elapsed_ms;
+    }
+
+    pub fn get_time_ms(&self) -> f32 {
+        self.time_ms
+    }
+
+    /// Mixes every tone played so far into `duration_ms` of mono samples between -1 and 1.
+    pub fn render(&self, sample_rate: u32, duration_ms: f32) -> Vec<f32> {
+        let rate = sample_rate as f32 / 1000.0;
+        let mut samples = vec![0.0; (duration_ms * rate) as usize];
+
+        for (start_ms, tone) in self.tones.iter() {
+            let first = (start_ms * rate) as usize;
+            let length = (tone.duration_ms * rate) as usize;
+            let volume = tone.volume.max(SILENCE);
+            let mut phase = 0.0f32;
+            for (i, sample) in samples.iter_mut().skip(first).take(length).enumerate() {
+                // Frequency and volume both follow exponential ramps over the length of the tone
+                let progress = i as f32 / length as f32;
+                let frequency = tone.frequency * (tone.end_frequency / tone.frequency).powf(progress);
+                let gain = volume * (SILENCE / volume).powf(progress);
+                *sample += gain * Self::wave(tone.wave, phase);
+                phase = (phase + frequency / sample_rate as f32).fract();
+            }
+        }
+
+        for sample in samples.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+        samples
+    }
+
+    /// Renders like `render`, into a 16 bit mono WAV file.
+    pub fn render_wav(&self, sample_rate: u32, duration_ms: f32) -> Vec<u8> {
+        encode_wav(&self.render(sample_rate, duration_ms), sample_rate)
+    }
+
+    // One period of the wave, with `phase` from 0 to 1
+    fn wave(wave: Wave, phase: f32) -> f32 {
+        match wave {
+            Wave::Sine => (phase * std::f32::consts::TAU).sin(),
+            Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Wave::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Wave::Sawtooth => 2.0 * phase - 1.0
+        }
+    }
+}
+
+impl AudioBackend for OfflineBackend {
+    fn play(&mut self, tone: &Tone) -> Result<(), String> {
+        self.tones.push((self.time_ms + tone.delay_ms, *tone));
+        Ok(())
+    }
+}
+
+impl Default for OfflineBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes mono samples between -1 and 1 as a 16 bit PCM WAV file.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+
+    // Header
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    // Format: PCM, 1 channel, 16 bits
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+
+    // Samples
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    #[test]
+    fn encodes_a_wav_header_and_samples() {
+        let wav = encode_wav(&[0.0, 1.0, -2.0], 8000);
+        assert_eq!(wav.len(), 44 + 3 * 2);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(read_u32(&wav, 4), 36 + 3 * 2);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(read_u32(&wav, 24), 8000);
+        assert_eq!(read_u32(&wav, 28), 16000);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(read_u32(&wav, 40), 3 * 2);
+
+        let samples: Vec<i16> = wav[44..].chunks(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]])).collect();
+        assert_eq!(samples, vec![0, i16::MAX, -i16::MAX]);
+    }
+}
@@ -0,0 +1,56 @@
+use web_sys::{AudioContext, AudioContextState, OscillatorType};
+
+use crate::audio::{AudioBackend, Tone, Wave};
+
+// Exponential ramps cannot reach 0, so sounds fade out to this instead
+const SILENCE: f32 = 0.0001;
+
+/// Plays tones on Web Audio oscillators.
+pub struct WebAudioBackend {
+    context: AudioContext
+}
+
+impl WebAudioBackend {
+    pub fn new() -> Result<WebAudioBackend, String> {
+        let context = AudioContext::new().map_err(|_| String::from("Web Audio is not supported"))?;
+        Ok(WebAudioBackend {
+            context
+        })
+    }
+}
+
+impl AudioBackend for WebAudioBackend {
+    fn play(&mut self, tone: &Tone) -> Result<(), String> {
+        let error = |_| String::from("Could not play a tone");
+        let start = self.context.current_time() + tone.delay_ms as f64 / 1000.0;
+        let end = start + tone.duration_ms as f64 / 1000.0;
+
+        let oscillator = self.context.create_oscillator().map_err(error)?;
+        oscillator.set_type(match tone.wave {
+            Wave::Sine => OscillatorType::Sine,
+            Wave::Triangle => OscillatorType::Triangle,
+            Wave::Square => OscillatorType::Square,
+            Wave::Sawtooth => OscillatorType::Sawtooth
+        });
+        let frequency = oscillator.frequency();
+        frequency.set_value_at_time(tone.frequency, start).map_err(error)?;
+        frequency.exponential_ramp_to_value_at_time(tone.end_frequency, end).map_err(error)?;
+
+        let gain = self.context.create_gain().map_err(error)?;
+        gain.gain().set_value_at_time(tone.volume.max(SILENCE), start).map_err(error)?;
+        gain.gain().exponential_ramp_to_value_at_time(SILENCE, end).map_err(error)?;
+
+        oscillator.connect_with_audio_node(&gain).map_err(error)?;
+        gain.connect_with_audio_node(&self.context.destination()).map_err(error)?;
+        oscillator.start_with_when(start).map_err(error)?;
+        oscillator.stop_with_when(end).map_err(error)?;
+        Ok(())
+    }
+
+    // Browsers keep audio suspended until the page gets some input
+    fn resume(&mut self) {
+        if self.context.state() == AudioContextState::Suspended {
+            let _ = self.context.resume();
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pong::net::{ClientMessage, DEFAULT_PORT, GameConfig, ServerMessage, client::Connection, lobby_url};
+use pong::session::online::OnlineSession;
+use pong::world::TICK_MS;
+
+/// A headless client that joins a match server and plays by chasing the ball, for testing online play.
+///
+/// With `--create` it opens a room in the lobby (to `--points` if given) and prints its code, and with
+/// `--join <code>` it joins one. Either way it readies up straight away.
+fn main() -> io::Result<()> {
+    let url = arg("--url").unwrap_or_else(|| format!("ws://127.0.0.1:{}", DEFAULT_PORT));
+    let seconds: f32 = arg("--seconds").map(|seconds| seconds.parse().expect("--seconds expects a number")).unwrap_or(10.0);
+
+    let lobby_request = match (flag("--create"), arg("--join")) {
+        (true, _) => {
+            let points_to_win = arg("--points").map(|points| points.parse().expect("--points expects a number")).unwrap_or(0);
+            Some(ClientMessage::CreateRoom { config: GameConfig { points_to_win, ..GameConfig::default() } })
+        },
+        (false, Some(code)) => Some(ClientMessage::JoinRoom { code }),
+        (false, None) => None
+    };
+    let mut connection = match &lobby_request {
+        Some(request) => {
+            let mut connection = Connection::connect(&lobby_url(&url))?;
+            connection.send(request)?;
+            connection
+        },
+        None => Connection::connect(&url)?
+    };
+
+    // Wait for an opponent
+    let mut ready = false;
+    let mut online = loop {
+        match connection.receive()? {
+            Some(ServerMessage::Room { code, ready: players, .. }) => {
+                println!("In room {} with {} players", code, players.len());
+                if !ready {
+                    connection.send(&ClientMessage::SetReady { ready: true })?;
+                    ready = true;
+                }
+            },
+            Some(ServerMessage::LobbyError { message }) => return Err(io::Error::other(message)),
+            Some(message) => {
+                if let Some(online) = OnlineSession::from_welcome(&message) {
+                    break online;
+                }
+            },
+            None => thread::sleep(Duration::from_millis(10))
+        }
+    };
+    println!("Playing as {:?}", online.get_side());
+
+    let tick = Duration::from_secs_f32(TICK_MS / 1000.0);
+    let ticks = (seconds * 1000.0 / TICK_MS) as u64;
+    let mut next_tick = Instant::now();
+    let mut max_correction: f32 = 0.0;
+
+    for _ in 0..ticks {
+        // Receive, keeping track of how far the server moves our predicted paddle
+        // The server hangs up right after saying the match is over
+        while !online.is_over() {
+            let message = match connection.receive()? {
+                Some(message) => message,
+                None => break
+            };
+            let predicted_y = online.get_world().get_paddle(online.get_side()).get_y();
+            online.receive(message);
+            let corrected_y = online.get_world().get_paddle(online.get_side()).get_y();
+            max_correction = max_correction.max((corrected_y - predicted_y).abs());
+        }
+        if online.has_opponent_left() {
+            println!("Opponent left");
+            break;
+        }
+        if online.is_over() {
+            println!("Match over");
+            break;
+        }
+
+        // Chase the ball
+        let world = online.get_world();
+        let paddle = world.get_paddle(online.get_side());
+        let offset = world.get_nearest_ball(paddle.get_x(), paddle.get_y()).get_y() - paddle.get_y();
+        online.set_dir(if offset.abs() < paddle.get_height()/4.0 { 0 } else { offset.signum() as i8 });
+        online.tick();
+
+        // Send
+        for message in online.take_outgoing() {
+            connection.send(&message)?;
+        }
+
+        next_tick += tick;
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+
+    let (p1_score, p2_score) = online.get_world().get_scores();
+    println!("Score {} - {} at tick {}, largest prediction correction {:.1}px", p1_score, p2_score, online.get_world().get_tick(), max_correction);
+    connection.close();
+
+    Ok(())
+}
+
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).cloned()
+}
+
+fn flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
@@ -0,0 +1,20 @@
+use pong::audio::music::{Sequencer, get_bpm};
+
+/// Renders the background music of a match to a WAV file, without a sound card.
+fn main() {
+    let seed: u64 = arg("--seed").map(|seed| seed.parse().expect("--seed expects an unsigned integer")).unwrap_or_else(rand::random);
+    let rally: u32 = arg("--rally").map(|rally| rally.parse().expect("--rally expects a number")).unwrap_or(0);
+    let seconds: f32 = arg("--seconds").map(|seconds| seconds.parse().expect("--seconds expects a number")).unwrap_or(30.0);
+    let sample_rate: u32 = arg("--sample-rate").map(|rate| rate.parse().expect("--sample-rate expects a number")).unwrap_or(44_100);
+    let out = arg("--out").unwrap_or_else(|| String::from("music.wav"));
+    println!("Seed {}", seed);
+
+    let wav = Sequencer::render_wav(seed, rally, seconds * 1000.0, sample_rate);
+    std::fs::write(&out, wav).unwrap_or_else(|err| panic!("Could not write {}: {}", out, err));
+    println!("Wrote {} seconds at {} BPM to {}", seconds, get_bpm(rally), out);
+}
+
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).cloned()
+}
@@ -0,0 +1,300 @@
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use minifb::{InputCallback, Key, KeyRepeat, Window, WindowOptions};
+
+use pong::leaderboard::{Leaderboard, initials::InitialsEntry};
+#[cfg(feature = "net")]
+use pong::net::{client::Connection, spectate_url};
+use pong::render::{Render, software::SoftwareRenderer};
+use pong::replay::{Replay, player::ReplayPlayer};
+use pong::rollback::{RollbackSession, udp::UdpTransport};
+use pong::session::{LiveSession, Session};
+use pong::stats::{Stats, StatsBook};
+use pong::storage::file::FileStorage;
+use pong::world::{Side, ai::Difficulty};
+
+const WIN_WIDTH: usize = 960;
+const WIN_HEIGHT: usize = 600;
+
+/// Collects the characters typed into the window, for entering initials.
+struct TypedChars(Rc<RefCell<Vec<char>>>);
+
+impl InputCallback for TypedChars {
+    fn add_char(&mut self, uni_char: u32) {
+        self.0.borrow_mut().extend(char::from_u32(uni_char));
+    }
+}
+
+fn main() {
+    let mut window = Window::new("Pong", WIN_WIDTH, WIN_HEIGHT, WindowOptions::default()).expect("Could not create window");
+    window.set_target_fps(240);
+    let typed = Rc::new(RefCell::new(Vec::new()));
+    window.set_input_callback(Box::new(TypedChars(typed.clone())));
+
+    let mut session = match (arg("--replay"), arg("--peer")) {
+        (Some(path), _) => Session::Playback(ReplayPlayer::new(load_replay(&path))),
+        (None, Some(peer)) => Session::Rollback(rollback_session(&peer)),
+        (None, None) => {
+            let mut live = match arg("--resume") {
+                Some(path) => load_state(&path),
+                None => LiveSession::new(WIN_WIDTH as f32, WIN_HEIGHT as f32, seed_arg())
+            };
+            if flag("--instant-replay") {
+                live.set_instant_replay(true);
+            }
+            if let Some(difficulty) = arg("--difficulty") {
+                live.set_difficulty(difficulty.parse().unwrap_or_else(|err| panic!("{}", err)));
+            }
+            if let Some(points) = arg("--points") {
+                live.set_points_to_win(points.parse().expect("--points expects a number"));
+            }
+            if let Some(mode) = arg("--mode") {
+                live.set_mode(mode.parse().unwrap_or_else(|err| panic!("{}", err)));
+            }
+            Session::Live(live)
+        }
+    };
+    // With the `net` feature the local match can make way for one watched on a match server
+    #[cfg(feature = "net")]
+    let mut connection = arg("--spectate").map(|url| Connection::connect(&spectate_url(&url)).unwrap_or_else(|err| panic!("Could not connect to {}: {}", url, err)));
+
+    let mut stats = StatsBook::new(Box::new(FileStorage::in_home_dir()));
+    let mut leaderboard = Leaderboard::new(Box::new(FileStorage::in_home_dir()));
+    if leaderboard.is_tampered() {
+        println!("Some leaderboard entries failed their checksum and were dropped");
+    }
+    let mut initials: Option<InitialsEntry> = None;
+
+    let mut renderer = SoftwareRenderer::new(WIN_WIDTH, WIN_HEIGHT);
+    let mut scores = session.get_world().get_scores();
+    let mut status = String::new();
+
+    let mut prev_time = Instant::now();
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        // dt
+        let curr_time = Instant::now();
+        let dt = curr_time.duration_since(prev_time).as_secs_f32() * 1000.0;
+        prev_time = curr_time;
+
+        // Input
+        let typed_chars: Vec<char> = typed.borrow_mut().drain(..).collect();
+        match &mut session {
+            // While initials are being entered, the keyboard types them instead of playing
+            _ if initials.is_some() => {
+                let entry = initials.as_mut().unwrap();
+                for letter in typed_chars {
+                    entry.type_letter(letter);
+                }
+                if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+                    entry.cycle(true);
+                }
+                if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+                    entry.cycle(false);
+                }
+                if window.is_key_pressed(Key::Left, KeyRepeat::No) || window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+                    entry.move_cursor(false);
+                }
+                if window.is_key_pressed(Key::Right, KeyRepeat::No) {
+                    entry.move_cursor(true);
+                }
+                if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+                    let date = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |date| date.as_millis() as u64);
+                    match leaderboard.submit(entry.finish(date)) {
+                        Ok(Some(rank)) => println!("{} is number {} on the {:?} leaderboard", entry.get_initials(), rank + 1, entry.get_mode()),
+                        Ok(None) => {},
+                        Err(err) => println!("Could not save high score: {}", err)
+                    }
+                    initials = None;
+                }
+                session.set_dir(0);
+            },
+            // While a replay plays, the keyboard drives the playback instead of the paddle
+            Session::Playback(player) => {
+                if window.is_key_pressed(Key::Space, KeyRepeat::No) {
+                    player.set_paused(!player.is_paused());
+                }
+                if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
+                    player.skip(false);
+                }
+                if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
+                    player.skip(true);
+                }
+                if window.is_key_pressed(Key::Up, KeyRepeat::No) {
+                    player.set_speed(player.get_speed() * 2.0);
+                }
+                if window.is_key_pressed(Key::Down, KeyRepeat::No) {
+                    player.set_speed(player.get_speed() / 2.0);
+                }
+            },
+            session => {
+                if window.is_key_down(Key::Up) {
+                    session.set_dir(1);
+                }
+                else if window.is_key_down(Key::Down) {
+                    session.set_dir(-1);
+                }
+                else {
+                    session.set_dir(0);
+                }
+                if let Session::Live(live) = session {
+                    if window.is_key_pressed(Key::Space, KeyRepeat::No) || window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+                        if live.is_over() {
+                            live.rematch(rand::random());
+                        }
+                        else {
+                            live.skip_instant_replay();
+                        }
+                    }
+                }
+            }
+        }
+
+        // Network
+        #[cfg(feature = "net")]
+        if let Some(conn) = &mut connection {
+            loop {
+                match conn.receive() {
+                    Ok(Some(message)) => session.receive(message),
+                    Ok(None) => break,
+                    Err(err) => {
+                        println!("Disconnected: {}", err);
+                        connection = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Step
+        session.advance(dt);
+
+        // Stats
+        if let Session::Live(live) = &mut session {
+            let new_stats = live.take_stats();
+            stats.record(&new_stats).unwrap_or_else(|err| println!("Could not save stats: {}", err));
+            // Only the stats of the tick that ended the match count it as played
+            if new_stats.matches_played > 0 && leaderboard.qualifies(live.get_mode(), live.get_score()) {
+                initials = Some(InitialsEntry::new(live.get_score(), live.get_mode(), live.get_world().get_seed()));
+            }
+        }
+
+        // Score, instant replay, winner and high score indicator
+        let new_scores = session.get_world().get_scores();
+        let new_status = match (&session, &initials) {
+            (_, Some(entry)) => {
+                let letters: Vec<String> = entry.get_initials().chars().enumerate()
+                    .map(|(i, letter)| if i == entry.get_cursor() { format!("[{}]", letter) } else { letter.to_string() })
+                    .collect();
+                format!(" - new high score {}! Your initials: {} (enter to save)", entry.get_score(), letters.join(" "))
+            },
+            (Session::Live(live), None) if live.is_over() => match live.get_winner() {
+                Some(Side::P1) => String::from(" - you win! (space for a rematch)"),
+                Some(Side::P2) => String::from(" - the AI wins! (space for a rematch)"),
+                None => format!(" - game over, you scored {} (space to play again)", live.get_score())
+            },
+            _ if session.is_instant_replay() => String::from(" (replay)"),
+            _ => String::new()
+        };
+        if new_scores != scores || new_status != status {
+            scores = new_scores;
+            status = new_status;
+            window.set_title(&format!("Pong {} - {}{}", scores.0, scores.1, status));
+        }
+
+        // Draw
+        renderer.render(session.get_displayed_world());
+        window.update_with_buffer(renderer.get_buffer(), renderer.get_width(), renderer.get_height()).expect("Could not update window");
+    }
+
+    stats.save().unwrap_or_else(|err| println!("Could not save stats: {}", err));
+    println!("Match seed: {}", session.get_world().get_seed());
+    if !stats.get_session().is_empty() {
+        print_stats(stats.get_session());
+    }
+    if let Session::Rollback(rollback) = &session {
+        let (rollbacks, max_rollback) = rollback.get_rollback_stats();
+        println!("Rolled back {} times, at most {} ticks", rollbacks, max_rollback);
+    }
+    if let (Some(path), Some(replay)) = (arg("--record"), session.get_replay()) {
+        save_replay(&path, replay);
+    }
+    if let (Some(path), Session::Live(live)) = (arg("--suspend"), &session) {
+        fs::write(&path, live.save_state()).unwrap_or_else(|err| panic!("Could not save state {}: {}", path, err));
+    }
+}
+
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).cloned()
+}
+
+fn flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
+/// Reads the match seed from `--seed <u64>`, picking a random one when it is not given.
+fn seed_arg() -> u64 {
+    arg("--seed")
+        .map(|seed| seed.parse().expect("--seed expects an unsigned integer"))
+        .unwrap_or_else(rand::random)
+}
+
+/// Connects to `--peer <addr>` from `--bind <addr>`. Both players must pass the same `--seed`, and one of them
+/// `--side p2`.
+fn rollback_session(peer: &str) -> RollbackSession {
+    let bind = arg("--bind").unwrap_or_else(|| "0.0.0.0:9002".to_string());
+    let side = match arg("--side").as_deref() {
+        Some("p2") => Side::P2,
+        Some("p1") | None => Side::P1,
+        Some(side) => panic!("--side expects p1 or p2, not {}", side)
+    };
+    let seed = arg("--seed")
+        .map(|seed| seed.parse().expect("--seed expects an unsigned integer"))
+        .expect("Playing against a peer needs the same --seed on both sides");
+
+    let transport = UdpTransport::new(bind.as_str(), peer).unwrap_or_else(|err| panic!("Could not open {}: {}", bind, err));
+    RollbackSession::new(side, WIN_WIDTH as f32, WIN_HEIGHT as f32, seed, Box::new(transport))
+}
+
+/// Replays ending in `.json` use the debug form, anything else the binary one.
+fn load_replay(path: &str) -> Replay {
+    let replay = if path.ends_with(".json") {
+        Replay::from_json(&fs::read_to_string(path).expect("Could not read replay"))
+    }
+    else {
+        Replay::from_bytes(&fs::read(path).expect("Could not read replay"))
+    };
+    replay.unwrap_or_else(|err| panic!("Could not load replay {}: {}", path, err))
+}
+
+fn print_stats(stats: &Stats) {
+    println!("Matches played: {}", stats.matches_played);
+    for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard].iter() {
+        let record = stats.get_record(*difficulty);
+        if record.wins + record.losses > 0 {
+            println!("  {:?}: {} won, {} lost", difficulty, record.wins, record.losses);
+        }
+    }
+    println!("Longest rally: {} hits", stats.longest_rally);
+    println!("Fastest ball: {:.0} px/s", stats.fastest_ball);
+    println!("Points conceded: {} by you, {} by the AI", stats.p1_points_conceded, stats.p2_points_conceded);
+}
+
+fn load_state(path: &str) -> LiveSession {
+    let bytes = fs::read(path).expect("Could not read saved state");
+    LiveSession::load_state(&bytes).unwrap_or_else(|err| panic!("Could not load saved state {}: {}", path, err))
+}
+
+fn save_replay(path: &str, replay: &Replay) {
+    let result = if path.ends_with(".json") {
+        fs::write(path, replay.to_json())
+    }
+    else {
+        fs::write(path, replay.to_bytes())
+    };
+    result.unwrap_or_else(|err| panic!("Could not save replay {}: {}", path, err));
+}
@@ -0,0 +1,17 @@
+use std::net::TcpListener;
+
+use pong::net::{DEFAULT_PORT, server};
+
+fn main() -> std::io::Result<()> {
+    let port = arg("--port").map(|port| port.parse().expect("--port expects a port number")).unwrap_or(DEFAULT_PORT);
+    let broadcast_delay: f32 = arg("--broadcast-delay").map(|delay| delay.parse().expect("--broadcast-delay expects a number of seconds")).unwrap_or(0.0);
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Listening on port {}", listener.local_addr()?.port());
+
+    server::serve(listener, broadcast_delay * 1000.0)
+}
+
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).cloned()
+}
@@ -0,0 +1,246 @@
+use std::fs;
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossterm::{cursor, event, execute, queue, terminal};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags};
+
+use pong::leaderboard::{Leaderboard, initials::InitialsEntry};
+use pong::render::{Render, terminal::TerminalRenderer};
+use pong::session::LiveSession;
+use pong::stats::StatsBook;
+use pong::storage::file::FileStorage;
+use pong::world::{Side, TICK_MS};
+
+// The simulation runs in the same units as the browser version and is scaled down when drawn
+const WORLD_WIDTH: f32 = 960.0;
+const WORLD_HEIGHT: f32 = 600.0;
+
+const TICKS_PER_FRAME: u32 = 8;
+
+const INSTANT_REPLAY_LABEL: &str = " REPLAY ";
+const P1_WIN_LABEL: &str = " YOU WIN - SPACE FOR A REMATCH ";
+const P2_WIN_LABEL: &str = " THE AI WINS - SPACE FOR A REMATCH ";
+
+// Without key release events a held key is only seen through auto-repeat, which starts after a delay
+const KEY_HOLD_TIMEOUT: Duration = Duration::from_millis(550);
+
+fn main() -> io::Result<()> {
+    // Everything that can stop the game with a bad argument goes before the terminal is taken over
+    let mut session = LiveSession::new(WORLD_WIDTH, WORLD_HEIGHT, seed_arg());
+    session.set_instant_replay(flag("--instant-replay"));
+    if let Some(difficulty) = arg("--difficulty") {
+        session.set_difficulty(difficulty.parse().unwrap_or_else(|err| panic!("{}", err)));
+    }
+    if let Some(points) = arg("--points") {
+        session.set_points_to_win(points.parse().expect("--points expects a number"));
+    }
+    if let Some(mode) = arg("--mode") {
+        session.set_mode(mode.parse().unwrap_or_else(|err| panic!("{}", err)));
+    }
+    let mut leaderboard = Leaderboard::new(Box::new(FileStorage::in_home_dir()));
+    let mut stats = StatsBook::new(Box::new(FileStorage::in_home_dir()));
+    // Nothing can be printed while the game has the terminal
+    let mut stats_error = None;
+
+    let result = {
+        let terminal = TerminalGuard::enter()?;
+        run(&mut io::stdout(), terminal.key_releases, &mut session, &mut leaderboard, &mut stats, &mut stats_error)
+    };
+
+    println!("Match seed: {}", session.get_world().get_seed());
+    if leaderboard.is_tampered() {
+        println!("Some leaderboard entries failed their checksum and were dropped");
+    }
+    if let Some(err) = stats.record(&session.take_stats()).and_then(|()| stats.save()).err().or(stats_error) {
+        eprintln!("Could not save stats: {}", err);
+    }
+    if let Some(path) = arg("--record") {
+        let replay = session.get_replay();
+        let saved = if path.ends_with(".json") { fs::write(&path, replay.to_json()) } else { fs::write(&path, replay.to_bytes()) };
+        saved.unwrap_or_else(|err| panic!("Could not save replay {}: {}", path, err));
+    }
+    result
+}
+
+/// Holds the terminal in raw mode on the alternate screen, and gives it back when dropped, even by a panic.
+struct TerminalGuard {
+    // Whether the terminal reports key releases, which it was asked to
+    key_releases: bool
+}
+
+impl TerminalGuard {
+    fn enter() -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        // From here on, anything that goes wrong still leaves raw mode
+        let mut guard = TerminalGuard { key_releases: false };
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(io::stdout(), event::PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+            guard.key_releases = true;
+        }
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.key_releases {
+            let _ = execute!(stdout, event::PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run(stdout: &mut io::Stdout, key_releases: bool, session: &mut LiveSession, leaderboard: &mut Leaderboard, stats: &mut StatsBook, stats_error: &mut Option<String>) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let mut renderer = TerminalRenderer::new(cols as usize, rows as usize);
+
+    let tick = Duration::from_secs_f32(TICK_MS / 1000.0);
+    let mut next_tick = Instant::now();
+    let mut ticks_since_frame: u32 = 0;
+    let mut last_key_time = Instant::now();
+    // Releasing one arrow key while the other is still held goes back to the held one
+    let mut up_held = false;
+    let mut down_held = false;
+    let mut initials: Option<InitialsEntry> = None;
+    let mut was_over = session.is_over();
+
+    loop {
+        // Input
+        while event::poll(next_tick.saturating_duration_since(Instant::now()))? {
+            let event = event::read()?;
+
+            // While initials are being entered, the keyboard types them instead of playing
+            if let (Some(entry), Event::Key(KeyEvent { code, kind: KeyEventKind::Press | KeyEventKind::Repeat, modifiers, .. })) = (&mut initials, &event) {
+                match code {
+                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Up => entry.cycle(true),
+                    KeyCode::Down => entry.cycle(false),
+                    KeyCode::Left | KeyCode::Backspace => entry.move_cursor(false),
+                    KeyCode::Right => entry.move_cursor(true),
+                    KeyCode::Char(letter) => entry.type_letter(*letter),
+                    KeyCode::Enter => {
+                        let date = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |date| date.as_millis() as u64);
+                        // There is nowhere to show an error while playing, and the game goes on without the entry
+                        let _ = leaderboard.submit(entry.finish(date));
+                        initials = None;
+                    },
+                    _ => {}
+                }
+                continue;
+            }
+
+            match event {
+                Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL, .. }) |
+                Event::Key(KeyEvent { code: KeyCode::Char('q'), .. }) |
+                Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => return Ok(()),
+                Event::Key(KeyEvent { code: KeyCode::Up, kind, .. }) => {
+                    last_key_time = Instant::now();
+                    up_held = kind != KeyEventKind::Release;
+                    // Without releases, pressing a key is all that says the other one was let go
+                    down_held &= key_releases;
+                    session.set_p1_dir(held_dir(up_held, down_held));
+                },
+                Event::Key(KeyEvent { code: KeyCode::Char(' '), .. }) |
+                Event::Key(KeyEvent { code: KeyCode::Enter, .. }) => {
+                    if session.is_over() {
+                        session.rematch(rand::random());
+                    }
+                    else {
+                        session.skip_instant_replay();
+                    }
+                },
+                Event::Key(KeyEvent { code: KeyCode::Down, kind, .. }) => {
+                    last_key_time = Instant::now();
+                    down_held = kind != KeyEventKind::Release;
+                    up_held &= key_releases;
+                    session.set_p1_dir(held_dir(up_held, down_held));
+                },
+                Event::Resize(cols, rows) => {
+                    renderer = TerminalRenderer::new(cols as usize, rows as usize);
+                    queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+                },
+                _ => {}
+            }
+        }
+        if !key_releases && last_key_time.elapsed() > KEY_HOLD_TIMEOUT {
+            up_held = false;
+            down_held = false;
+            session.set_p1_dir(0);
+        }
+
+        // Step
+        session.tick();
+        next_tick += tick;
+
+        // Stats, saved as points end so that a closed terminal loses nothing but the rally
+        if let Err(err) = stats.record(&session.take_stats()) {
+            *stats_error = Some(err);
+        }
+        ticks_since_frame += 1;
+        if session.is_over() && !was_over && leaderboard.qualifies(session.get_mode(), session.get_score()) {
+            initials = Some(InitialsEntry::new(session.get_score(), session.get_mode(), session.get_world().get_seed()));
+        }
+        was_over = session.is_over();
+
+        // Draw
+        if ticks_since_frame == TICKS_PER_FRAME {
+            ticks_since_frame = 0;
+            renderer.render(session.get_displayed_world());
+            queue!(stdout, cursor::MoveTo(0, 0))?;
+            stdout.write_all(renderer.get_frame().as_bytes())?;
+            let label = match (&initials, session.get_winner()) {
+                (Some(entry), _) => {
+                    let letters: Vec<String> = entry.get_initials().chars().enumerate()
+                        .map(|(i, letter)| if i == entry.get_cursor() { format!("[{}]", letter) } else { letter.to_string() })
+                        .collect();
+                    Some(format!(" NEW HIGH SCORE {} - INITIALS {} - ENTER TO SAVE ", entry.get_score(), letters.join(" ")))
+                },
+                (None, Some(Side::P1)) => Some(String::from(P1_WIN_LABEL)),
+                (None, Some(Side::P2)) => Some(String::from(P2_WIN_LABEL)),
+                (None, None) if session.is_over() => Some(format!(" GAME OVER - SCORE {} - SPACE TO PLAY AGAIN ", session.get_score())),
+                (None, None) if session.is_instant_replay() => Some(String::from(INSTANT_REPLAY_LABEL)),
+                (None, None) => None
+            };
+            if let Some(label) = label {
+                let (cols, rows) = terminal::size()?;
+                queue!(stdout, cursor::MoveTo(cols.saturating_sub(label.len() as u16)/2, rows.saturating_sub(1)))?;
+                stdout.write_all(label.as_bytes())?;
+            }
+            stdout.flush()?;
+        }
+    }
+}
+
+// Up wins when both arrow keys are held, as in the desktop build
+fn held_dir(up_held: bool, down_held: bool) -> i8 {
+    if up_held {
+        1
+    }
+    else if down_held {
+        -1
+    }
+    else {
+        0
+    }
+}
+
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).cloned()
+}
+
+fn flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
+/// Reads the match seed from `--seed <u64>`, picking a random one when it is not given.
+fn seed_arg() -> u64 {
+    arg("--seed")
+        .map(|seed| seed.parse().expect("--seed expects an unsigned integer"))
+        .unwrap_or_else(rand::random)
+}
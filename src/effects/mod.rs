@@ -0,0 +1,207 @@
+use crate::world::{Event, Side, World};
+
+pub mod particles;
+pub mod shake;
+pub mod trail;
+use particles::{Particle, ParticleSystem};
+use shake::ScreenShake;
+use trail::Trail;
+
+// Hits at least this fast (in pixels per millisecond) shake the screen, harder the faster they are
+const HARD_HIT_SPEED: f32 = 0.9;
+
+/// Which effects play and how strongly, which depends on the theme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EffectsConfig {
+    pub particles: bool,
+    /// Positions of the ball the trail goes back, 0 for no trail.
+    pub trail_length: usize,
+    /// How hard hard hits shake the screen, in radiuses of the ball for a ball twice as fast as a hard hit. 0 does
+    /// not shake.
+    pub shake: f32,
+    /// How long paddles flash when hit, in milliseconds. 0 does not flash.
+    pub flash_ms: f32
+}
+
+impl EffectsConfig {
+    /// Nothing at all.
+    pub fn none() -> EffectsConfig {
+        EffectsConfig {
+            particles: false,
+            trail_length: 0,
+            shake: 0.0,
+            flash_ms: 0.0
+        }
+    }
+}
+
+/// The visual feedback of a match, driven by what happens in it: particles, a trail behind every ball, screen shake
+/// on hard hits and paddles flashing when hit. Reduced motion turns all of them off.
+pub struct Effects {
+    config: EffectsConfig,
+    reduced_motion: bool,
+    particles: ParticleSystem,
+    // One for each ball in play, in the same order
+    trails: Vec<Trail>,
+    shake: ScreenShake,
+    // Milliseconds left of the flash of player 1 and player 2
+    flash_ms: [f32; 2]
+}
+
+impl Effects {
+    pub fn new(config: EffectsConfig, seed: u64) -> Effects {
+        Effects {
+            config,
+            reduced_motion: false,
+            particles: ParticleSystem::new(seed),
+            trails: Vec::new(),
+            shake: ScreenShake::new(),
+            flash_ms: [0.0, 0.0]
+        }
+    }
+
+    pub fn set_config(&mut self, config: EffectsConfig) {
+        self.config = config;
+        for trail in self.trails.iter_mut() {
+            trail.set_length(config.trail_length);
+        }
+    }
+
+    pub fn get_config(&self) -> EffectsConfig {
+        self.config
+    }
+
+    pub fn set_reduced_motion(&mut self, reduced_motion: bool) {
+        self.reduced_motion = reduced_motion;
+        if reduced_motion {
+            self.clear();
+        }
+    }
+
+    pub fn is_reduced_motion(&self) -> bool {
+        self.reduced_motion
+    }
+
+    /// Starts effects for `events`, which happened in `world` over the last `dt` milliseconds, and moves every
+    /// effect on.
+    pub fn update(&mut self, world: &World, events: &[Event], dt: f32) {
+        if self.reduced_motion {
+            return;
+        }
+
+        for event in events {
+            if let Event::PaddleHit(side) = event {
+                let paddle = world.get_paddle(*side);
+                let ball = world.get_nearest_ball(paddle.get_x(), paddle.get_y());
+                let speed = ball.get_speed();
+                if speed >= HARD_HIT_SPEED {
+                    self.shake.kick(self.config.shake * ball.get_radius() * speed / HARD_HIT_SPEED / 2.0);
+                }
+                self.flash_ms[Self::index(*side)] = self.config.flash_ms;
+            }
+        }
+
+        if self.config.particles {
+            self.particles.update(world, events, dt);
+        }
+        // Balls are only ever added at the end, but any of them can leave play, after which the trails would follow the
+        // wrong balls
+        let balls = world.get_balls();
+        if balls.len() < self.trails.len() {
+            self.trails.clear();
+        }
+        while self.trails.len() < balls.len() {
+            self.trails.push(Trail::new(self.config.trail_length));
+        }
+        // A ball goes nowhere near a third of the arena in one frame unless it was served again
+        for (trail, ball) in self.trails.iter_mut().zip(balls) {
+            trail.record(ball.get_x(), ball.get_y(), world.get_width() / 3.0);
+        }
+        self.shake.update(dt);
+        for flash_ms in self.flash_ms.iter_mut() {
+            *flash_ms = (*flash_ms - dt).max(0.0);
+        }
+    }
+
+    pub fn get_particles(&self) -> &[Particle] {
+        self.particles.get_particles()
+    }
+
+    /// One for each ball in play.
+    pub fn get_trails(&self) -> &[Trail] {
+        &self.trails
+    }
+
+    /// How far to move the arena, in pixels.
+    pub fn get_shake_offset(&self) -> (f32, f32) {
+        self.shake.get_offset()
+    }
+
+    /// How much of the flash of `side`'s paddle is left, from 1 right after a hit down to 0.
+    pub fn get_flash(&self, side: Side) -> f32 {
+        if self.config.flash_ms > 0.0 { self.flash_ms[Self::index(side)] / self.config.flash_ms } else { 0.0 }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.trails.clear();
+        self.shake.clear();
+        self.flash_ms = [0.0, 0.0];
+    }
+
+    fn index(side: Side) -> usize {
+        match side {
+            Side::P1 => 0,
+            Side::P2 => 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const EVERYTHING: EffectsConfig = EffectsConfig { particles: true, trail_length: 8, shake: 1.0, flash_ms: 100.0 };
+
+    // A ball twice as fast as a hard hit, just off the paddle of player 1
+    fn hard_hit() -> World {
+        let world = World::new(960.0, 600.0, 1);
+        let mut json: serde_json::Value = serde_json::from_str(&world.to_json()).unwrap();
+        json["balls"] = json!([{ "radius": 6.0, "x": 100.0, "y": 300.0, "velo_x": HARD_HIT_SPEED * 2.0, "velo_y": 0.0 }]);
+        World::from_json(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn shakes_and_flashes_on_a_hard_hit_until_both_die_down() {
+        let world = hard_hit();
+        let mut effects = Effects::new(EVERYTHING, 1);
+        effects.update(&world, &[Event::PaddleHit(Side::P1)], 20.0);
+        assert_eq!(effects.get_flash(Side::P1), 0.8);
+        assert_eq!(effects.get_flash(Side::P2), 0.0);
+        assert_ne!(effects.get_shake_offset(), (0.0, 0.0));
+        assert!(!effects.get_particles().is_empty());
+
+        effects.update(&world, &[], 1000.0);
+        assert_eq!(effects.get_flash(Side::P1), 0.0);
+        assert_eq!(effects.get_shake_offset(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn plays_nothing_with_reduced_motion() {
+        let world = hard_hit();
+        let mut effects = Effects::new(EVERYTHING, 1);
+        effects.update(&world, &[Event::PaddleHit(Side::P1)], 20.0);
+        // Turning it on stops what is playing
+        effects.set_reduced_motion(true);
+        assert_eq!(effects.get_shake_offset(), (0.0, 0.0));
+        assert_eq!(effects.get_flash(Side::P1), 0.0);
+
+        effects.update(&world, &[Event::PaddleHit(Side::P1)], 20.0);
+        assert_eq!(effects.get_shake_offset(), (0.0, 0.0));
+        assert_eq!(effects.get_flash(Side::P1), 0.0);
+        assert!(effects.get_particles().is_empty());
+        assert!(effects.get_trails().iter().all(Trail::is_empty));
+    }
+}
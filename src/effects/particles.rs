@@ -0,0 +1,270 @@
+use std::f32::consts::{PI, TAU};
+
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use crate::world::{Event, Side, World};
+
+/// Most particles alive at once. Particles spawned past it are dropped.
+pub const MAX_PARTICLES: usize = 1024;
+
+// Particles keep this much of their speed every millisecond
+const DRAG: f32 = 0.997;
+
+/// A speck of light flying off from something that happened in the match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    /// In pixels per millisecond.
+    pub velo_x: f32,
+    pub velo_y: f32,
+    pub size: f32,
+    pub age_ms: f32,
+    pub lifetime_ms: f32
+}
+
+impl Particle {
+    /// How much is left of the particle, from 1 when spawned down to 0 when it dies.
+    pub fn get_fade(&self) -> f32 {
+        (1.0 - self.age_ms / self.lifetime_ms).clamp(0.0, 1.0)
+    }
+}
+
+/// How to spawn a handful of particles at once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub count: u32,
+    /// The angle the particles fly off at, in radians counterclockwise from the right.
+    pub direction: f32,
+    /// How wide the particles fan out around `direction`, in radians.
+    pub spread: f32,
+    /// Slowest and fastest speed, in pixels per millisecond.
+    pub speed: (f32, f32),
+    /// Shortest and longest lifetime, in milliseconds.
+    pub lifetime_ms: (f32, f32),
+    pub size: f32
+}
+
+impl Emitter {
+    /// A few quick sparks fanning out from a hit.
+    pub fn sparks(direction: f32, size: f32) -> Emitter {
+        Emitter {
+            count: 12,
+            direction,
+            spread: PI / 2.0,
+            speed: (0.2, 0.6),
+            lifetime_ms: (150.0, 400.0),
+            size
+        }
+    }
+
+    /// Many slower particles in every direction, for a goal.
+    pub fn burst(size: f32) -> Emitter {
+        Emitter {
+            count: 60,
+            direction: 0.0,
+            spread: TAU,
+            speed: (0.05, 0.8),
+            lifetime_ms: (400.0, 1000.0),
+            size
+        }
+    }
+}
+
+/// Particles in a pool that never grows past its capacity, so that the pool is allocated once.
+pub struct ParticlePool {
+    particles: Vec<Particle>,
+    capacity: usize
+}
+
+impl ParticlePool {
+    pub fn new(capacity: usize) -> ParticlePool {
+        ParticlePool {
+            particles: Vec::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    /// Adds a particle, unless the pool is full.
+    pub fn spawn(&mut self, particle: Particle) -> bool {
+        if self.particles.len() < self.capacity {
+            self.particles.push(particle);
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Moves and ages every particle by `dt` milliseconds, dropping the ones that died.
+    pub fn update(&mut self, dt: f32) {
+        let drag = DRAG.powf(dt);
+        for particle in self.particles.iter_mut() {
+            particle.x += particle.velo_x * dt;
+            particle.y += particle.velo_y * dt;
+            particle.velo_x *= drag;
+            particle.velo_y *= drag;
+            particle.age_ms += dt;
+        }
+        self.particles.retain(|particle| particle.age_ms < particle.lifetime_ms);
+    }
+
+    pub fn get_particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+}
+
+/// Spawns particles for what happens in a match: sparks when a ball hits a paddle or a wall, and a burst where
+/// a ball left the arena when a side scores.
+pub struct ParticleSystem {
+    pool: ParticlePool,
+    rng: Pcg32,
+    // Where the balls were after the last update, since a ball that scored is back in the middle or out of play by
+    // the time the point is scored
+    last_balls: Vec<(f32, f32)>
+}
+
+impl ParticleSystem {
+    /// Particles only look random, and come out the same for the same `seed`.
+    pub fn new(seed: u64) -> ParticleSystem {
+        ParticleSystem {
+            pool: ParticlePool::new(MAX_PARTICLES),
+            rng: Pcg32::seed_from_u64(seed),
+            last_balls: Vec::new()
+        }
+    }
+
+    pub fn emit(&mut self, emitter: &Emitter, x: f32, y: f32) {
+        for _ in 0..emitter.count {
+            let angle = emitter.direction + (self.rng.gen::<f32>() - 0.5) * emitter.spread;
+            let speed = self.rng.gen_range(emitter.speed.0, emitter.speed.1);
+            let particle = Particle {
+                x,
+                y,
+                velo_x: angle.cos() * speed,
+                velo_y: angle.sin() * speed,
+                size: emitter.size,
+                age_ms: 0.0,
+                lifetime_ms: self.rng.gen_range(emitter.lifetime_ms.0, emitter.lifetime_ms.1)
+            };
+            if !self.pool.spawn(particle) {
+                break;
+            }
+        }
+    }
+
+    /// Spawns particles for `events`, which happened in `world` over the last `dt` milliseconds, and moves every
+    /// particle on.
+    pub fn update(&mut self, world: &World, events: &[Event], dt: f32) {
+        let balls = world.get_balls();
+        let size = balls[0].get_radius();
+        for event in events {
+            match event {
+                Event::PaddleHit(side) => {
+                    let direction = if *side == Side::P1 { 0.0 } else { PI };
+                    let paddle = world.get_paddle(*side);
+                    let ball = world.get_nearest_ball(paddle.get_x(), paddle.get_y());
+                    self.emit(&Emitter::sparks(direction, size), ball.get_x(), ball.get_y());
+                },
+                Event::WallBounce => {
+                    let wall_distance = |y: f32| y.min(world.get_height() - y);
+                    let ball = balls.iter()
+                        .min_by(|a, b| wall_distance(a.get_y()).total_cmp(&wall_distance(b.get_y())))
+                        .expect("There is always a ball in play");
+                    let direction = if ball.get_y() < world.get_height()/2.0 { PI/2.0 } else { -PI/2.0 };
+                    self.emit(&Emitter::sparks(direction, size), ball.get_x(), ball.get_y());
+                },
+                Event::Scored(side) => {
+                    let x = if *side == Side::P1 { world.get_width() } else { 0.0 };
+                    let y = self.last_balls.iter()
+                        .min_by(|(a, _), (b, _)| (a - x).abs().total_cmp(&(b - x).abs()))
+                        .map_or(world.get_height()/2.0, |(_, y)| *y);
+                    self.emit(&Emitter::burst(size), x, y);
+                },
+                Event::Served => {}
+            }
+        }
+
+        self.pool.update(dt);
+        self.last_balls = balls.iter().map(|ball| (ball.get_x(), ball.get_y())).collect();
+    }
+
+    pub fn get_particles(&self) -> &[Particle] {
+        self.pool.get_particles()
+    }
+
+    pub fn clear(&mut self) {
+        self.pool.clear();
+        self.last_balls.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(lifetime_ms: f32) -> Particle {
+        Particle { x: 10.0, y: 20.0, velo_x: 1.0, velo_y: -0.5, size: 2.0, age_ms: 0.0, lifetime_ms }
+    }
+
+    #[test]
+    fn drops_particles_past_capacity_and_reuses_the_room_of_dead_ones() {
+        let mut pool = ParticlePool::new(4);
+        let allocated = pool.particles.capacity();
+        for _ in 0..4 {
+            assert!(pool.spawn(particle(100.0)));
+        }
+        assert!(!pool.spawn(particle(100.0)));
+        assert_eq!(pool.len(), 4);
+
+        pool.update(100.0);
+        assert!(pool.is_empty());
+        for _ in 0..4 {
+            assert!(pool.spawn(particle(100.0)));
+        }
+        assert!(!pool.spawn(particle(100.0)));
+        assert_eq!(pool.particles.capacity(), allocated);
+    }
+
+    #[test]
+    fn moves_particles_until_they_expire() {
+        let mut pool = ParticlePool::new(4);
+        pool.spawn(particle(100.0));
+        pool.spawn(particle(300.0));
+
+        pool.update(50.0);
+        let first = pool.get_particles()[0];
+        assert_eq!((first.x, first.y, first.age_ms), (60.0, -5.0, 50.0));
+        assert_eq!(first.get_fade(), 0.5);
+        // Drag slows them down
+        assert!(first.velo_x < 1.0);
+
+        pool.update(50.0);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.get_particles()[0].lifetime_ms, 300.0);
+        pool.update(200.0);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn stops_emitting_once_the_pool_is_full() {
+        let mut particles = ParticleSystem::new(1);
+        for _ in 0..MAX_PARTICLES / 60 + 1 {
+            particles.emit(&Emitter::burst(2.0), 0.0, 0.0);
+        }
+        assert_eq!(particles.get_particles().len(), MAX_PARTICLES);
+    }
+}
@@ -0,0 +1,81 @@
+// The shake keeps this much of its strength every millisecond
+const DECAY: f32 = 0.985;
+
+// Below this many pixels the shake stops
+const STILL: f32 = 0.1;
+
+/// Shaking of the whole arena that dies down quickly. It wobbles along a fixed path rather than at random, so it
+/// looks the same every time.
+pub struct ScreenShake {
+    strength: f32,
+    elapsed_ms: f32
+}
+
+impl ScreenShake {
+    pub fn new() -> ScreenShake {
+        ScreenShake {
+            strength: 0.0,
+            elapsed_ms: 0.0
+        }
+    }
+
+    /// Shakes by up to `strength` pixels, unless it already shakes harder.
+    pub fn kick(&mut self, strength: f32) {
+        self.strength = self.strength.max(strength);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed_ms += dt;
+        self.strength *= DECAY.powf(dt);
+        if self.strength < STILL {
+            self.strength = 0.0;
+        }
+    }
+
+    /// How far to move everything in the arena, in pixels.
+    pub fn get_offset(&self) -> (f32, f32) {
+        if self.strength == 0.0 {
+            return (0.0, 0.0);
+        }
+        ((self.elapsed_ms * 0.091).sin() * self.strength, (self.elapsed_ms * 0.073).cos() * self.strength)
+    }
+
+    pub fn clear(&mut self) {
+        self.strength = 0.0;
+    }
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dies_down_to_nothing() {
+        let mut shake = ScreenShake::new();
+        assert_eq!(shake.get_offset(), (0.0, 0.0));
+        shake.kick(5.0);
+        shake.update(16.0);
+        let (x, y) = shake.get_offset();
+        assert!(x.abs() > 0.0 && x.abs() <= 5.0 && y.abs() <= 5.0);
+
+        shake.update(1000.0);
+        assert_eq!(shake.strength, 0.0);
+        assert_eq!(shake.get_offset(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn keeps_the_hardest_kick() {
+        let mut shake = ScreenShake::new();
+        shake.kick(5.0);
+        shake.kick(2.0);
+        assert_eq!(shake.strength, 5.0);
+        shake.kick(8.0);
+        assert_eq!(shake.strength, 8.0);
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+/// The last positions of the ball, newest first, to draw a trail fading out behind it.
+pub struct Trail {
+    points: VecDeque<(f32, f32)>,
+    length: usize
+}
+
+impl Trail {
+    pub fn new(length: usize) -> Trail {
+        Trail {
+            points: VecDeque::with_capacity(length),
+            length
+        }
+    }
+
+    /// Keeps the last `length` positions, 0 turning the trail off.
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+        self.points.truncate(length);
+    }
+
+    /// Adds the newest position. A ball that jumped further than `max_jump`, like when it is served again or an
+    /// instant replay starts, starts a new trail.
+    pub fn record(&mut self, x: f32, y: f32, max_jump: f32) {
+        if let Some((last_x, last_y)) = self.points.front() {
+            if (x - last_x).hypot(y - last_y) > max_jump {
+                self.points.clear();
+            }
+        }
+        if self.length > 0 {
+            self.points.truncate(self.length - 1);
+            self.points.push_front((x, y));
+        }
+    }
+
+    /// Newest first.
+    pub fn get_points(&self) -> impl Iterator<Item = &(f32, f32)> {
+        self.points.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(trail: &Trail) -> Vec<(f32, f32)> {
+        trail.get_points().copied().collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_points_first_up_to_its_length() {
+        let mut trail = Trail::new(3);
+        for x in 0..5 {
+            trail.record(x as f32, 0.0, 10.0);
+        }
+        assert_eq!(points(&trail), [(4.0, 0.0), (3.0, 0.0), (2.0, 0.0)]);
+
+        trail.set_length(2);
+        assert_eq!(points(&trail), [(4.0, 0.0), (3.0, 0.0)]);
+        trail.set_length(0);
+        trail.record(5.0, 0.0, 10.0);
+        assert!(trail.is_empty());
+    }
+
+    #[test]
+    fn starts_over_when_the_ball_jumps() {
+        let mut trail = Trail::new(3);
+        trail.record(0.0, 0.0, 10.0);
+        trail.record(5.0, 0.0, 10.0);
+        trail.record(50.0, 0.0, 10.0);
+        assert_eq!(points(&trail), [(50.0, 0.0)]);
+    }
+}
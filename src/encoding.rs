@@ -0,0 +1,46 @@
+/// Reads the little endian binary encodings of replays and saved states.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    // What is being read, for error messages
+    name: &'static str
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], name: &'static str) -> Reader<'a> {
+        Reader {
+            bytes,
+            pos: 0,
+            name
+        }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.bytes.get(self.pos..self.pos + len).ok_or_else(|| format!("{} is truncated", self.name))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn take_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub fn take_f32(&mut self) -> Result<f32, String> {
+        let value = f32::from_le_bytes(self.take_array()?);
+        if value.is_finite() {
+            Ok(value)
+        }
+        else {
+            Err(format!("{} holds a number that is not finite", self.name))
+        }
+    }
+
+    /// Everything not read yet.
+    pub fn take_rest(&mut self) -> &'a [u8] {
+        let rest = &self.bytes[self.pos..];
+        self.pos = self.bytes.len();
+        rest
+    }
+}
@@ -0,0 +1,83 @@
+use web_sys::Element;
+
+/// The HTML shown over the canvas. Elements are only touched when what they show changes.
+pub struct Hud {
+    p1_score: Element,
+    p2_score: Element,
+    scores: Option<(u32, u32)>,
+    scores_visible: bool,
+    instant_replay: Element,
+    instant_replay_visible: bool,
+    initials: Element,
+    initials_title: Element,
+    initials_letters: Element,
+    shown_letters: String
+}
+
+impl Hud {
+    pub fn new(document: &web_sys::Document) -> Result<Hud, String> {
+        let element = |id: &str| document.get_element_by_id(id).ok_or_else(|| format!("The page has no #{}", id));
+        Ok(Hud {
+            p1_score: element("p1-score")?,
+            p2_score: element("p2-score")?,
+            scores: None,
+            scores_visible: true,
+            instant_replay: element("instant-replay")?,
+            instant_replay_visible: false,
+            initials: element("initials")?,
+            initials_title: element("initials-title")?,
+            initials_letters: element("initials-letters")?,
+            shown_letters: String::new()
+        })
+    }
+
+    pub fn set_scores(&mut self, scores: (u32, u32)) {
+        if self.scores != Some(scores) {
+            self.scores = Some(scores);
+            self.p1_score.set_inner_html(&scores.0.to_string());
+            self.p2_score.set_inner_html(&scores.1.to_string());
+        }
+    }
+
+    pub fn set_scores_visible(&mut self, visible: bool) {
+        if visible != self.scores_visible {
+            self.scores_visible = visible;
+            Self::set_visible(&self.p1_score, visible);
+            Self::set_visible(&self.p2_score, visible);
+        }
+    }
+
+    pub fn set_instant_replay_visible(&mut self, visible: bool) {
+        if visible != self.instant_replay_visible {
+            self.instant_replay_visible = visible;
+            Self::set_visible(&self.instant_replay, visible);
+        }
+    }
+
+    pub fn show_initials(&mut self, score: u32) {
+        self.initials_title.set_inner_html(&format!("New high score: {}", score));
+        Self::set_visible(&self.initials, true);
+    }
+
+    pub fn hide_initials(&mut self) {
+        Self::set_visible(&self.initials, false);
+        self.set_initials_letters("");
+    }
+
+    /// `letters` may hold HTML markup.
+    pub fn set_initials_letters(&mut self, letters: &str) {
+        if letters != self.shown_letters {
+            self.initials_letters.set_inner_html(letters);
+            self.shown_letters = String::from(letters);
+        }
+    }
+
+    fn set_visible(element: &Element, visible: bool) {
+        if visible {
+            element.remove_attribute("hidden").unwrap();
+        }
+        else {
+            element.set_attribute("hidden", "").unwrap();
+        }
+    }
+}
@@ -3,109 +3,381 @@ use std::cell::RefCell;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{WebGlProgram, WebGlShader, WebGlRenderingContext};
 use js_sys::Date;
 
-use crate::game_object::{ball, paddle, traits::{Draw, Step}};
-use ball::Ball;
-use paddle::Paddle;
+use crate::audio::{AudioBackend, Mixer, NullBackend, music::Sequencer, web::WebAudioBackend};
+use crate::effects::{Effects, EffectsConfig};
+use crate::leaderboard::Leaderboard;
+use crate::menu::{Menu, Screen, settings::{MAX_VOLUME, Settings}};
+use crate::net::{ClientMessage, ServerMessage};
+use crate::render::{RenderBackend, overlay::Overlay};
+use crate::scene::{Input, SceneStack};
+use crate::session::{LiveSession, Session};
+use crate::stats::StatsBook;
+use crate::storage::{MemoryStorage, Storage, local::LocalStorage};
+
+mod hud;
+mod scenes;
+use hud::Hud;
+use scenes::{AttractScene, MenuScene, PlayScene};
+
+// Gamepad sticks further out than this count as pressing the d-pad
+const STICK_THRESHOLD: f64 = 0.5;
+
+/// How long without any input before the attract mode starts, in milliseconds.
+pub const ATTRACT_DELAY_MS: f32 = 30_000.0;
+
+/// The buttons of a standard gamepad that the game uses.
+#[derive(Clone, Copy, Default, PartialEq)]
+struct GamepadState {
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+    confirm: bool,
+    back: bool,
+    start: bool
+}
+
+impl GamepadState {
+    // Every button stands in for a key, so that scenes only deal with the keyboard
+    fn keys(&self) -> [(bool, &'static str); 7] {
+        [
+            (self.up, "ArrowUp"),
+            (self.down, "ArrowDown"),
+            (self.left, "ArrowLeft"),
+            (self.right, "ArrowRight"),
+            (self.confirm, "Enter"),
+            (self.back, "Backspace"),
+            (self.start, "Escape")
+        ]
+    }
+}
+
+/// What the scenes of the browser game share.
+pub struct Game {
+    session: Rc<RefCell<Session>>,
+    renderer: Box<dyn RenderBackend>,
+    socket: Rc<RefCell<Option<web_sys::WebSocket>>>,
+    stats: Rc<RefCell<StatsBook>>,
+    leaderboard: Rc<RefCell<Leaderboard>>,
+    menu: Menu,
+    settings_storage: Box<dyn Storage>,
+    overlay: Overlay,
+    effects: Effects,
+    hud: Hud,
+    mixer: Mixer,
+    // The music of the match being played, when the music is on
+    music: Option<Sequencer>,
+    // Set while the attract mode shows
+    attract: bool,
+    // 0 never starts it
+    attract_delay_ms: f32,
+    idle_ms: f32,
+    // Set between the browser dropping the WebGL context and giving it back
+    context_lost: bool
+}
+
+impl Game {
+    /// Saves the settings of the menus, and applies them to everything but the match.
+    fn save_settings(&mut self) {
+        self.menu.get_settings().save(self.settings_storage.as_mut())
+            .unwrap_or_else(|err| web_sys::console::warn_1(&format!("Could not save settings: {}", err).into()));
+        self.apply_settings();
+    }
 
-extern crate rand;
-use rand::prelude::*;
+    fn apply_settings(&mut self) {
+        let settings = self.menu.get_settings();
+        self.renderer.set_theme(settings.theme);
+        self.renderer.set_post_effects(settings.post_effects);
+        self.effects.set_config(settings.theme.get_effects());
+        self.effects.set_reduced_motion(settings.reduced_motion);
+        self.mixer.set_master_volume(settings.volume as f32 / MAX_VOLUME as f32);
+        self.mixer.set_sfx_volume(settings.sfx_volume as f32 / MAX_VOLUME as f32);
+        self.mixer.set_music_volume(if settings.music { settings.music_volume as f32 / MAX_VOLUME as f32 } else { 0.0 });
+        self.mixer.set_muted(settings.muted);
+    }
+}
 
 pub struct GameManager {
-    context: WebGlRenderingContext,
-    win_width: f32,
-    win_height: f32,
-
-    p1: Rc<RefCell<paddle::Paddle>>,
-    p2: Rc<RefCell<paddle::Paddle>>,
-    ball: Rc<RefCell<ball::Ball>>,
-    p1_score: u32,
-    p2_score: u32
+    session: Rc<RefCell<Session>>,
+    canvas: web_sys::HtmlCanvasElement,
+    socket: Rc<RefCell<Option<web_sys::WebSocket>>>,
+    stats: Rc<RefCell<StatsBook>>,
+    leaderboard: Rc<RefCell<Leaderboard>>,
+    game: Rc<RefCell<Game>>,
+    scenes: Rc<RefCell<SceneStack<Game>>>,
+    // Input from the event handlers, for the scenes to take in the next frame
+    inputs: Rc<RefCell<Vec<Input>>>
 }
 
 impl GameManager {
-    pub fn new(context: WebGlRenderingContext, vert_shader_src: &str, frag_shader_src: &str, win_width: f32, win_height: f32) -> Result<GameManager, JsValue> {
-        let vert_shader = Self::compile_shader(&context, WebGlRenderingContext::VERTEX_SHADER, vert_shader_src)?;
-        let frag_shader = Self::compile_shader(&context, WebGlRenderingContext::FRAGMENT_SHADER, frag_shader_src)?;
-        let program = Self::link_program(&context, &vert_shader, &frag_shader)?;
-        context.use_program(Some(&program));
-        context.delete_shader(Some(&vert_shader));
-        context.delete_shader(Some(&frag_shader));
+    /// Plays on `canvas`, drawn by `renderer`.
+    pub fn new(canvas: web_sys::HtmlCanvasElement, renderer: Box<dyn RenderBackend>, win_width: f32, win_height: f32, seed: u64) -> Result<GameManager, JsValue> {
+
+        let document = web_sys::window().expect("Could not get window").document().expect("Could not get document");
+        let session = Rc::new(RefCell::new(Session::Live(LiveSession::new(win_width, win_height, seed))));
+        web_sys::console::log_1(&format!("Drawing with {}", renderer.get_version()).into());
+
+        let settings_storage = Self::open_storage();
+        let settings = Settings::load(settings_storage.as_ref());
+
+        // Without Web Audio the game is silent
+        let audio: Box<dyn AudioBackend> = match WebAudioBackend::new() {
+            Ok(audio) => Box::new(audio),
+            Err(err) => {
+                web_sys::console::warn_1(&format!("The game will be silent: {}", err).into());
+                Box::new(NullBackend)
+            }
+        };
 
-        context.bind_attrib_location(&program, 0, "attr_position");
+        let stats = Rc::new(RefCell::new(StatsBook::new(Self::open_storage())));
+        let leaderboard = Leaderboard::new(Self::open_storage());
+        if leaderboard.is_tampered() {
+            web_sys::console::warn_1(&"Some leaderboard entries failed their checksum and were dropped".into());
+        }
+        let leaderboard = Rc::new(RefCell::new(leaderboard));
+        let socket = Rc::new(RefCell::new(None));
+
+        let mut game = Game {
+            session: session.clone(),
+            renderer,
+            socket: socket.clone(),
+            stats: stats.clone(),
+            leaderboard: leaderboard.clone(),
+            menu: Menu::new(settings),
+            settings_storage,
+            overlay: Overlay::new(),
+            effects: Effects::new(EffectsConfig::none(), rand::random()),
+            hud: Hud::new(&document)?,
+            mixer: Mixer::new(audio),
+            music: None,
+            attract: false,
+            attract_delay_ms: ATTRACT_DELAY_MS,
+            idle_ms: 0.0,
+            context_lost: false
+        };
+
+        game.apply_settings();
+
+        // The match waits behind the title screen
+        let mut scenes = SceneStack::new();
+        scenes.push(&mut game, Box::new(PlayScene));
+        scenes.push(&mut game, Box::new(MenuScene::new(Screen::Title)));
 
-        let paddle_width = win_width.min(win_height)/50.0;
-        let paddle_height = win_width.min(win_height)/5.0;
+        Ok(GameManager {
+            session,
+            canvas,
+            socket,
+            stats,
+            leaderboard,
+            game: Rc::new(RefCell::new(game)),
+            scenes: Rc::new(RefCell::new(scenes)),
+            inputs: Rc::new(RefCell::new(Vec::new()))
+        })
+    }
 
-        let p1 = Rc::new(RefCell::new(Paddle::new(paddle_width/2.0, win_height/2.0, paddle_width, paddle_height)));
-        p1.borrow_mut().init_gl(&context, win_width, win_height);
+    // Without localStorage (like in private browsing) nothing lasts longer than the page
+    fn open_storage() -> Box<dyn Storage> {
+        match LocalStorage::new() {
+            Ok(storage) => Box::new(storage),
+            Err(err) => {
+                web_sys::console::warn_1(&format!("Settings, stats and high scores will not be kept: {}", err).into());
+                Box::new(MemoryStorage::new())
+            }
+        }
+    }
 
-        let p2 = Rc::new(RefCell::new(Paddle::new(win_width - paddle_width/2.0, win_height/2.0, paddle_width, paddle_height)));
-        p2.borrow_mut().init_gl(&context, win_width, win_height);
+    pub fn get_session(&self) -> Rc<RefCell<Session>> {
+        self.session.clone()
+    }
 
-        let ball = Rc::new(RefCell::new(Ball::new(win_width/2.0, win_height/2.0, paddle_width/2.0)));
-        ball.borrow_mut().init_gl(&context, win_width, win_height);
+    pub fn get_stats(&self) -> Rc<RefCell<StatsBook>> {
+        self.stats.clone()
+    }
 
-        let p1_score: u32 = 0;
-        let p2_score: u32 = 0;
+    pub fn get_leaderboard(&self) -> Rc<RefCell<Leaderboard>> {
+        self.leaderboard.clone()
+    }
 
-        Ok(GameManager {
-            context: context,
-            win_width: win_width,
-            win_height: win_height,
-
-            p1: p1,
-            p2: p2,
-            ball: ball,
-            p1_score: p1_score,
-            p2_score: p2_score
-        })
+    /// Closes every scene above the match, like the menus, to go straight to playing.
+    pub fn close_scenes(&self) {
+        self.scenes.borrow_mut().truncate(&mut self.game.borrow_mut(), 1);
+    }
+
+    /// Sets how long the game waits without any input before showing the attract mode, 0 never showing it.
+    pub fn set_attract_delay(&self, delay_ms: f32) {
+        let mut game = self.game.borrow_mut();
+        game.attract_delay_ms = delay_ms;
+        game.idle_ms = 0.0;
+    }
+
+    /// Turns one of the CRT effects on or off and remembers it with the settings.
+    pub fn set_post_effect(&self, name: &str, enabled: bool) -> Result<(), String> {
+        let mut game = self.game.borrow_mut();
+        game.menu.get_settings_mut().post_effects.set(name, enabled)?;
+        game.save_settings();
+        Ok(())
+    }
+
+    /// Replaces the sources of the shader program called `name` while the game runs. A program that does not compile
+    /// keeps the old one drawing, and the error points at the lines it is about.
+    pub fn reload_shader(&self, name: &str, vert_shader_src: &str, frag_shader_src: &str) -> Result<(), String> {
+        self.game.borrow_mut().renderer.reload_shader(name, vert_shader_src, frag_shader_src)
+    }
+
+    /// The vertex and fragment shader sources of the program called `name`.
+    pub fn get_shader_source(&self, name: &str) -> Option<(String, String)> {
+        self.game.borrow().renderer.get_shader_source(name).map(|(vert, frag)| (String::from(vert), String::from(frag)))
+    }
+
+    /// Leaves whatever is showing for the attract mode. A match in progress is paused when the attract mode ends.
+    pub fn start_attract(&self) {
+        let mut game = self.game.borrow_mut();
+        let mut scenes = self.scenes.borrow_mut();
+        let interrupted = match &*game.session.borrow() {
+            Session::Live(live) => !live.is_over() && matches!(game.menu.get_screen(), None | Some(Screen::Pause)),
+            _ => false
+        };
+        scenes.truncate(&mut game, 1);
+        scenes.push(&mut game, Box::new(AttractScene::new(interrupted)));
+    }
+
+    /// Connects to a match server, to play, watch or go to the lobby depending on `url`, and sends `first_message`
+    /// as soon as the connection is open. The local match keeps going until the online match starts.
+    pub fn connect(&mut self, url: &str, first_message: Option<ClientMessage>) -> Result<(), JsValue> {
+        let socket = web_sys::WebSocket::new(url)?;
+
+        if let Some(message) = first_message {
+            let socket_clone = socket.clone();
+            let open_closure = Closure::once(move || {
+                socket_clone.send_with_str(&message.to_json()).unwrap_or_else(|_| web_sys::console::warn_1(&"Could not send to the server".into()));
+            });
+            socket.set_onopen(Some(open_closure.as_ref().unchecked_ref()));
+            open_closure.forget();
+        }
+
+        let session_clone = self.session.clone();
+        let message_closure = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+            let message = match event.data().as_string().map(|text| ServerMessage::from_json(&text)) {
+                Some(Ok(message)) => message,
+                _ => {
+                    web_sys::console::warn_1(&"Unexpected message from the server".into());
+                    return;
+                }
+            };
+
+            match &message {
+                ServerMessage::Room { code, ready, .. } => {
+                    let ready_count = ready.iter().filter(|ready| **ready).count();
+                    web_sys::console::log_1(&format!("Room {}: {} of {} players ready", code, ready_count, ready.len()).into());
+                },
+                ServerMessage::LobbyError { message } => web_sys::console::warn_1(&message.into()),
+                _ => {}
+            }
+            session_clone.borrow_mut().receive(message);
+        }) as Box<dyn FnMut(_)>);
+        socket.set_onmessage(Some(message_closure.as_ref().unchecked_ref()));
+        message_closure.forget();
+
+        if let Some(old_socket) = self.socket.borrow_mut().replace(socket) {
+            old_socket.close()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message over the connection made by `connect`.
+    pub fn send(&self, message: &ClientMessage) -> Result<(), JsValue> {
+        match self.socket.borrow().as_ref() {
+            Some(socket) => socket.send_with_str(&message.to_json()),
+            None => Err("Not connected to a server".into())
+        }
     }
 
     pub fn init_event_handlers(&mut self, document: &web_sys::Document) -> Result<(), JsValue> {
-        let p1_clone1 = self.p1.clone();
+        let inputs_clone1 = self.inputs.clone();
         let key_down_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
-            if event.key() == "ArrowUp" {
-                p1_clone1.borrow_mut().set_dir(1.0);
-            }
-            else if event.key() == "ArrowDown" {
-                p1_clone1.borrow_mut().set_dir(-1.0);
-            }
+            inputs_clone1.borrow_mut().push(Input::KeyDown(event.key()));
         }) as Box<dyn FnMut(_)>);
         document.add_event_listener_with_callback("keydown", key_down_closure.as_ref().unchecked_ref())?;
         key_down_closure.forget();
 
-        let p1_clone2 = self.p1.clone();
+        let inputs_clone2 = self.inputs.clone();
         let key_up_closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
-            if event.key() == "ArrowUp" {
-                p1_clone2.borrow_mut().set_dir(0.0);
-            }
-            else if event.key() == "ArrowDown" {
-                p1_clone2.borrow_mut().set_dir(0.0);
-            }
+            inputs_clone2.borrow_mut().push(Input::KeyUp(event.key()));
         }) as Box<dyn FnMut(_)>);
         document.add_event_listener_with_callback("keyup", key_up_closure.as_ref().unchecked_ref())?;
         key_up_closure.forget();
 
+        let session_clone1 = self.session.clone();
+        let canvas_clone1 = self.canvas.clone();
+        let inputs_clone3 = self.inputs.clone();
+        let mouse_move_closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+            let (x, y) = Self::to_world(&canvas_clone1, &session_clone1.borrow(), event.client_x(), event.client_y());
+            inputs_clone3.borrow_mut().push(Input::Point(x, y));
+        }) as Box<dyn FnMut(_)>);
+        self.canvas.add_event_listener_with_callback("mousemove", mouse_move_closure.as_ref().unchecked_ref())?;
+        mouse_move_closure.forget();
+
+        let session_clone2 = self.session.clone();
+        let canvas_clone2 = self.canvas.clone();
+        let inputs_clone4 = self.inputs.clone();
+        let click_closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+            let (x, y) = Self::to_world(&canvas_clone2, &session_clone2.borrow(), event.client_x(), event.client_y());
+            inputs_clone4.borrow_mut().push(Input::Click(x, y));
+        }) as Box<dyn FnMut(_)>);
+        self.canvas.add_event_listener_with_callback("click", click_closure.as_ref().unchecked_ref())?;
+        click_closure.forget();
+
+        let session_clone3 = self.session.clone();
+        let canvas_clone3 = self.canvas.clone();
+        let inputs_clone5 = self.inputs.clone();
+        let touch_closure = Closure::wrap(Box::new(move |event: web_sys::TouchEvent| {
+            // Taps would otherwise be repeated as clicks
+            event.prevent_default();
+            if let Some(touch) = event.changed_touches().get(0) {
+                let (x, y) = Self::to_world(&canvas_clone3, &session_clone3.borrow(), touch.client_x(), touch.client_y());
+                inputs_clone5.borrow_mut().push(Input::Click(x, y));
+            }
+        }) as Box<dyn FnMut(_)>);
+        self.canvas.add_event_listener_with_callback("touchstart", touch_closure.as_ref().unchecked_ref())?;
+        touch_closure.forget();
+
+        // The browser may drop the context, like when the GPU resets or a phone runs low on memory. Everything on the
+        // GPU is gone with it, so the game waits until it comes back, then sets it all up again.
+        let game_clone1 = self.game.clone();
+        let context_lost_closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            // Without this the context never comes back
+            event.prevent_default();
+            web_sys::console::warn_1(&"The WebGL context was lost, the game waits for it to come back".into());
+            game_clone1.borrow_mut().context_lost = true;
+        }) as Box<dyn FnMut(_)>);
+        self.canvas.add_event_listener_with_callback("webglcontextlost", context_lost_closure.as_ref().unchecked_ref())?;
+        context_lost_closure.forget();
+
+        let game_clone2 = self.game.clone();
+        let context_restored_closure = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            let mut game = game_clone2.borrow_mut();
+            match game.renderer.restore() {
+                Ok(()) => {
+                    web_sys::console::log_1(&"The WebGL context is back".into());
+                    game.context_lost = false;
+                },
+                Err(err) => web_sys::console::error_1(&format!("Could not set up the WebGL context again: {}", err).into())
+            }
+        }) as Box<dyn FnMut(_)>);
+        self.canvas.add_event_listener_with_callback("webglcontextrestored", context_restored_closure.as_ref().unchecked_ref())?;
+        context_restored_closure.forget();
+
         Ok(())
     }
 
     pub fn start_game(&mut self) -> Result<(), JsValue> {
-        let p1_clone = self.p1.clone();
-        let p2_clone = self.p2.clone();
-        let ball_clone = self.ball.clone();
-        let context_clone = self.context.clone();
-        let p1_html = web_sys::window().expect("Could not get window").document().unwrap().get_element_by_id("p1-score").unwrap();
-        let mut p1_score = self.p1_score.clone();
-        let p2_html = web_sys::window().expect("Could not get window").document().unwrap().get_element_by_id("p2-score").unwrap();
-        let mut p2_score = self.p2_score.clone();
-        let win_width = self.win_width.clone();
-        let win_height = self.win_height.clone();
-
-        let mut rng = rand::thread_rng();
-        let mut p2_target = rng.gen_range(-p2_clone.borrow().get_height()/2.0, p2_clone.borrow().get_height()/2.0);
+        let game_clone = self.game.clone();
+        let scenes_clone = self.scenes.clone();
+        let inputs_clone = self.inputs.clone();
+        let mut gamepad = GamepadState::default();
 
         let mut prev_time = Date::now();
 
@@ -128,68 +400,51 @@ impl GameManager {
             let dt = (curr_time - prev_time) as f32;
             prev_time = curr_time;
 
-            // Step
-            p1_clone.borrow_mut().step(dt);
-            p2_clone.borrow_mut().step(dt);
-            ball_clone.borrow_mut().step(dt);
-
-            // Physics
-
-            // Out of bounds
-            if ball_clone.borrow().get_dir() < 0.0 && ball_clone.borrow().get_x() <= 0.0 - p1_clone.borrow().get_width()/2.0 {
-                ball_clone.borrow_mut().reset(win_width/2.0, win_height/2.0);
-                p2_score += 1;
-                p1_html.set_inner_html(&p1_score.to_string()[..]);
-                p2_html.set_inner_html(&p2_score.to_string()[..]);
-            }
-            if ball_clone.borrow().get_dir() > 0.0 && ball_clone.borrow().get_x() >= win_width + p2_clone.borrow().get_width()/2.0 {
-                ball_clone.borrow_mut().reset(win_width/2.0, win_height/2.0);
-                p1_score += 1;
-                p1_html.set_inner_html(&p1_score.to_string()[..]);
-                p2_html.set_inner_html(&p2_score.to_string()[..]);
-            }
+            let mut game = game_clone.borrow_mut();
+            let mut scenes = scenes_clone.borrow_mut();
 
-            // Walls
-            if ball_clone.borrow().get_y_dir() < 0.0 && ball_clone.borrow().get_y() <= p1_clone.borrow().get_width()/2.0 {
-                ball_clone.borrow_mut().bounce_y();
+            // Nothing moves while the context is lost, so that everything carries on where it was once it is back
+            if game.context_lost {
+                inputs_clone.borrow_mut().clear();
+                window2.set_timeout_with_callback_and_timeout_and_arguments_0(timeout_callback_clone.borrow_mut().as_ref().unchecked_ref(), 1000 / 240).unwrap();
+                return;
             }
-            if ball_clone.borrow().get_y_dir() > 0.0 && ball_clone.borrow().get_y() >= win_height - p1_clone.borrow().get_width()/2.0 {
-                ball_clone.borrow_mut().bounce_y();
+
+            // Gamepad
+            let new_gamepad = Self::read_gamepad().unwrap_or_default();
+            for ((now, key), (before, _)) in new_gamepad.keys().iter().zip(gamepad.keys().iter()) {
+                if now != before {
+                    let key = String::from(*key);
+                    inputs_clone.borrow_mut().push(if *now { Input::KeyDown(key) } else { Input::KeyUp(key) });
+                }
             }
+            gamepad = new_gamepad;
 
-            // Player 1 paddle collision
-            if ball_clone.borrow().get_dir() < 0.0 && ball_clone.borrow().get_x() <= p1_clone.borrow().get_width()*3.0/2.0 && Self::within(ball_clone.borrow().get_y(), p1_clone.borrow().get_y(), p1_clone.borrow().get_height()/2.0) {
-                let dy = (ball_clone.borrow().get_y() - p1_clone.borrow().get_y())/p1_clone.borrow().get_height()/2.0;
-                ball_clone.borrow_mut().bounce(dy);
+            // Input
+            let inputs: Vec<Input> = inputs_clone.borrow_mut().drain(..).collect();
+            if inputs.iter().any(|input| matches!(input, Input::KeyDown(_) | Input::Click(_, _))) {
+                game.mixer.resume();
             }
-            // Player 2 paddle collision
-            else if ball_clone.borrow().get_dir() > 0.0 && ball_clone.borrow().get_x() >= win_width - p2_clone.borrow().get_width()*3.0/2.0 && Self::within(ball_clone.borrow().get_y(), p2_clone.borrow().get_y(), p2_clone.borrow().get_height()/2.0) {
-                let dy = (ball_clone.borrow().get_y() - p2_clone.borrow().get_y())/p2_clone.borrow().get_height()/2.0;
-                ball_clone.borrow_mut().bounce(dy);
-                p2_target = rng.gen_range(-p2_clone.borrow().get_height()/2.0, p2_clone.borrow().get_height()/2.0);
+            for input in inputs.iter() {
+                scenes.input(&mut game, input);
             }
 
-            // Player 2 AI
-            if !Self::within(p2_clone.borrow().get_y() + p2_target, ball_clone.borrow().get_y(), p2_clone.borrow().get_height()/50.0) {
-                if p2_clone.borrow().get_y() + p2_target < ball_clone.borrow().get_y() {
-                    p2_clone.borrow_mut().set_dir(1.0);
-                }
-                else {
-                    p2_clone.borrow_mut().set_dir(-1.0);
-                }
-            }
-            else {
-                p2_clone.borrow_mut().set_dir(0.0);
+            // Attract mode, once nobody has touched the title or game over menu of a local match for a while
+            game.idle_ms = if inputs.is_empty() { game.idle_ms + dt } else { 0.0 };
+            let live = matches!(*game.session.borrow(), Session::Live(_));
+            let idle_menu = matches!(game.menu.get_screen(), Some(Screen::Title) | Some(Screen::GameOver { .. }));
+            if live && idle_menu && !game.attract && game.attract_delay_ms > 0.0 && game.idle_ms >= game.attract_delay_ms {
+                scenes.truncate(&mut game, 1);
+                scenes.push(&mut game, Box::new(AttractScene::new(false)));
             }
 
-            // Clear
-            context_clone.clear_color(0.0, 0.0, 0.0, 1.0);
-            context_clone.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+            // Step
+            scenes.update(&mut game, dt);
 
             // Draw
-            ball_clone.borrow_mut().draw(&context_clone, win_width, win_height);
-            p1_clone.borrow_mut().draw(&context_clone, win_width, win_height);
-            p2_clone.borrow_mut().draw(&context_clone, win_width, win_height);
+            game.renderer.begin_frame();
+            scenes.render(&mut game);
+            game.renderer.end_frame();
 
             window2.set_timeout_with_callback_and_timeout_and_arguments_0(timeout_callback_clone.borrow_mut().as_ref().unchecked_ref(), 1000 / 240).unwrap();
         }) as Box<dyn FnMut()>);
@@ -198,34 +453,33 @@ impl GameManager {
         Ok(())
     }
 
-    fn within(x: f32, y: f32, tolerance: f32) -> bool {
-        x < y+tolerance && x > y-tolerance
+    /// Converts a position in the page to world coordinates.
+    fn to_world(canvas: &web_sys::HtmlCanvasElement, session: &Session, client_x: i32, client_y: i32) -> (f32, f32) {
+        let rect = canvas.get_bounding_client_rect();
+        let world = session.get_world();
+        let x = (client_x as f64 - rect.left()) / rect.width() * world.get_width() as f64;
+        let y = (rect.bottom() - client_y as f64) / rect.height() * world.get_height() as f64;
+        (x as f32, y as f32)
     }
 
-    fn compile_shader(context: &WebGlRenderingContext, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
-        let shader = context.create_shader(shader_type).ok_or_else(|| String::from("Unable to create shader object"))?;
-        context.shader_source(&shader, &source);
-        context.compile_shader(&shader);
-
-        if context.get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS).as_bool().unwrap_or(false) {
-            Ok(shader)
-        }
-        else {
-            Err(context.get_shader_info_log(&shader).unwrap_or_else(|| String::from("Unknown error creating shader")))
-        }
-    }
-
-    fn link_program(context: &WebGlRenderingContext, vert_shader: &WebGlShader, frag_shader: &WebGlShader) -> Result<WebGlProgram, String> {
-        let program = context.create_program().ok_or_else(|| String::from("Unable to create program object"))?;
-        context.attach_shader(&program, vert_shader);
-        context.attach_shader(&program, frag_shader);
-        context.link_program(&program);
-
-        if context.get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS).as_bool().unwrap_or(false) {
-            Ok(program)
-        }
-        else {
-            Err(context.get_program_info_log(&program).unwrap_or_else(|| String::from("Unknown error creating program")))
-        }
+    /// The first connected gamepad with the standard mapping, if there is one.
+    fn read_gamepad() -> Option<GamepadState> {
+        let gamepads = web_sys::window()?.navigator().get_gamepads().ok()?;
+        let gamepad = gamepads.iter().find_map(|gamepad| gamepad.dyn_into::<web_sys::Gamepad>().ok().filter(|gamepad| gamepad.connected()))?;
+
+        let buttons = gamepad.buttons();
+        let button = |index: u32| buttons.get(index).dyn_into::<web_sys::GamepadButton>().is_ok_and(|button| button.pressed());
+        let axes = gamepad.axes();
+        let axis = |index: u32| axes.get(index).as_f64().unwrap_or(0.0);
+
+        Some(GamepadState {
+            up: button(12) || axis(1) < -STICK_THRESHOLD,
+            down: button(13) || axis(1) > STICK_THRESHOLD,
+            left: button(14) || axis(0) < -STICK_THRESHOLD,
+            right: button(15) || axis(0) > STICK_THRESHOLD,
+            confirm: button(0),
+            back: button(1),
+            start: button(9)
+        })
     }
 }
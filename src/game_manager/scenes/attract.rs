@@ -0,0 +1,101 @@
+use crate::effects::{Effects, EffectsConfig};
+use crate::game_manager::Game;
+use crate::menu::{Menu, Screen, TITLE_SIZE};
+use crate::scene::{Input, Scene, Transition};
+use crate::session::{LiveSession, Session};
+use crate::world::ai::Difficulty;
+
+use super::{MenuScene, render_world, start_match};
+
+// How long the prompt to play stays lit, then dark, in milliseconds
+const BLINK_MS: f32 = 500.0;
+
+/// A demo of the AI playing itself, for when nobody is playing. Any key, click or tap starts a real match below it,
+/// or goes back to the match it interrupted, paused.
+pub struct AttractScene {
+    demo: Option<LiveSession>,
+    effects: Effects,
+    elapsed_ms: f32,
+    interrupted: bool
+}
+
+impl AttractScene {
+    /// `interrupted` is whether a match in progress is on hold below, to be paused again rather than replaced.
+    pub fn new(interrupted: bool) -> AttractScene {
+        AttractScene {
+            demo: None,
+            effects: Effects::new(EffectsConfig::none(), rand::random()),
+            elapsed_ms: 0.0,
+            interrupted
+        }
+    }
+}
+
+impl Scene<Game> for AttractScene {
+    // The demo is played in the same arena as the real match
+    fn enter(&mut self, game: &mut Game) {
+        let (width, height) = {
+            let session = game.session.borrow();
+            (session.get_world().get_width(), session.get_world().get_height())
+        };
+        let mut demo = LiveSession::new(width, height, rand::random());
+        // The hard AI keeps the rallies going the longest
+        demo.set_difficulty(Difficulty::Hard);
+        demo.set_p1_ai(Some(Difficulty::Hard));
+        self.demo = Some(demo);
+        // The same effects as the real match
+        self.effects.set_config(game.effects.get_config());
+        self.effects.set_reduced_motion(game.effects.is_reduced_motion());
+        game.attract = true;
+    }
+
+    fn exit(&mut self, game: &mut Game) {
+        game.attract = false;
+    }
+
+    fn input(&mut self, game: &mut Game, input: &Input) -> Transition<Game> {
+        match input {
+            Input::KeyDown(_) | Input::Click(_, _) if self.interrupted => Transition::Replace(Box::new(MenuScene::new(Screen::Pause))),
+            Input::KeyDown(_) | Input::Click(_, _) => {
+                if let Session::Live(live) = &mut *game.session.borrow_mut() {
+                    start_match(live, game.menu.get_settings());
+                }
+                Transition::Pop
+            },
+            _ => Transition::Stay
+        }
+    }
+
+    fn update(&mut self, game: &mut Game, dt: f32) -> Transition<Game> {
+        self.elapsed_ms += dt;
+        if let Some(demo) = &mut self.demo {
+            demo.advance(dt);
+            let events = demo.take_events();
+            self.effects.update(demo.get_world(), &events, dt);
+        }
+
+        // Only local matches can be interrupted
+        if matches!(*game.session.borrow(), Session::Live(_)) { Transition::Stay } else { Transition::Pop }
+    }
+
+    fn render(&mut self, game: &mut Game) {
+        let demo = match &self.demo {
+            Some(demo) => demo,
+            None => return
+        };
+        let world = demo.get_world();
+        render_world(game.renderer.as_mut(), &self.effects, world);
+        game.hud.set_scores(world.get_scores());
+        game.hud.set_scores_visible(true);
+        game.hud.set_instant_replay_visible(false);
+
+        let (width, height) = (world.get_width(), world.get_height());
+        let size = Menu::font_size(width, height);
+        game.overlay.clear();
+        game.overlay.text("PONG", width/2.0, height*0.8, size*TITLE_SIZE, false);
+        if ((self.elapsed_ms / BLINK_MS) as u32).is_multiple_of(2) {
+            game.overlay.text("PRESS ANY KEY", width/2.0, height*0.2, size, true);
+        }
+        game.renderer.render_overlay(&game.overlay, width, height);
+    }
+}
@@ -0,0 +1,75 @@
+use js_sys::Date;
+
+use crate::game_manager::Game;
+use crate::leaderboard::initials::InitialsEntry;
+use crate::scene::{Input, Scene, Transition};
+
+/// Arcade style entry of the initials for a new high score, in HTML over the match.
+pub struct InitialsScene {
+    entry: InitialsEntry
+}
+
+impl InitialsScene {
+    pub fn new(entry: InitialsEntry) -> InitialsScene {
+        InitialsScene {
+            entry
+        }
+    }
+}
+
+impl Scene<Game> for InitialsScene {
+    fn enter(&mut self, game: &mut Game) {
+        game.hud.show_initials(self.entry.get_score());
+    }
+
+    fn exit(&mut self, game: &mut Game) {
+        game.hud.hide_initials();
+    }
+
+    fn input(&mut self, game: &mut Game, input: &Input) -> Transition<Game> {
+        let key = match input {
+            Input::KeyDown(key) => key,
+            _ => return Transition::Stay
+        };
+
+        let entry = &mut self.entry;
+        match &key[..] {
+            "ArrowUp" => entry.cycle(true),
+            "ArrowDown" => entry.cycle(false),
+            "ArrowLeft" | "Backspace" => entry.move_cursor(false),
+            "ArrowRight" => entry.move_cursor(true),
+            "Enter" => {
+                match game.leaderboard.borrow_mut().submit(entry.finish(Date::now() as u64)) {
+                    Ok(Some(rank)) => web_sys::console::log_1(&format!("{} is number {} on the {:?} leaderboard", entry.get_initials(), rank + 1, entry.get_mode()).into()),
+                    Ok(None) => {},
+                    Err(err) => web_sys::console::warn_1(&format!("Could not save high score: {}", err).into())
+                }
+                return Transition::Pop;
+            },
+            key => {
+                let mut chars = key.chars();
+                if let (Some(letter), None) = (chars.next(), chars.next()) {
+                    entry.type_letter(letter);
+                }
+            }
+        }
+        Transition::Stay
+    }
+
+    fn update(&mut self, _game: &mut Game, _dt: f32) -> Transition<Game> {
+        Transition::Stay
+    }
+
+    fn render(&mut self, game: &mut Game) {
+        let letters = self.entry.get_initials().chars().enumerate()
+            .map(|(i, letter)| if i == self.entry.get_cursor() { format!("<u>{}</u>", letter) } else { letter.to_string() })
+            .collect::<Vec<String>>()
+            .join(" ");
+        game.hud.set_initials_letters(&letters);
+    }
+
+    // The match stays drawn below, behind the HTML
+    fn is_opaque(&self, _game: &Game) -> bool {
+        false
+    }
+}
@@ -0,0 +1,88 @@
+use crate::game_manager::Game;
+use crate::menu::{MenuCommand, MenuInput, Screen};
+use crate::scene::{Input, Scene, Transition};
+use crate::session::Session;
+
+use super::start_match;
+
+/// The menus, opened on a screen. They drive the local match below them, and close once it goes on.
+pub struct MenuScene {
+    screen: Screen
+}
+
+impl MenuScene {
+    pub fn new(screen: Screen) -> MenuScene {
+        MenuScene {
+            screen
+        }
+    }
+}
+
+impl Scene<Game> for MenuScene {
+    fn enter(&mut self, game: &mut Game) {
+        game.menu.close();
+        game.menu.push(self.screen.clone());
+    }
+
+    fn exit(&mut self, game: &mut Game) {
+        game.menu.close();
+    }
+
+    fn input(&mut self, game: &mut Game, input: &Input) -> Transition<Game> {
+        let (width, height) = {
+            let session = game.session.borrow();
+            (session.get_world().get_width(), session.get_world().get_height())
+        };
+        let command = match input {
+            Input::KeyDown(key) => {
+                let input = match &key[..] {
+                    "ArrowUp" => MenuInput::Up,
+                    "ArrowDown" => MenuInput::Down,
+                    "ArrowLeft" => MenuInput::Left,
+                    "ArrowRight" => MenuInput::Right,
+                    " " | "Enter" => MenuInput::Confirm,
+                    "Escape" | "Backspace" => MenuInput::Back,
+                    _ => return Transition::Stay
+                };
+                game.menu.input(input)
+            },
+            Input::KeyUp(_) => None,
+            Input::Point(x, y) => {
+                game.menu.point(width, height, *x, *y);
+                None
+            },
+            Input::Click(x, y) => game.menu.click(width, height, *x, *y)
+        };
+
+        let session = game.session.clone();
+        match (command, &mut *session.borrow_mut()) {
+            (Some(MenuCommand::Start), Session::Live(live)) => start_match(live, game.menu.get_settings()),
+            (Some(MenuCommand::SettingsChanged), _) => game.save_settings(),
+            _ => {}
+        }
+
+        if game.menu.is_open() { Transition::Stay } else { Transition::Pop }
+    }
+
+    // Only local matches have menus, and online ones can start at any time
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Transition<Game> {
+        if matches!(*game.session.borrow(), Session::Live(_)) { Transition::Stay } else { Transition::Pop }
+    }
+
+    fn render(&mut self, game: &mut Game) {
+        if self.is_opaque(game) {
+            game.renderer.clear();
+            game.hud.set_scores_visible(false);
+        }
+
+        let session = game.session.borrow();
+        let (width, height) = (session.get_world().get_width(), session.get_world().get_height());
+        game.overlay.clear();
+        game.menu.draw(width, height, &mut game.overlay);
+        game.renderer.render_overlay(&game.overlay, width, height);
+    }
+
+    fn is_opaque(&self, game: &Game) -> bool {
+        game.menu.get_screen().is_some_and(|screen| screen.is_opaque())
+    }
+}
@@ -0,0 +1,43 @@
+use crate::effects::Effects;
+use crate::menu::settings::Settings;
+use crate::render::RenderBackend;
+use crate::session::LiveSession;
+use crate::world::{Side, World};
+
+mod attract;
+mod initials;
+mod menu;
+mod play;
+pub use attract::AttractScene;
+pub use initials::InitialsScene;
+pub use menu::MenuScene;
+pub use play::PlayScene;
+
+/// Applies the settings to `live` and starts a new match. A match that has not started yet keeps its seed, so that a
+/// seed from the page URL is played.
+fn start_match(live: &mut LiveSession, settings: &Settings) {
+    live.set_mode(settings.mode);
+    live.set_difficulty(settings.difficulty);
+    live.set_points_to_win(settings.points_to_win);
+    live.set_instant_replay(settings.instant_replay);
+
+    let seed = if live.get_world().get_tick() == 0 { live.get_world().get_seed() } else { rand::random() };
+    if seed != live.get_world().get_seed() {
+        web_sys::console::log_1(&format!("Match seed: {}", seed).into());
+    }
+    live.rematch(seed);
+}
+
+/// Draws `world` with its effects: shaken, with the paddles flashing and the trails and particles on top.
+fn render_world(renderer: &mut dyn RenderBackend, effects: &Effects, world: &World) {
+    let (width, height) = (world.get_width(), world.get_height());
+    let (offset_x, offset_y) = effects.get_shake_offset();
+    renderer.set_shake(offset_x, offset_y);
+    renderer.set_flash(effects.get_flash(Side::P1), effects.get_flash(Side::P2));
+    renderer.render(world);
+    let radius = world.get_balls()[0].get_radius();
+    for trail in effects.get_trails() {
+        renderer.render_trail(trail, radius, width, height);
+    }
+    renderer.render_particles(effects.get_particles(), width, height);
+}
@@ -0,0 +1,148 @@
+use crate::audio::music::Sequencer;
+use crate::game_manager::Game;
+use crate::leaderboard::initials::InitialsEntry;
+use crate::menu::Screen;
+use crate::scene::{Input, Scene, Transition};
+use crate::session::{LiveSession, Session, mode::Mode};
+use crate::world::Side;
+
+use super::{InitialsScene, MenuScene, render_world};
+
+/// A match, whether local, online or a replay played back. The bottom of the stack.
+pub struct PlayScene;
+
+impl PlayScene {
+    fn game_over_message(live: &LiveSession) -> String {
+        let (p1_score, p2_score) = live.get_world().get_scores();
+        match (live.get_mode(), live.get_winner()) {
+            (_, Some(Side::P1)) => format!("You win {} - {}", p1_score, p2_score),
+            (_, Some(Side::P2)) => format!("The AI wins {} - {}", p2_score, p1_score),
+            (Mode::Survival, None) => format!("You lasted {} seconds", live.get_score()),
+            (_, None) => format!("You scored {}", live.get_score())
+        }
+    }
+}
+
+impl Scene<Game> for PlayScene {
+    // Keys let go of while another scene was on top would otherwise keep the paddle moving
+    fn resume(&mut self, game: &mut Game) {
+        game.session.borrow_mut().set_dir(0);
+    }
+
+    fn input(&mut self, game: &mut Game, input: &Input) -> Transition<Game> {
+        let key = match input {
+            Input::KeyDown(key) => key,
+            Input::KeyUp(key) => {
+                if key == "ArrowUp" || key == "ArrowDown" {
+                    game.session.borrow_mut().set_dir(0);
+                }
+                return Transition::Stay;
+            },
+            _ => return Transition::Stay
+        };
+
+        if key == "m" || key == "M" {
+            let settings = game.menu.get_settings_mut();
+            settings.muted = !settings.muted;
+            game.save_settings();
+            return Transition::Stay;
+        }
+
+        match &mut *game.session.borrow_mut() {
+            // While a replay plays, the keyboard drives the playback instead of the paddle
+            Session::Playback(player) => {
+                match &key[..] {
+                    " " => player.set_paused(!player.is_paused()),
+                    "ArrowLeft" => player.skip(false),
+                    "ArrowRight" => player.skip(true),
+                    "ArrowUp" => player.set_speed(player.get_speed() * 2.0),
+                    "ArrowDown" => player.set_speed(player.get_speed() / 2.0),
+                    _ => {}
+                }
+            },
+            session => {
+                if key == "ArrowUp" {
+                    session.set_dir(1);
+                }
+                else if key == "ArrowDown" {
+                    session.set_dir(-1);
+                }
+                else if let Session::Live(live) = session {
+                    match &key[..] {
+                        " " | "Enter" => live.skip_instant_replay(),
+                        "Escape" => {
+                            // The page may well be closed from the pause menu, so whatever is left of the point is kept
+                            game.stats.borrow_mut().save().unwrap_or_else(|err| web_sys::console::warn_1(&format!("Could not save stats: {}", err).into()));
+                            return Transition::Push(Box::new(MenuScene::new(Screen::Pause)));
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Transition::Stay
+    }
+
+    fn update(&mut self, game: &mut Game, dt: f32) -> Transition<Game> {
+        // Step
+        game.session.borrow_mut().advance(dt);
+
+        // Network
+        if let (Session::Online(online), Some(socket)) = (&mut *game.session.borrow_mut(), game.socket.borrow().as_ref()) {
+            for message in online.take_outgoing() {
+                socket.send_with_str(&message.to_json()).unwrap_or_else(|_| web_sys::console::warn_1(&"Could not send input to the server".into()));
+            }
+        }
+
+        // Sound and effects, which follow whatever world is shown, like an instant replay
+        {
+            let mut session = game.session.borrow_mut();
+            let events = if let Session::Live(live) = &mut *session { live.take_events() } else { Vec::new() };
+            let world = session.get_displayed_world();
+            let speed = world.get_balls().iter().map(|ball| ball.get_speed()).fold(0.0, f32::max);
+            game.mixer.play_events(&events, speed)
+                .unwrap_or_else(|err| web_sys::console::warn_1(&format!("Could not play sound: {}", err).into()));
+            game.effects.update(world, &events, dt);
+        }
+
+        if let Session::Live(live) = &mut *game.session.borrow_mut() {
+            // Music, the same for every match with the same seed, until the match is over
+            let seed = live.get_world().get_seed();
+            if game.music.as_ref().is_none_or(|music| music.get_seed() != seed) {
+                game.music = Some(Sequencer::new(seed));
+            }
+            let playing = game.menu.get_settings().music && !live.is_over();
+            if let Some(music) = game.music.as_mut().filter(|_| playing) {
+                music.set_rally(live.get_rally());
+                game.mixer.play_music(&music.advance(dt))
+                    .unwrap_or_else(|err| web_sys::console::warn_1(&format!("Could not play music: {}", err).into()));
+            }
+
+            // Stats
+            let stats = live.take_stats();
+            game.stats.borrow_mut().record(&stats).unwrap_or_else(|err| web_sys::console::warn_1(&format!("Could not save stats: {}", err).into()));
+            // Only the stats of the tick that ended the match count it as played
+            if stats.matches_played > 0 && game.leaderboard.borrow().qualifies(live.get_mode(), live.get_score()) {
+                let entry = InitialsEntry::new(live.get_score(), live.get_mode(), live.get_world().get_seed());
+                return Transition::Push(Box::new(InitialsScene::new(entry)));
+            }
+
+            // Game over, once the instant replay of the last point is out of the way
+            if live.is_over() && !live.is_instant_replay() {
+                return Transition::Push(Box::new(MenuScene::new(Screen::GameOver { message: Self::game_over_message(live) })));
+            }
+        }
+
+        Transition::Stay
+    }
+
+    fn render(&mut self, game: &mut Game) {
+        let session = game.session.borrow();
+        let world = session.get_displayed_world();
+        render_world(game.renderer.as_mut(), &game.effects, world);
+
+        game.hud.set_scores(session.get_world().get_scores());
+        game.hud.set_scores_visible(true);
+        game.hud.set_instant_replay_visible(session.is_instant_replay());
+    }
+}
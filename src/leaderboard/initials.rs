@@ -0,0 +1,73 @@
+use crate::leaderboard::Entry;
+use crate::session::mode::Mode;
+
+/// Letters in a player's initials.
+pub const INITIALS_LEN: usize = 3;
+
+/// Arcade style entry of the initials for a score that made the leaderboard. Up and down change the letter under
+/// the cursor, left and right move the cursor, and letters can also be typed straight in.
+pub struct InitialsEntry {
+    score: u32,
+    mode: Mode,
+    seed: u64,
+    letters: [u8; INITIALS_LEN],
+    cursor: usize
+}
+
+impl InitialsEntry {
+    pub fn new(score: u32, mode: Mode, seed: u64) -> InitialsEntry {
+        InitialsEntry {
+            score,
+            mode,
+            seed,
+            letters: [b'A'; INITIALS_LEN],
+            cursor: 0
+        }
+    }
+
+    /// Moves the letter under the cursor forwards or backwards through the alphabet, wrapping around.
+    pub fn cycle(&mut self, forwards: bool) {
+        let letter = self.letters[self.cursor] - b'A';
+        let letter = if forwards { (letter + 1) % 26 } else { (letter + 25) % 26 };
+        self.letters[self.cursor] = b'A' + letter;
+    }
+
+    pub fn move_cursor(&mut self, right: bool) {
+        self.cursor = if right { (self.cursor + 1).min(INITIALS_LEN - 1) } else { self.cursor.saturating_sub(1) };
+    }
+
+    /// Puts `letter` under the cursor and moves on to the next one. Anything that is not a letter is ignored.
+    pub fn type_letter(&mut self, letter: char) {
+        if letter.is_ascii_alphabetic() {
+            self.letters[self.cursor] = letter.to_ascii_uppercase() as u8;
+            self.move_cursor(true);
+        }
+    }
+
+    pub fn get_initials(&self) -> String {
+        self.letters.iter().map(|letter| *letter as char).collect()
+    }
+
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The entry to submit to the leaderboard, dated `date` (in milliseconds since the Unix epoch).
+    pub fn finish(&self, date: u64) -> Entry {
+        Entry {
+            name: self.get_initials(),
+            score: self.score,
+            mode: self.mode,
+            date,
+            seed: self.seed
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::session::mode::Mode;
+use crate::storage::Storage;
+
+pub mod initials;
+
+/// Where `Leaderboard` keeps its entries.
+pub const LEADERBOARD_KEY: &str = "pong-leaderboard";
+
+/// Entries kept for each mode.
+pub const TOP_N: usize = 10;
+
+// Mixed into every checksum, so that a checksum cannot be worked out from the entry alone without reading the code
+const CHECKSUM_SALT: &[u8] = b"pong-leaderboard-v1";
+
+/// A score on the leaderboard.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The player's initials.
+    pub name: String,
+    pub score: u32,
+    pub mode: Mode,
+    /// Milliseconds since the Unix epoch.
+    pub date: u64,
+    /// The seed of the match, to play it again.
+    pub seed: u64
+}
+
+impl Entry {
+    // FNV-1a over the salt and the JSON form of the entry
+    fn checksum(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Entries are always representable as JSON");
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in CHECKSUM_SALT.iter().chain(json.iter()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+// How entries are stored
+#[derive(Serialize, Deserialize)]
+struct SignedEntry {
+    #[serde(flatten)]
+    entry: Entry,
+    checksum: String
+}
+
+/// The best scores of every mode with a leaderboard, kept in `storage`.
+///
+/// Every entry is stored with a checksum, so that entries edited by hand are noticed and dropped. This keeps honest
+/// people honest; anyone who reads the code can still forge an entry.
+pub struct Leaderboard {
+    storage: Box<dyn Storage>,
+    // Best first within each mode
+    entries: Vec<Entry>,
+    tampered: bool
+}
+
+impl Leaderboard {
+    /// A leaderboard that cannot be read is started over rather than getting in the way of playing.
+    pub fn new(storage: Box<dyn Storage>) -> Leaderboard {
+        let signed: Vec<SignedEntry> = match storage.load(LEADERBOARD_KEY) {
+            Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_default(),
+            _ => Vec::new()
+        };
+
+        let count = signed.len();
+        let mut entries: Vec<Entry> = signed.into_iter()
+            .filter(|signed| signed.checksum == signed.entry.checksum())
+            .map(|signed| signed.entry)
+            .collect();
+        let tampered = entries.len() != count;
+        entries.sort_by(|a, b| a.mode.cmp(&b.mode).then(b.score.cmp(&a.score)));
+
+        Leaderboard {
+            storage,
+            entries,
+            tampered
+        }
+    }
+
+    /// Whether any stored entry failed its checksum and was dropped.
+    pub fn is_tampered(&self) -> bool {
+        self.tampered
+    }
+
+    /// The best scores of `mode`, best first.
+    pub fn get_top(&self, mode: Mode) -> Vec<&Entry> {
+        self.entries.iter().filter(|entry| entry.mode == mode).take(TOP_N).collect()
+    }
+
+    /// Whether `score` would make the leaderboard of `mode`.
+    pub fn qualifies(&self, mode: Mode, score: u32) -> bool {
+        if !mode.has_leaderboard() || score == 0 {
+            return false;
+        }
+        let top = self.get_top(mode);
+        top.len() < TOP_N || top.last().is_none_or(|lowest| score > lowest.score)
+    }
+
+    /// Adds `entry` and saves the leaderboard, returning its rank from 0 if it made the leaderboard. An entry that
+    /// ties with another ranks below it.
+    pub fn submit(&mut self, entry: Entry) -> Result<Option<usize>, String> {
+        if !self.qualifies(entry.mode, entry.score) {
+            return Ok(None);
+        }
+
+        let mode = entry.mode;
+        let index = self.entries.iter()
+            .position(|other| other.mode > mode || (other.mode == mode && other.score < entry.score))
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+
+        // Only the top of each mode is kept
+        let mut kept = 0;
+        self.entries.retain(|other| {
+            if other.mode != mode {
+                return true;
+            }
+            kept += 1;
+            kept <= TOP_N
+        });
+
+        self.save()?;
+        let first = self.entries.iter().position(|other| other.mode == mode).unwrap_or(index);
+        Ok(Some(index - first))
+    }
+
+    /// Forgets every entry of every mode.
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.entries.clear();
+        self.tampered = false;
+        self.storage.remove(LEADERBOARD_KEY)
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let signed: Vec<SignedEntry> = self.entries.iter()
+            .map(|entry| SignedEntry { entry: entry.clone(), checksum: entry.checksum() })
+            .collect();
+        let json = serde_json::to_string(&signed).expect("Entries are always representable as JSON");
+        self.storage.save(LEADERBOARD_KEY, &json)
+    }
+}
@@ -0,0 +1,450 @@
+use crate::render::overlay::Overlay;
+use crate::render::post::PostEffects;
+use crate::session::mode::{ENDLESS_LIVES, Mode};
+use crate::world::ai::Difficulty;
+
+pub mod settings;
+use settings::{MAX_POINTS_TO_WIN, MAX_VOLUME, Settings};
+
+/// A screen of the menus.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Screen {
+    Title,
+    ModeSelect,
+    DifficultySelect,
+    Settings,
+    Controls,
+    /// Over a match that is on hold.
+    Pause,
+    /// Over a match that is over, with what became of it.
+    GameOver { message: String }
+}
+
+impl Screen {
+    /// Whether the screen hides the world behind it. The others are drawn over the match.
+    pub fn is_opaque(&self) -> bool {
+        !matches!(self, Screen::Pause | Screen::GameOver { .. })
+    }
+}
+
+/// What the menus are driven with, whichever device it came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back
+}
+
+/// What the host has to do after an input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuCommand {
+    /// Start a new match with the settings. The menus are closed.
+    Start,
+    /// Carry on with the match on hold. The menus are closed.
+    Resume,
+    /// Save the settings, and show the theme if it changed.
+    SettingsChanged
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Item {
+    Play,
+    Settings,
+    Controls,
+    Mode(Mode),
+    Difficulty(Difficulty),
+    PointsToWin,
+    InstantReplay,
+    Theme,
+    Crt,
+    Volume,
+    SfxVolume,
+    Music,
+    MusicVolume,
+    Sound,
+    ReducedMotion,
+    Resume,
+    Restart,
+    MainMenu,
+    Back
+}
+
+/// How many times larger than the other lines of a screen its title is.
+pub const TITLE_SIZE: f32 = 3.0;
+
+// Lines of text on a screen, all in font pixels of the size `Menu::font_size` picks for the world
+const LINE_HEIGHT: f32 = 12.0;
+
+/// The menus, as a stack of screens: going into a screen pushes it and going back pops it, and the stack is empty
+/// while playing. Every screen remembers which of its items is selected.
+pub struct Menu {
+    stack: Vec<(Screen, usize)>,
+    settings: Settings
+}
+
+impl Menu {
+    /// Starts out closed, for `push` to open.
+    pub fn new(settings: Settings) -> Menu {
+        Menu {
+            stack: Vec::new(),
+            settings
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.stack.is_empty()
+    }
+
+    pub fn get_screen(&self) -> Option<&Screen> {
+        self.stack.last().map(|(screen, _)| screen)
+    }
+
+    pub fn push(&mut self, screen: Screen) {
+        self.stack.push((screen, 0));
+    }
+
+    /// Closes every screen.
+    pub fn close(&mut self) {
+        self.stack.clear();
+    }
+
+    pub fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// For settings changed outside of the menus, like muting with a shortcut.
+    pub fn get_settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    pub fn input(&mut self, input: MenuInput) -> Option<MenuCommand> {
+        let items = self.items();
+        let (screen, selected) = self.stack.last_mut()?;
+        let screen = screen.clone();
+
+        match input {
+            MenuInput::Up => {
+                *selected = (*selected + items.len() - 1) % items.len();
+                None
+            },
+            MenuInput::Down => {
+                *selected = (*selected + 1) % items.len();
+                None
+            },
+            MenuInput::Left | MenuInput::Right => {
+                let item = items[*selected];
+                self.adjust(item, input == MenuInput::Right)
+            },
+            MenuInput::Confirm => {
+                let item = items[*selected];
+                self.confirm(item)
+            },
+            MenuInput::Back => match screen {
+                Screen::Title | Screen::GameOver { .. } => None,
+                _ => self.confirm(Item::Back)
+            }
+        }
+    }
+
+    /// Selects the item under `x` and `y` in world coordinates, if there is one, as when the pointer moves over it.
+    pub fn point(&mut self, width: f32, height: f32, x: f32, y: f32) -> bool {
+        let index = self.item_at(width, height, x, y);
+        if let (Some(index), Some((_, selected))) = (index, self.stack.last_mut()) {
+            *selected = index;
+        }
+        index.is_some()
+    }
+
+    /// Confirms the item under `x` and `y` in world coordinates, if there is one, as when it is clicked or tapped.
+    pub fn click(&mut self, width: f32, height: f32, x: f32, y: f32) -> Option<MenuCommand> {
+        if self.point(width, height, x, y) {
+            self.input(MenuInput::Confirm)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Draws the screen on top of the stack into `overlay`, scaled to a world of `width` by `height`.
+    pub fn draw(&self, width: f32, height: f32, overlay: &mut Overlay) {
+        let (screen, selected) = match self.stack.last() {
+            Some(top) => top,
+            None => return
+        };
+        let size = Self::font_size(width, height);
+
+        overlay.text(Self::title(screen), width/2.0, height*0.8, size*TITLE_SIZE, false);
+        let lines = Self::lines(screen);
+        for (i, line) in lines.iter().enumerate() {
+            overlay.text(line, width/2.0, height*0.8 - (i as f32 + 2.5)*size*LINE_HEIGHT, size, false);
+        }
+
+        for (i, item) in self.items().iter().enumerate() {
+            let label = self.label(*item);
+            let label = if i == *selected { format!("> {} <", label) } else { label };
+            overlay.text(&label, width/2.0, Self::item_y(&lines, i, size, height), size, i == *selected);
+        }
+    }
+
+    fn item_at(&self, width: f32, height: f32, x: f32, y: f32) -> Option<usize> {
+        let (screen, _) = self.stack.last()?;
+        let size = Self::font_size(width, height);
+        let lines = Self::lines(screen);
+
+        self.items().iter().enumerate().position(|(i, item)| {
+            let half_width = Overlay::text_width(&format!("> {} <", self.label(*item)), size)/2.0;
+            (x - width/2.0).abs() <= half_width && (y - Self::item_y(&lines, i, size, height)).abs() <= size*LINE_HEIGHT/2.0
+        })
+    }
+
+    /// The size of the font pixels of the menus in a world of `width` by `height`. Font pixels are kept whole numbers
+    /// of screen pixels so that the text stays crisp, the longest lines (about 40 characters) fit across the world,
+    /// and the longest screen (the settings) fits down it.
+    pub fn font_size(width: f32, height: f32) -> f32 {
+        (height/220.0).min(width/300.0).floor().max(1.0)
+    }
+
+    fn item_y(lines: &[String], index: usize, size: f32, height: f32) -> f32 {
+        let first = if lines.is_empty() { 2.5 } else { lines.len() as f32 + 3.5 };
+        height*0.8 - (first + index as f32)*size*LINE_HEIGHT
+    }
+
+    fn title(screen: &Screen) -> &'static str {
+        match screen {
+            Screen::Title => "PONG",
+            Screen::ModeSelect => "MODE",
+            Screen::DifficultySelect => "DIFFICULTY",
+            Screen::Settings => "SETTINGS",
+            Screen::Controls => "CONTROLS",
+            Screen::Pause => "PAUSED",
+            Screen::GameOver { .. } => "GAME OVER"
+        }
+    }
+
+    fn lines(screen: &Screen) -> Vec<String> {
+        match screen {
+            Screen::ModeSelect => vec![
+                String::from("VERSUS: FIRST TO THE POINTS TO WIN"),
+                format!("ENDLESS: SCORE BEFORE THE AI SCORES {}", ENDLESS_LIVES),
+                String::from("SURVIVAL: LAST UNTIL THE AI SCORES")
+            ],
+            Screen::Controls => vec![
+                String::from("UP / DOWN: MOVE YOUR PADDLE"),
+                String::from("SPACE / ENTER: SKIP AN INSTANT REPLAY"),
+                String::from("ESCAPE: PAUSE"),
+                String::from("M: SOUND ON OR OFF"),
+                String::from("GAMEPADS: D-PAD OR STICK, A, B AND START"),
+                String::from("TOUCH AND MOUSE: TAP OR CLICK MENU ITEMS")
+            ],
+            Screen::GameOver { message } => vec![message.to_uppercase()],
+            _ => Vec::new()
+        }
+    }
+
+    fn items(&self) -> Vec<Item> {
+        match self.get_screen() {
+            Some(Screen::Title) => vec![Item::Play, Item::Settings, Item::Controls],
+            Some(Screen::ModeSelect) => vec![Item::Mode(Mode::Versus), Item::Mode(Mode::Endless), Item::Mode(Mode::Survival), Item::Mode(Mode::MultiBall), Item::Back],
+            Some(Screen::DifficultySelect) => vec![
+                Item::Difficulty(Difficulty::Easy), Item::Difficulty(Difficulty::Normal), Item::Difficulty(Difficulty::Hard), Item::Back
+            ],
+            Some(Screen::Settings) => vec![
+                Item::PointsToWin, Item::InstantReplay, Item::Theme, Item::Crt, Item::Volume, Item::SfxVolume, Item::Music, Item::MusicVolume,
+                Item::Sound, Item::ReducedMotion, Item::Back
+            ],
+            Some(Screen::Controls) => vec![Item::Back],
+            Some(Screen::Pause) => vec![Item::Resume, Item::Restart, Item::MainMenu],
+            Some(Screen::GameOver { .. }) => vec![Item::Restart, Item::MainMenu],
+            None => Vec::new()
+        }
+    }
+
+    fn label(&self, item: Item) -> String {
+        match item {
+            Item::Play => String::from("PLAY"),
+            Item::Settings => String::from("SETTINGS"),
+            Item::Controls => String::from("CONTROLS"),
+            Item::Mode(mode) => format!("{:?}", mode).to_uppercase(),
+            Item::Difficulty(difficulty) => format!("{:?}", difficulty).to_uppercase(),
+            Item::PointsToWin => match self.settings.points_to_win {
+                0 => String::from("POINTS TO WIN: ENDLESS"),
+                points => format!("POINTS TO WIN: {}", points)
+            },
+            Item::InstantReplay => format!("INSTANT REPLAY: {}", if self.settings.instant_replay { "ON" } else { "OFF" }),
+            Item::Theme => format!("THEME: {}", self.settings.theme.get_name().to_uppercase()),
+            Item::Crt => format!("CRT: {}", if self.settings.post_effects.any() { "ON" } else { "OFF" }),
+            Item::Volume => format!("MASTER VOLUME: {}", self.settings.volume),
+            Item::SfxVolume => format!("EFFECTS VOLUME: {}", self.settings.sfx_volume),
+            Item::Music => format!("MUSIC: {}", if self.settings.music { "ON" } else { "OFF" }),
+            Item::MusicVolume => format!("MUSIC VOLUME: {}", self.settings.music_volume),
+            Item::Sound => format!("SOUND: {}", if self.settings.muted { "OFF" } else { "ON" }),
+            Item::ReducedMotion => format!("REDUCED MOTION: {}", if self.settings.reduced_motion { "ON" } else { "OFF" }),
+            Item::Resume => String::from("RESUME"),
+            Item::Restart => match self.get_screen() {
+                Some(Screen::GameOver { .. }) => String::from("REMATCH"),
+                _ => String::from("RESTART")
+            },
+            Item::MainMenu => String::from("MAIN MENU"),
+            Item::Back => String::from("BACK")
+        }
+    }
+
+    // Left and right change settings
+    fn adjust(&mut self, item: Item, up: bool) -> Option<MenuCommand> {
+        let settings = &mut self.settings;
+        match item {
+            Item::PointsToWin => {
+                settings.points_to_win = if up { (settings.points_to_win + 1) % (MAX_POINTS_TO_WIN + 1) } else { (settings.points_to_win + MAX_POINTS_TO_WIN) % (MAX_POINTS_TO_WIN + 1) };
+            },
+            Item::InstantReplay => settings.instant_replay = !settings.instant_replay,
+            Item::Theme => settings.theme = settings.theme.cycle(up),
+            // Every effect at once, the page can turn them on and off one by one
+            Item::Crt => settings.post_effects = if settings.post_effects.any() { PostEffects::default() } else { PostEffects::all() },
+            Item::Volume => {
+                settings.volume = if up { (settings.volume + 1).min(MAX_VOLUME) } else { settings.volume.saturating_sub(1) };
+            },
+            Item::SfxVolume => {
+                settings.sfx_volume = if up { (settings.sfx_volume + 1).min(MAX_VOLUME) } else { settings.sfx_volume.saturating_sub(1) };
+            },
+            Item::Music => settings.music = !settings.music,
+            Item::MusicVolume => {
+                settings.music_volume = if up { (settings.music_volume + 1).min(MAX_VOLUME) } else { settings.music_volume.saturating_sub(1) };
+            },
+            Item::Sound => settings.muted = !settings.muted,
+            Item::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            _ => return None
+        }
+        Some(MenuCommand::SettingsChanged)
+    }
+
+    fn confirm(&mut self, item: Item) -> Option<MenuCommand> {
+        match item {
+            Item::Play => self.push(Screen::ModeSelect),
+            Item::Settings => self.push(Screen::Settings),
+            Item::Controls => self.push(Screen::Controls),
+            Item::Mode(mode) => {
+                self.settings.mode = mode;
+                self.push(Screen::DifficultySelect);
+                return Some(MenuCommand::SettingsChanged);
+            },
+            Item::Difficulty(difficulty) => {
+                self.settings.difficulty = difficulty;
+                self.close();
+                return Some(MenuCommand::Start);
+            },
+            // Volumes wrap around when confirmed, so that they can be set with a single button
+            Item::Volume if self.settings.volume == MAX_VOLUME => {
+                self.settings.volume = 0;
+                return Some(MenuCommand::SettingsChanged);
+            },
+            Item::SfxVolume if self.settings.sfx_volume == MAX_VOLUME => {
+                self.settings.sfx_volume = 0;
+                return Some(MenuCommand::SettingsChanged);
+            },
+            Item::MusicVolume if self.settings.music_volume == MAX_VOLUME => {
+                self.settings.music_volume = 0;
+                return Some(MenuCommand::SettingsChanged);
+            },
+            Item::PointsToWin | Item::InstantReplay | Item::Theme | Item::Crt | Item::Volume | Item::SfxVolume | Item::Music | Item::MusicVolume | Item::Sound
+            | Item::ReducedMotion => {
+                return self.adjust(item, true);
+            },
+            Item::Resume => {
+                self.close();
+                return Some(MenuCommand::Resume);
+            },
+            Item::Restart => {
+                self.close();
+                return Some(MenuCommand::Start);
+            },
+            Item::MainMenu => {
+                self.close();
+                self.push(Screen::Title);
+            },
+            Item::Back => {
+                self.stack.pop();
+                if self.stack.is_empty() {
+                    return Some(MenuCommand::Resume);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu_at(screen: Screen) -> Menu {
+        let mut menu = Menu::new(Settings::new());
+        menu.push(screen);
+        menu
+    }
+
+    fn press(menu: &mut Menu, inputs: &[MenuInput]) -> Option<MenuCommand> {
+        inputs.iter().map(|input| menu.input(*input)).last().flatten()
+    }
+
+    #[test]
+    fn starts_a_match_from_the_title_through_mode_and_difficulty() {
+        let mut menu = menu_at(Screen::Title);
+        assert_eq!(menu.input(MenuInput::Confirm), None);
+        assert_eq!(menu.get_screen(), Some(&Screen::ModeSelect));
+
+        assert_eq!(press(&mut menu, &[MenuInput::Down, MenuInput::Confirm]), Some(MenuCommand::SettingsChanged));
+        assert_eq!(menu.get_screen(), Some(&Screen::DifficultySelect));
+        assert_eq!(menu.get_settings().mode, Mode::Endless);
+
+        assert_eq!(press(&mut menu, &[MenuInput::Down, MenuInput::Down, MenuInput::Confirm]), Some(MenuCommand::Start));
+        assert!(!menu.is_open());
+        assert_eq!(menu.get_settings().difficulty, Difficulty::Hard);
+    }
+
+    #[test]
+    fn goes_back_a_screen_at_a_time_down_to_the_title() {
+        let mut menu = menu_at(Screen::Title);
+        press(&mut menu, &[MenuInput::Confirm, MenuInput::Confirm]);
+        assert_eq!(menu.get_screen(), Some(&Screen::DifficultySelect));
+
+        assert_eq!(menu.input(MenuInput::Back), None);
+        assert_eq!(menu.get_screen(), Some(&Screen::ModeSelect));
+        assert_eq!(menu.input(MenuInput::Back), None);
+        assert_eq!(menu.get_screen(), Some(&Screen::Title));
+        // There is nothing behind the title
+        assert_eq!(menu.input(MenuInput::Back), None);
+        assert_eq!(menu.get_screen(), Some(&Screen::Title));
+    }
+
+    #[test]
+    fn keeps_volumes_in_range_and_wraps_points_to_win() {
+        let mut menu = menu_at(Screen::Settings);
+        // Points to win are first, going round through endless
+        menu.get_settings_mut().points_to_win = MAX_POINTS_TO_WIN;
+        assert_eq!(menu.input(MenuInput::Right), Some(MenuCommand::SettingsChanged));
+        assert_eq!(menu.get_settings().points_to_win, 0);
+        menu.input(MenuInput::Left);
+        assert_eq!(menu.get_settings().points_to_win, MAX_POINTS_TO_WIN);
+
+        // Then the master volume, four items down
+        press(&mut menu, &[MenuInput::Down; 4]);
+        press(&mut menu, &[MenuInput::Right; MAX_VOLUME as usize + 2]);
+        assert_eq!(menu.get_settings().volume, MAX_VOLUME);
+        press(&mut menu, &[MenuInput::Left; MAX_VOLUME as usize + 2]);
+        assert_eq!(menu.get_settings().volume, 0);
+    }
+
+    #[test]
+    fn resumes_from_the_pause_menu() {
+        let mut menu = menu_at(Screen::Pause);
+        assert_eq!(menu.input(MenuInput::Confirm), Some(MenuCommand::Resume));
+        assert!(!menu.is_open());
+
+        let mut menu = menu_at(Screen::Pause);
+        assert_eq!(menu.input(MenuInput::Back), Some(MenuCommand::Resume));
+        assert!(!menu.is_open());
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::render::post::PostEffects;
+use crate::render::theme::Theme;
+use crate::session::mode::Mode;
+use crate::storage::Storage;
+use crate::world::ai::Difficulty;
+
+/// Where `Settings` are kept.
+pub const SETTINGS_KEY: &str = "pong-settings";
+
+/// Loudest setting of both volumes.
+pub const MAX_VOLUME: u8 = 10;
+
+/// Highest points to win the menu goes up to.
+pub const MAX_POINTS_TO_WIN: u32 = 21;
+
+/// Everything the player can choose in the menus, remembered between visits.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mode: Mode,
+    pub difficulty: Difficulty,
+    /// For versus matches, 0 plays forever.
+    pub points_to_win: u32,
+    pub instant_replay: bool,
+    pub theme: Theme,
+    /// The master volume, from 0 (silent) to `MAX_VOLUME`.
+    pub volume: u8,
+    /// The volume of the sound effects under the master volume, from 0 (silent) to `MAX_VOLUME`.
+    pub sfx_volume: u8,
+    /// Background music, off unless turned on.
+    pub music: bool,
+    /// The volume of the music under the master volume, from 0 (silent) to `MAX_VOLUME`.
+    pub music_volume: u8,
+    pub muted: bool,
+    /// Turns off the trail, screen shake, flashes and particles.
+    pub reduced_motion: bool,
+    /// The CRT look, off unless turned on.
+    pub post_effects: PostEffects
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            mode: Mode::default(),
+            difficulty: Difficulty::default(),
+            points_to_win: 5,
+            instant_replay: false,
+            theme: Theme::default(),
+            volume: MAX_VOLUME / 2,
+            sfx_volume: MAX_VOLUME,
+            music: false,
+            music_volume: MAX_VOLUME / 2,
+            muted: false,
+            reduced_motion: false,
+            post_effects: PostEffects::default()
+        }
+    }
+
+    /// Settings that cannot be read are replaced with the defaults rather than getting in the way of playing.
+    pub fn load(storage: &dyn Storage) -> Settings {
+        let mut settings: Settings = match storage.load(SETTINGS_KEY) {
+            Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_default(),
+            _ => Settings::new()
+        };
+        settings.volume = settings.volume.min(MAX_VOLUME);
+        settings.sfx_volume = settings.sfx_volume.min(MAX_VOLUME);
+        settings.music_volume = settings.music_volume.min(MAX_VOLUME);
+        settings.points_to_win = settings.points_to_win.min(MAX_POINTS_TO_WIN);
+        settings
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> Result<(), String> {
+        storage.save(SETTINGS_KEY, &serde_json::to_string(self).expect("Settings are always representable as JSON"))
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
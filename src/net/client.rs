@@ -0,0 +1,52 @@
+use std::io;
+use std::net::TcpStream;
+
+use tungstenite::{Message, WebSocket};
+
+use crate::net::{ClientMessage, ServerMessage};
+
+/// A nonblocking connection to a match server, for native clients.
+pub struct Connection {
+    socket: WebSocket<TcpStream>
+}
+
+impl Connection {
+    /// Connects to a `ws://` URL, blocking until the handshake is done.
+    pub fn connect(url: &str) -> io::Result<Connection> {
+        let host = url.trim_start_matches("ws://").split('/').next().unwrap_or_default();
+        let stream = TcpStream::connect(host)?;
+        stream.set_nodelay(true)?;
+        let (socket, _) = tungstenite::client(url, stream).map_err(io::Error::other)?;
+        socket.get_ref().set_nonblocking(true)?;
+
+        Ok(Connection { socket })
+    }
+
+    /// The next message from the server, or `None` when there is nothing to read right now.
+    pub fn receive(&mut self) -> io::Result<Option<ServerMessage>> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => return ServerMessage::from_json(&text).map(Some).map_err(io::Error::other),
+                Ok(Message::Close(_)) => return Err(io::ErrorKind::ConnectionAborted.into()),
+                Ok(_) => {},
+                Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(tungstenite::Error::Io(err)) => return Err(err),
+                Err(err) => return Err(io::Error::other(err))
+            }
+        }
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        match self.socket.send(Message::text(message.to_json())) {
+            Ok(()) => Ok(()),
+            // Flushed with the next message
+            Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(io::Error::other(err))
+        }
+    }
+
+    pub fn close(mut self) {
+        let _ = self.socket.close(None);
+        let _ = self.socket.flush();
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use crate::net::{ClientMessage, GameConfig, ServerMessage};
+
+/// Letters room codes are made of, leaving out the ones easily mistaken for each other.
+pub const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+pub const ROOM_CODE_LENGTH: usize = 4;
+
+pub type ClientId = u64;
+
+/// Two players from the same room who are both ready, to be taken out of the lobby and into a match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchStart {
+    pub code: String,
+    pub players: [ClientId; 2],
+    pub config: GameConfig
+}
+
+struct Room {
+    config: GameConfig,
+    // In seat order, with whether each is ready
+    players: Vec<(ClientId, bool)>
+}
+
+/// Players setting up matches in rooms.
+///
+/// The lobby never touches the network itself: the host assigns every connection an id with `connect`, passes
+/// on what it sends through `receive`, sends what `take_outgoing` returns, and starts the matches `take_started`
+/// returns with the connections of both players.
+pub struct Lobby {
+    rng: Pcg32,
+    next_client: ClientId,
+    // The room each client is in, if any
+    clients: HashMap<ClientId, Option<String>>,
+    rooms: HashMap<String, Room>,
+
+    outgoing: Vec<(ClientId, ServerMessage)>,
+    started: Vec<MatchStart>
+}
+
+impl Lobby {
+    /// Room codes are drawn from `seed`.
+    pub fn new(seed: u64) -> Lobby {
+        Lobby {
+            rng: Pcg32::seed_from_u64(seed),
+            next_client: 0,
+            clients: HashMap::new(),
+            rooms: HashMap::new(),
+
+            outgoing: Vec::new(),
+            started: Vec::new()
+        }
+    }
+
+    pub fn connect(&mut self) -> ClientId {
+        let client = self.next_client;
+        self.next_client += 1;
+        self.clients.insert(client, None);
+        client
+    }
+
+    /// Forgets a client that left, along with its seat.
+    pub fn disconnect(&mut self, client: ClientId) {
+        self.leave_room(client);
+        self.clients.remove(&client);
+    }
+
+    pub fn receive(&mut self, client: ClientId, message: ClientMessage) {
+        let code = match self.clients.get(&client) {
+            Some(code) => code.clone(),
+            None => return
+        };
+
+        let result = match (message, code) {
+            (ClientMessage::CreateRoom { config }, None) => self.create_room(client, config),
+            (ClientMessage::JoinRoom { code }, None) => self.join_room(client, &code.to_uppercase()),
+            (ClientMessage::CreateRoom { .. }, Some(_)) | (ClientMessage::JoinRoom { .. }, Some(_)) => Err(String::from("Already in a room")),
+            (ClientMessage::SetConfig { config }, Some(code)) => self.set_config(client, &code, config),
+            (ClientMessage::SetReady { ready }, Some(code)) => {
+                self.set_ready(client, &code, ready);
+                Ok(())
+            },
+            (ClientMessage::LeaveRoom, Some(_)) => {
+                self.leave_room(client);
+                self.outgoing.push((client, ServerMessage::LeftRoom));
+                Ok(())
+            },
+            (ClientMessage::Inputs { .. }, _) => Err(String::from("No match is running")),
+            (_, None) => Err(String::from("Not in a room"))
+        };
+
+        if let Err(message) = result {
+            self.outgoing.push((client, ServerMessage::LobbyError { message }));
+        }
+    }
+
+    /// Messages for clients, in the order they should be sent.
+    pub fn take_outgoing(&mut self) -> Vec<(ClientId, ServerMessage)> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    /// Matches that are ready to start. Their players have left the lobby.
+    pub fn take_started(&mut self) -> Vec<MatchStart> {
+        std::mem::take(&mut self.started)
+    }
+
+    pub fn get_client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn get_room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    /// The room `client` is in, if any.
+    pub fn get_room_code(&self, client: ClientId) -> Option<&str> {
+        self.clients.get(&client).and_then(|code| code.as_deref())
+    }
+
+    fn create_room(&mut self, client: ClientId, config: GameConfig) -> Result<(), String> {
+        config.validate()?;
+
+        let code = loop {
+            let code: String = (0..ROOM_CODE_LENGTH).map(|_| *ROOM_CODE_ALPHABET.choose(&mut self.rng).unwrap() as char).collect();
+            if !self.rooms.contains_key(&code) {
+                break code;
+            }
+        };
+
+        self.rooms.insert(code.clone(), Room { config, players: vec![(client, false)] });
+        self.clients.insert(client, Some(code.clone()));
+        self.send_room(&code);
+        Ok(())
+    }
+
+    fn join_room(&mut self, client: ClientId, code: &str) -> Result<(), String> {
+        let room = self.rooms.get_mut(code).ok_or_else(|| format!("No room with code {}", code))?;
+        if room.players.len() == 2 {
+            return Err(format!("Room {} is full", code));
+        }
+
+        room.players.push((client, false));
+        self.clients.insert(client, Some(code.to_string()));
+        self.send_room(code);
+        Ok(())
+    }
+
+    fn set_config(&mut self, client: ClientId, code: &str, config: GameConfig) -> Result<(), String> {
+        config.validate()?;
+        let room = self.rooms.get_mut(code).unwrap();
+        if room.players[0].0 != client {
+            return Err(String::from("Only the creator of the room can change its config"));
+        }
+
+        room.config = config;
+        for (_, ready) in room.players.iter_mut() {
+            *ready = false;
+        }
+        self.send_room(code);
+        Ok(())
+    }
+
+    fn set_ready(&mut self, client: ClientId, code: &str, ready: bool) {
+        let room = self.rooms.get_mut(code).unwrap();
+        for (player, player_ready) in room.players.iter_mut() {
+            if *player == client {
+                *player_ready = ready;
+            }
+        }
+
+        if room.players.len() == 2 && room.players.iter().all(|(_, ready)| *ready) {
+            let room = self.rooms.remove(code).unwrap();
+            let players = [room.players[0].0, room.players[1].0];
+            for player in players.iter() {
+                self.clients.remove(player);
+            }
+            self.started.push(MatchStart { code: code.to_string(), players, config: room.config });
+        }
+        else {
+            self.send_room(code);
+        }
+    }
+
+    fn leave_room(&mut self, client: ClientId) {
+        let code = match self.clients.get_mut(&client).and_then(|code| code.take()) {
+            Some(code) => code,
+            None => return
+        };
+
+        let room = self.rooms.get_mut(&code).unwrap();
+        room.players.retain(|(player, _)| *player != client);
+        if room.players.is_empty() {
+            self.rooms.remove(&code);
+        }
+        else {
+            // Whoever is left waits for someone new
+            room.players[0].1 = false;
+            self.send_room(&code);
+        }
+    }
+
+    fn send_room(&mut self, code: &str) {
+        let room = &self.rooms[code];
+        let ready: Vec<bool> = room.players.iter().map(|(_, ready)| *ready).collect();
+        for (seat, (player, _)) in room.players.iter().enumerate() {
+            let message = ServerMessage::Room { code: code.to_string(), config: room.config, ready: ready.clone(), seat };
+            self.outgoing.push((*player, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+
+    use super::*;
+
+    const SEED: u64 = 7;
+    const STEPS: u64 = 20_000;
+    const MAX_CLIENTS: usize = 100;
+
+    // What a simulated client last heard about its room
+    #[derive(Clone)]
+    struct RoomView {
+        code: String,
+        config: GameConfig,
+        ready: Vec<bool>,
+        seat: usize
+    }
+
+    // Many simulated clients join, leave and ready up at random, and after every step what each client was told
+    // has to match the lobby
+    #[test]
+    fn tells_every_client_the_truth_about_its_room() {
+        let mut rng = Pcg32::seed_from_u64(SEED);
+        let mut lobby = Lobby::new(rng.gen());
+        // Ordered so that a seed always plays out the same
+        let mut views: BTreeMap<ClientId, Option<RoomView>> = BTreeMap::new();
+        let mut gone: HashSet<ClientId> = HashSet::new();
+        // Every room code handed out, to try joining
+        let mut codes: Vec<String> = Vec::new();
+        let mut seen_codes: HashSet<String> = HashSet::new();
+
+        let mut matches = 0;
+
+        for _ in 0..STEPS {
+            // Act
+            let clients: Vec<ClientId> = views.keys().copied().collect();
+            if clients.is_empty() || (clients.len() < MAX_CLIENTS && rng.gen_bool(0.1)) {
+                views.insert(lobby.connect(), None);
+            }
+            else {
+                let client = *clients.choose(&mut rng).unwrap();
+                let message = match rng.gen_range(0, 12) {
+                    0 => {
+                        lobby.disconnect(client);
+                        views.remove(&client);
+                        gone.insert(client);
+                        None
+                    },
+                    1 | 2 => Some(ClientMessage::CreateRoom { config: random_config(&mut rng) }),
+                    3..=5 => {
+                        // Mostly rooms that are open, some that are long gone or never were
+                        let open: Vec<&String> = views.values().flatten().map(|view| &view.code).collect();
+                        let code = match (open.choose(&mut rng), codes.choose(&mut rng)) {
+                            (Some(code), _) if rng.gen_bool(0.7) => code.to_lowercase(),
+                            (_, Some(code)) if rng.gen_bool(0.7) => code.clone(),
+                            _ => String::from("ZZZZ")
+                        };
+                        Some(ClientMessage::JoinRoom { code })
+                    },
+                    6..=8 => Some(ClientMessage::SetReady { ready: rng.gen_bool(0.8) }),
+                    9 => Some(ClientMessage::SetConfig { config: random_config(&mut rng) }),
+                    10 => Some(ClientMessage::LeaveRoom),
+                    _ => Some(ClientMessage::Inputs { first_seq: 1, dirs: vec![1] })
+                };
+                if let Some(message) = message {
+                    lobby.receive(client, message);
+                }
+            }
+
+            // Hear back
+            for (client, message) in lobby.take_outgoing() {
+                assert!(!gone.contains(&client), "Client {} was sent {:?} after leaving", client, message);
+                let view = views.get_mut(&client).expect("Message for a client that never connected");
+                match message {
+                    ServerMessage::Room { code, config, ready, seat } => {
+                        assert_eq!(code.len(), ROOM_CODE_LENGTH);
+                        assert!(code.bytes().all(|letter| ROOM_CODE_ALPHABET.contains(&letter)), "Bad room code {}", code);
+                        assert!(seat < ready.len() && ready.len() <= 2);
+                        if seen_codes.insert(code.clone()) {
+                            codes.push(code.clone());
+                        }
+                        *view = Some(RoomView { code, config, ready, seat });
+                    },
+                    ServerMessage::LeftRoom => *view = None,
+                    ServerMessage::LobbyError { .. } => {},
+                    message => panic!("Unexpected message {:?}", message)
+                }
+            }
+
+            for start in lobby.take_started() {
+                assert_ne!(start.players[0], start.players[1]);
+                for (seat, player) in start.players.iter().enumerate() {
+                    let view = views.remove(player).expect("Match started for a client that is not in the lobby");
+                    let view = view.expect("Match started for a client that was never told about its room");
+                    assert_eq!(view.code, start.code);
+                    assert_eq!(view.seat, seat);
+                    assert_eq!(view.config, start.config, "Match started with a config a player has not seen");
+                    gone.insert(*player);
+                }
+                matches += 1;
+            }
+
+            // Everyone's view matches the lobby
+            for (client, view) in views.iter() {
+                assert_eq!(view.as_ref().map(|view| view.code.as_str()), lobby.get_room_code(*client), "Client {} is confused about its room", client);
+            }
+            let mut rooms: HashMap<&str, Vec<&RoomView>> = HashMap::new();
+            for view in views.values().flatten() {
+                rooms.entry(&view.code).or_default().push(view);
+            }
+            assert_eq!(rooms.len(), lobby.get_room_count(), "Rooms nobody is in");
+            for (code, seated) in rooms.iter() {
+                assert!(seated.iter().all(|view| view.ready.len() == seated.len()), "Room {} has a stale player list", code);
+            }
+            assert_eq!(views.len(), lobby.get_client_count());
+        }
+        assert!(matches > 0, "No match ever started");
+    }
+
+    fn random_config(rng: &mut Pcg32) -> GameConfig {
+        GameConfig {
+            width: rng.gen_range(50.0, 2000.0),
+            height: rng.gen_range(50.0, 2000.0),
+            points_to_win: rng.gen_range(0, 120),
+            seed: if rng.gen() { Some(rng.gen()) } else { None }
+        }
+    }
+}
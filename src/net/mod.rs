@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use crate::world::{Side, snapshot::Snapshot};
+
+#[cfg(feature = "net")]
+pub mod client;
+pub mod lobby;
+#[cfg(feature = "net")]
+pub mod relay;
+#[cfg(feature = "net")]
+pub mod server;
+
+pub const DEFAULT_PORT: u16 = 9001;
+
+/// Connections to this path on a match server watch a match instead of playing one.
+pub const SPECTATE_PATH: &str = "/spectate";
+/// Connections to this path on a match server go to the lobby, where matches are set up in rooms.
+pub const LOBBY_PATH: &str = "/lobby";
+
+/// Size of the arena in online matches, unless a room agreed on another.
+pub const ARENA_WIDTH: f32 = 960.0;
+pub const ARENA_HEIGHT: f32 = 600.0;
+
+/// How an online match is played, agreed on by both players in a lobby room.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub width: f32,
+    pub height: f32,
+    /// The match ends when either player reaches this score, or never when it is 0.
+    pub points_to_win: u32,
+    /// Pins the match seed, which is picked at random otherwise.
+    pub seed: Option<u64>
+}
+
+impl GameConfig {
+    /// Checks that a config sent by a client is playable.
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_size = |size: f32| (100.0..=4000.0).contains(&size);
+        if !valid_size(self.width) || !valid_size(self.height) {
+            return Err(format!("Arena of {}x{} is not between 100x100 and 4000x4000", self.width, self.height));
+        }
+        if self.points_to_win > 99 {
+            return Err(format!("Matches go to at most 99 points, not {}", self.points_to_win));
+        }
+        Ok(())
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            width: ARENA_WIDTH,
+            height: ARENA_HEIGHT,
+            points_to_win: 0,
+            seed: None
+        }
+    }
+}
+
+/// Sent by clients to the server, as JSON over a WebSocket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    /// The client's paddle direction for consecutive ticks, numbered from `first_seq`.
+    Inputs { first_seq: u64, dirs: Vec<i8> },
+    /// Opens a new room in the lobby, to be joined by its code.
+    CreateRoom { config: GameConfig },
+    JoinRoom { code: String },
+    /// Changes the config of the room, which only its creator may do. Both players have to ready up again.
+    SetConfig { config: GameConfig },
+    /// The match starts once both players in a room are ready.
+    SetReady { ready: bool },
+    LeaveRoom
+}
+
+/// Sent by the server to clients, as JSON over a WebSocket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    /// The match is starting and the client plays `side`.
+    Welcome { side: Side, seed: u64, width: f32, height: f32 },
+    /// The authoritative state of the match. `ack` is the last of the client's inputs the server has applied.
+    State { snapshot: Snapshot, ack: u64 },
+    OpponentLeft,
+    /// The client is watching a match, which it sees `delay` ticks late.
+    Spectating { width: f32, height: f32, delay: u64 },
+    /// The match is over, because a player reached the points to win or because the match being watched ended.
+    MatchOver,
+    /// The room the client is in changed. `ready` holds whether each player in it is ready, and the client is
+    /// `seat` among them.
+    Room { code: String, config: GameConfig, ready: Vec<bool>, seat: usize },
+    LeftRoom,
+    /// A lobby request could not be carried out.
+    LobbyError { message: String }
+}
+
+/// The address to watch the matches of the server at `url`.
+pub fn spectate_url(url: &str) -> String {
+    format!("{}{}", url.trim_end_matches('/'), SPECTATE_PATH)
+}
+
+/// The address of the lobby of the server at `url`.
+pub fn lobby_url(url: &str) -> String {
+    format!("{}{}", url.trim_end_matches('/'), LOBBY_PATH)
+}
+
+impl ClientMessage {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Messages are always representable as JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<ClientMessage, String> {
+        serde_json::from_str(json).map_err(|err| err.to_string())
+    }
+}
+
+impl ServerMessage {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Messages are always representable as JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<ServerMessage, String> {
+        serde_json::from_str(json).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::TcpStream;
+
+use tungstenite::{Message, WebSocket};
+
+use crate::net::ServerMessage;
+use crate::world::snapshot::Snapshot;
+
+/// Passes the states of a match on to any number of spectators, `delay` ticks after they happened so that
+/// watching a match gives no advantage to someone playing it.
+pub struct Relay {
+    width: f32,
+    height: f32,
+    delay: u64,
+
+    // States not old enough to be sent yet
+    pending: VecDeque<Snapshot>,
+    spectators: Vec<WebSocket<TcpStream>>
+}
+
+impl Relay {
+    pub fn new(width: f32, height: f32, delay: u64) -> Relay {
+        Relay {
+            width,
+            height,
+            delay,
+
+            pending: VecDeque::new(),
+            spectators: Vec::new()
+        }
+    }
+
+    /// Adds a spectator, who sees the match from the next state sent on.
+    pub fn add(&mut self, mut socket: WebSocket<TcpStream>) -> io::Result<()> {
+        socket.get_ref().set_nonblocking(true)?;
+        socket.get_ref().set_nodelay(true)?;
+
+        let message = ServerMessage::Spectating { width: self.width, height: self.height, delay: self.delay };
+        let _ = socket.send(Message::text(message.to_json()));
+        self.spectators.push(socket);
+        Ok(())
+    }
+
+    /// Queues the state of the match at `snapshot.tick`, and sends every state that is now old enough.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        self.pending.push_back(snapshot);
+
+        while self.pending.front().is_some_and(|oldest| oldest.tick + self.delay <= snapshot.tick) {
+            let snapshot = self.pending.pop_front().unwrap();
+            self.broadcast(&ServerMessage::State { snapshot, ack: 0 });
+        }
+
+        // Spectators have nothing to say, but reading handles their pings and notices when they leave
+        self.spectators.retain_mut(|socket| loop {
+            match socket.read() {
+                Ok(Message::Close(_)) => break false,
+                Ok(_) => {},
+                Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => break true,
+                Err(_) => break false
+            }
+        });
+    }
+
+    /// Tells every spectator the match is over and lets them go. States still held back by the delay are never
+    /// shown.
+    pub fn finish(&mut self) {
+        self.broadcast(&ServerMessage::MatchOver);
+        for mut socket in self.spectators.drain(..) {
+            let _ = socket.close(None);
+            let _ = socket.flush();
+        }
+    }
+
+    pub fn get_spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let text = message.to_json();
+        for socket in self.spectators.iter_mut() {
+            // A full send buffer is flushed on the next send, and a dead connection is noticed when reading
+            let _ = socket.send(Message::text(text.clone()));
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::{Message, WebSocket};
+
+use crate::net::{ClientMessage, GameConfig, LOBBY_PATH, SPECTATE_PATH, ServerMessage, lobby::Lobby, relay::Relay};
+use crate::world::{Side, World, TICK_MS, input::TickInput};
+
+/// Clients are sent the state of the match every this many ticks.
+pub const STATE_INTERVAL: u64 = 4;
+
+// Inputs queued beyond this only add latency, so the oldest ones are dropped
+const MAX_QUEUED_INPUTS: usize = 24;
+
+// How often the lobby looks for requests
+const LOBBY_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// Clients that take longer than this to send their handshake are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Accepts players forever, starting a match on its own thread for every two that connect.
+///
+/// Connections to `LOBBY_PATH` set up their match in a room of the lobby instead. Connections to `SPECTATE_PATH`
+/// watch the newest match, whether set up in a room or not, or the next one if none is running, seeing it
+/// `broadcast_delay_ms` late.
+pub fn serve(listener: TcpListener, broadcast_delay_ms: f32) -> io::Result<()> {
+    let broadcast_delay = (broadcast_delay_ms / TICK_MS).round() as u64;
+    let (arrivals, incoming) = mpsc::channel();
+    let lobby_matches = arrivals.clone();
+    thread::spawn(move || match_players(incoming, lobby_matches, broadcast_delay));
+
+    loop {
+        let (stream, addr) = listener.accept()?;
+        let arrivals = arrivals.clone();
+        // A client that never finishes its handshake only holds up its own thread, and only for so long
+        thread::spawn(move || match handshake(stream) {
+            Ok((path, socket)) => {
+                let _ = arrivals.send(Arrival::Client(path, Box::new(socket)));
+            },
+            Err(err) => eprintln!("Handshake with {} failed: {}", addr, err)
+        });
+    }
+}
+
+// What the thread pairing up players hears about
+enum Arrival {
+    // A client that finished its handshake, with the path it asked for
+    Client(String, Box<WebSocket<TcpStream>>),
+    // A match set up in a room of the lobby started, and takes its spectators through this
+    RoomMatch(Sender<WebSocket<TcpStream>>)
+}
+
+// The path the client asked for and its socket, blocking again once the handshake is done
+// The handshake callback has to return tungstenite's error response, large as it is
+#[allow(clippy::result_large_err)]
+fn handshake(stream: TcpStream) -> Result<(String, WebSocket<TcpStream>), String> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|err| err.to_string())?;
+    let mut path = String::new();
+    let socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        Ok(response)
+    }).map_err(|err| err.to_string())?;
+    socket.get_ref().set_read_timeout(None).map_err(|err| err.to_string())?;
+    Ok((path, socket))
+}
+
+// Pairs up the players that arrive, hands the ones for the lobby over to it, and sends spectators to the newest
+// match
+fn match_players(incoming: Receiver<Arrival>, lobby_matches: Sender<Arrival>, broadcast_delay: u64) {
+    let mut waiting_player = None;
+    let mut waiting_spectators = Vec::new();
+    let mut newest_match: Option<Sender<WebSocket<TcpStream>>> = None;
+
+    let (lobby, incoming_lobby) = mpsc::channel();
+    thread::spawn(move || run_lobby(incoming_lobby, lobby_matches, broadcast_delay));
+
+    for arrival in incoming {
+        let (path, socket) = match arrival {
+            Arrival::Client(path, socket) => (path, *socket),
+            Arrival::RoomMatch(spectators) => {
+                for spectator in waiting_spectators.drain(..) {
+                    let _ = spectators.send(spectator);
+                }
+                newest_match = Some(spectators);
+                continue;
+            }
+        };
+
+        if path == LOBBY_PATH {
+            let _ = lobby.send(socket);
+            continue;
+        }
+        if path == SPECTATE_PATH {
+            // Sending fails once the match is over
+            let unwatched = match &newest_match {
+                Some(spectators) => spectators.send(socket).err().map(|SendError(socket)| socket),
+                None => Some(socket)
+            };
+            waiting_spectators.extend(unwatched);
+            continue;
+        }
+
+        // A player that left while waiting would only end its match at once
+        if waiting_player.as_mut().is_some_and(|p1| !is_connected(p1)) {
+            waiting_player = None;
+        }
+        let p1 = match waiting_player.take() {
+            Some(p1) => p1,
+            None => {
+                waiting_player = Some(socket);
+                continue;
+            }
+        };
+
+        let (spectators, incoming_spectators) = mpsc::channel();
+        for spectator in waiting_spectators.drain(..) {
+            let _ = spectators.send(spectator);
+        }
+        newest_match = Some(spectators);
+
+        spawn_match(p1, socket, GameConfig::default(), broadcast_delay, incoming_spectators);
+    }
+}
+
+// Whether the client waiting on `socket` is still there, from what it sent while it waited, without blocking. Until
+// its match starts, anything but a close is of no use.
+fn is_connected(socket: &mut WebSocket<TcpStream>) -> bool {
+    if socket.get_ref().set_nonblocking(true).is_err() {
+        return false;
+    }
+    let connected = loop {
+        match socket.read() {
+            Ok(Message::Close(_)) => break false,
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => break true,
+            Err(_) => break false
+        }
+    };
+    connected && socket.get_ref().set_nonblocking(false).is_ok()
+}
+
+// Sets up matches between the players connected through `incoming` as they ask, and starts them, passing every one
+// on to `matches` for spectators to find
+fn run_lobby(incoming: Receiver<WebSocket<TcpStream>>, matches: Sender<Arrival>, broadcast_delay: u64) {
+    let mut lobby = Lobby::new(rand::random());
+    let mut sockets = HashMap::new();
+
+    loop {
+        for socket in incoming.try_iter() {
+            if socket.get_ref().set_nonblocking(true).is_ok() {
+                sockets.insert(lobby.connect(), socket);
+            }
+        }
+
+        // Requests
+        let mut gone = Vec::new();
+        for (client, socket) in sockets.iter_mut() {
+            loop {
+                match socket.read() {
+                    Ok(Message::Text(text)) => {
+                        match ClientMessage::from_json(&text) {
+                            Ok(message) => lobby.receive(*client, message),
+                            Err(message) => {
+                                let _ = socket.send(Message::text(ServerMessage::LobbyError { message }.to_json()));
+                            }
+                        }
+                    },
+                    Ok(Message::Close(_)) => {
+                        gone.push(*client);
+                        break;
+                    },
+                    Ok(_) => {},
+                    Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        gone.push(*client);
+                        break;
+                    }
+                }
+            }
+        }
+        for client in gone {
+            sockets.remove(&client);
+            lobby.disconnect(client);
+        }
+
+        // Replies
+        for (client, message) in lobby.take_outgoing() {
+            if let Some(socket) = sockets.get_mut(&client) {
+                let _ = socket.send(Message::text(message.to_json()));
+            }
+        }
+
+        // Matches
+        for start in lobby.take_started() {
+            match (sockets.remove(&start.players[0]), sockets.remove(&start.players[1])) {
+                (Some(p1), Some(p2)) => {
+                    let (spectators, incoming_spectators) = mpsc::channel();
+                    let _ = matches.send(Arrival::RoomMatch(spectators));
+                    spawn_match(p1, p2, start.config, broadcast_delay, incoming_spectators);
+                },
+                // A player left in the same poll as they readied up, so whoever is still there goes back to the
+                // lobby, out of the room
+                (p1, p2) => {
+                    for mut socket in p1.into_iter().chain(p2) {
+                        let message = String::from("The other player left before the match started");
+                        let _ = socket.send(Message::text(ServerMessage::LobbyError { message }.to_json()));
+                        let _ = socket.send(Message::text(ServerMessage::LeftRoom.to_json()));
+                        sockets.insert(lobby.connect(), socket);
+                    }
+                }
+            }
+        }
+
+        thread::sleep(LOBBY_POLL_INTERVAL);
+    }
+}
+
+fn spawn_match(p1: WebSocket<TcpStream>, p2: WebSocket<TcpStream>, config: GameConfig, broadcast_delay: u64, incoming_spectators: Receiver<WebSocket<TcpStream>>) {
+    thread::spawn(move || {
+        let relay = Relay::new(config.width, config.height, broadcast_delay);
+        if let Err(err) = run_match(p1, p2, &config, relay, incoming_spectators) {
+            eprintln!("Match ended with an error: {}", err);
+        }
+    });
+}
+
+/// Runs one match to completion, which is when either player leaves or reaches the points to win. Its states go
+/// through `relay` to the spectators it already has and any that arrive through `incoming_spectators`.
+pub fn run_match(p1: WebSocket<TcpStream>, p2: WebSocket<TcpStream>, config: &GameConfig, mut relay: Relay, incoming_spectators: Receiver<WebSocket<TcpStream>>) -> io::Result<()> {
+    let mut players = [Player::new(p1, Side::P1)?, Player::new(p2, Side::P2)?];
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let mut world = World::new(config.width, config.height, seed);
+
+    for player in players.iter_mut() {
+        let side = player.side;
+        player.send(&ServerMessage::Welcome { side, seed, width: config.width, height: config.height });
+    }
+
+    let tick = Duration::from_secs_f32(TICK_MS / 1000.0);
+    let mut next_tick = Instant::now();
+
+    loop {
+        // Input
+        for i in 0..players.len() {
+            if !players[i].receive() {
+                players[1 - i].send(&ServerMessage::OpponentLeft);
+                players[1 - i].close();
+                relay.finish();
+                return Ok(());
+            }
+        }
+
+        // Step
+        world.tick(TickInput::new(players[0].next_dir(), players[1].next_dir()));
+
+        // State, always including the final one
+        let (p1_score, p2_score) = world.get_scores();
+        let over = config.points_to_win > 0 && p1_score.max(p2_score) >= config.points_to_win;
+        if over || world.get_tick().is_multiple_of(STATE_INTERVAL) {
+            let snapshot = world.snapshot();
+            for player in players.iter_mut() {
+                let ack = player.ack;
+                player.send(&ServerMessage::State { snapshot, ack });
+            }
+
+            for spectator in incoming_spectators.try_iter() {
+                if let Err(err) = relay.add(spectator) {
+                    eprintln!("Could not add a spectator: {}", err);
+                }
+            }
+            relay.push(snapshot);
+        }
+
+        if over {
+            for player in players.iter_mut() {
+                player.send(&ServerMessage::MatchOver);
+                player.close();
+            }
+            relay.finish();
+            return Ok(());
+        }
+
+        next_tick += tick;
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+}
+
+struct Player {
+    socket: WebSocket<TcpStream>,
+    side: Side,
+
+    // Inputs received but not simulated yet, with their sequence numbers
+    inputs: VecDeque<(u64, i8)>,
+    dir: i8,
+    ack: u64
+}
+
+impl Player {
+    fn new(socket: WebSocket<TcpStream>, side: Side) -> io::Result<Player> {
+        socket.get_ref().set_nonblocking(true)?;
+        socket.get_ref().set_nodelay(true)?;
+
+        Ok(Player {
+            socket,
+            side,
+
+            inputs: VecDeque::new(),
+            dir: 0,
+            ack: 0
+        })
+    }
+
+    /// Reads everything the client sent since the last call. Returns false once the client is gone.
+    fn receive(&mut self) -> bool {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => {
+                    match ClientMessage::from_json(&text) {
+                        Ok(ClientMessage::Inputs { first_seq, dirs }) => {
+                            for (seq, dir) in (first_seq..).zip(dirs) {
+                                let newest = self.inputs.back().map_or(self.ack, |(seq, _)| *seq);
+                                if seq > newest {
+                                    self.inputs.push_back((seq, dir.clamp(-1, 1)));
+                                }
+                            }
+                            while self.inputs.len() > MAX_QUEUED_INPUTS {
+                                self.inputs.pop_front();
+                            }
+                        },
+                        // Lobby requests mean nothing once the match has started
+                        Ok(_) => {},
+                        Err(err) => eprintln!("Bad message from {:?}: {}", self.side, err)
+                    }
+                },
+                Ok(Message::Close(_)) => return false,
+                Ok(_) => {},
+                Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(_) => return false
+            }
+        }
+    }
+
+    /// The direction for the next tick. When the client's inputs run late it keeps its last direction.
+    fn next_dir(&mut self) -> i8 {
+        if let Some((seq, dir)) = self.inputs.pop_front() {
+            self.ack = seq;
+            self.dir = dir;
+        }
+        self.dir
+    }
+
+    fn send(&mut self, message: &ServerMessage) {
+        // A full send buffer is flushed on the next send, and a dead connection is noticed when receiving
+        let _ = self.socket.send(Message::text(message.to_json()));
+    }
+
+    fn close(&mut self) {
+        let _ = self.socket.close(None);
+        let _ = self.socket.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::*;
+    use crate::net::client::Connection;
+
+    // Long enough for any match in these tests to be decided
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, 0.0));
+        addr
+    }
+
+    fn connect(addr: SocketAddr, path: &str) -> Connection {
+        Connection::connect(&format!("ws://{}{}", addr, path)).unwrap()
+    }
+
+    // The first message from the server `expected` accepts, skipping the others
+    fn receive_until(connection: &mut Connection, expected: impl Fn(&ServerMessage) -> bool) -> ServerMessage {
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            match connection.receive().unwrap() {
+                Some(message) if expected(&message) => return message,
+                Some(_) => {},
+                None => thread::sleep(Duration::from_millis(1))
+            }
+        }
+        panic!("Timed out waiting for the server");
+    }
+
+    // A room in the lobby with both players in it, host first
+    fn open_room(addr: SocketAddr, config: GameConfig) -> [Connection; 2] {
+        let mut host = connect(addr, LOBBY_PATH);
+        host.send(&ClientMessage::CreateRoom { config }).unwrap();
+        let code = match receive_until(&mut host, |message| matches!(message, ServerMessage::Room { .. })) {
+            ServerMessage::Room { code, .. } => code,
+            _ => unreachable!()
+        };
+        let mut guest = connect(addr, LOBBY_PATH);
+        guest.send(&ClientMessage::JoinRoom { code }).unwrap();
+        receive_until(&mut guest, |message| matches!(message, ServerMessage::Room { .. }));
+        receive_until(&mut host, |message| matches!(message, ServerMessage::Room { ready, .. } if ready.len() == 2));
+        [host, guest]
+    }
+
+    fn start_room_match(addr: SocketAddr, config: GameConfig) -> [Connection; 2] {
+        let mut players = open_room(addr, config);
+        for player in players.iter_mut() {
+            player.send(&ClientMessage::SetReady { ready: true }).unwrap();
+        }
+        for player in players.iter_mut() {
+            receive_until(player, |message| matches!(message, ServerMessage::Welcome { .. }));
+        }
+        players
+    }
+
+    #[test]
+    fn pairs_players_past_a_stalled_handshake() {
+        let addr = start_server();
+        // Never sends its handshake
+        let _stalled = TcpStream::connect(addr).unwrap();
+
+        let mut players = [connect(addr, "/"), connect(addr, "/")];
+        for (player, side) in players.iter_mut().zip([Side::P1, Side::P2]) {
+            let welcome = receive_until(player, |message| matches!(message, ServerMessage::Welcome { .. }));
+            assert!(matches!(welcome, ServerMessage::Welcome { side: welcomed, .. } if welcomed == side));
+            receive_until(player, |message| matches!(message, ServerMessage::State { .. }));
+        }
+    }
+
+    #[test]
+    fn pairs_players_past_one_that_left_while_waiting() {
+        let addr = start_server();
+        connect(addr, "/").close();
+        // Long enough for it to be the one waiting
+        thread::sleep(Duration::from_millis(100));
+
+        let mut first = connect(addr, "/");
+        thread::sleep(Duration::from_millis(100));
+        let mut second = connect(addr, "/");
+        for (player, side) in [(&mut first, Side::P1), (&mut second, Side::P2)] {
+            let welcome = receive_until(player, |message| matches!(message, ServerMessage::Welcome { .. }));
+            assert!(matches!(welcome, ServerMessage::Welcome { side: welcomed, .. } if welcomed == side));
+        }
+    }
+
+    #[test]
+    fn ends_a_room_match_at_its_points_to_win() {
+        let addr = start_server();
+        let config = GameConfig { width: 200.0, height: 200.0, points_to_win: 1, seed: Some(1) };
+
+        let mut players = start_room_match(addr, config);
+        for player in players.iter_mut() {
+            // Both paddles run off to the top, so the first serve scores
+            player.send(&ClientMessage::Inputs { first_seq: 1, dirs: vec![1] }).unwrap();
+        }
+        for player in players.iter_mut() {
+            receive_until(player, |message| matches!(message, ServerMessage::State { .. }));
+            receive_until(player, |message| *message == ServerMessage::MatchOver);
+        }
+    }
+
+    #[test]
+    fn spectates_a_room_match() {
+        let addr = start_server();
+        let _players = start_room_match(addr, GameConfig::default());
+
+        let mut spectator = connect(addr, SPECTATE_PATH);
+        receive_until(&mut spectator, |message| matches!(message, ServerMessage::Spectating { .. }));
+        receive_until(&mut spectator, |message| matches!(message, ServerMessage::State { .. }));
+    }
+
+    #[test]
+    fn sends_a_player_back_to_the_lobby_when_the_other_readies_and_leaves_at_once() {
+        let addr = start_server();
+        let mut host = connect(addr, LOBBY_PATH);
+        host.send(&ClientMessage::CreateRoom { config: GameConfig::default() }).unwrap();
+        let code = match receive_until(&mut host, |message| matches!(message, ServerMessage::Room { .. })) {
+            ServerMessage::Room { code, .. } => code,
+            _ => unreachable!()
+        };
+        host.send(&ClientMessage::SetReady { ready: true }).unwrap();
+        receive_until(&mut host, |message| matches!(message, ServerMessage::Room { ready, .. } if ready[0]));
+
+        // A plain socket, to control when its messages go out
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let (mut guest, _) = tungstenite::client(format!("ws://{}{}", addr, LOBBY_PATH), stream).unwrap();
+        guest.send(Message::text(ClientMessage::JoinRoom { code }.to_json())).unwrap();
+        receive_until(&mut host, |message| matches!(message, ServerMessage::Room { ready, .. } if ready.len() == 2));
+
+        // Readying up and closing go out in one write, so the lobby reads both in the same poll
+        guest.write(Message::text(ClientMessage::SetReady { ready: true }.to_json())).unwrap();
+        guest.close(None).unwrap();
+        guest.flush().unwrap();
+
+        receive_until(&mut host, |message| matches!(message, ServerMessage::LobbyError { .. }));
+        receive_until(&mut host, |message| *message == ServerMessage::LeftRoom);
+        // Still in the lobby, and free to open another room
+        host.send(&ClientMessage::CreateRoom { config: GameConfig::default() }).unwrap();
+        receive_until(&mut host, |message| matches!(message, ServerMessage::Room { .. }));
+    }
+}
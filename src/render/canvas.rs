@@ -0,0 +1,156 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::effects::particles::Particle;
+use crate::effects::trail::Trail;
+use crate::render::{Render, RenderBackend, get_net};
+use crate::render::overlay::Overlay;
+use crate::render::theme::Theme;
+use crate::world::World;
+
+/// Draws with the 2D context of the canvas, for browsers without WebGL. It draws everything the WebGL renderer does
+/// but the CRT effects.
+pub struct Canvas2dRenderer {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    theme: Theme,
+    // Everything in the arena moves this far while the screen shakes
+    shake_offset: (f32, f32),
+    // How much of the flash of each paddle is left, from 0 to 1
+    flash: [f32; 2],
+    // Canvas pixels for each unit of the world being drawn, set by every draw
+    scale: (f64, f64)
+}
+
+impl Canvas2dRenderer {
+    pub fn new(canvas: &HtmlCanvasElement) -> Result<Canvas2dRenderer, JsValue> {
+        let context = canvas.get_context("2d")?.ok_or("Browser does not support canvas")?.dyn_into::<CanvasRenderingContext2d>()?;
+        Ok(Canvas2dRenderer {
+            canvas: canvas.clone(),
+            context,
+            theme: Theme::default(),
+            shake_offset: (0.0, 0.0),
+            flash: [0.0, 0.0],
+            scale: (1.0, 1.0)
+        })
+    }
+
+    // Follows the size of the canvas, which may change between frames
+    fn set_world_size(&mut self, win_width: f32, win_height: f32) {
+        self.scale = (self.canvas.width() as f64 / win_width as f64, self.canvas.height() as f64 / win_height as f64);
+    }
+
+    // The world has y going up from the bottom, the canvas down from the top
+    fn to_canvas(&self, x: f32, y: f32) -> (f64, f64) {
+        let (scale_x, scale_y) = self.scale;
+        (x as f64 * scale_x, self.canvas.height() as f64 - y as f64 * scale_y)
+    }
+
+    // Centre and size, in the current colour
+    fn fill_rect(&self, [x, y, width, height]: [f32; 4]) {
+        let (scale_x, scale_y) = self.scale;
+        let (left, top) = self.to_canvas(x - width / 2.0, y + height / 2.0);
+        self.context.fill_rect(left, top, width as f64 * scale_x, height as f64 * scale_y);
+    }
+
+    // Centre and radius, in the current colour
+    fn fill_circle(&self, [x, y, radius]: [f32; 3]) {
+        use std::f64::consts::PI;
+
+        let (centre_x, centre_y) = self.to_canvas(x, y);
+        self.context.begin_path();
+        self.context.arc(centre_x, centre_y, radius.max(0.0) as f64 * self.scale.0, 0.0, 2.0 * PI)
+            .expect("The radius is never negative");
+        self.context.fill();
+    }
+
+    fn set_color(&self, color: [f32; 3]) {
+        self.set_color_alpha(color, 1.0);
+    }
+
+    fn set_color_alpha(&self, [red, green, blue]: [f32; 3], alpha: f32) {
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        self.context.set_fill_style_str(&format!("rgba({}, {}, {}, {})", channel(red), channel(green), channel(blue), alpha.clamp(0.0, 1.0)));
+    }
+}
+
+impl RenderBackend for Canvas2dRenderer {
+    fn get_version(&self) -> &'static str {
+        "Canvas 2D"
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn set_shake(&mut self, x: f32, y: f32) {
+        self.shake_offset = (x, y);
+    }
+
+    fn set_flash(&mut self, p1: f32, p2: f32) {
+        self.flash = [p1.clamp(0.0, 1.0), p2.clamp(0.0, 1.0)];
+    }
+
+    fn clear(&mut self) {
+        self.set_color(self.theme.get_background());
+        self.context.fill_rect(0.0, 0.0, self.canvas.width() as f64, self.canvas.height() as f64);
+    }
+
+    fn render_overlay(&mut self, overlay: &Overlay, win_width: f32, win_height: f32) {
+        self.set_world_size(win_width, win_height);
+        for rect in overlay.get_rects() {
+            self.set_color(if rect.highlight { self.theme.get_highlight() } else { self.theme.get_foreground() });
+            self.fill_rect([rect.x, rect.y, rect.width, rect.height]);
+        }
+    }
+
+    fn render_particles(&mut self, particles: &[Particle], win_width: f32, win_height: f32) {
+        self.set_world_size(win_width, win_height);
+        let (offset_x, offset_y) = self.shake_offset;
+        for particle in particles {
+            self.set_color_alpha(self.theme.get_highlight(), particle.get_fade());
+            self.fill_rect([particle.x + offset_x, particle.y + offset_y, particle.size, particle.size]);
+        }
+    }
+
+    fn render_trail(&mut self, trail: &Trail, radius: f32, win_width: f32, win_height: f32) {
+        self.set_world_size(win_width, win_height);
+        let (offset_x, offset_y) = self.shake_offset;
+        let count = trail.len() as f32;
+        // The newest point is where the ball already is
+        for (i, (x, y)) in trail.get_points().enumerate().skip(1) {
+            let fade = 1.0 - i as f32 / count;
+            self.set_color_alpha(self.theme.get_foreground(), fade * 0.5);
+            self.fill_circle([x + offset_x, y + offset_y, radius * (0.5 + fade / 2.0)]);
+        }
+    }
+}
+
+impl Render for Canvas2dRenderer {
+    fn render(&mut self, world: &World) {
+        self.set_world_size(world.get_width(), world.get_height());
+
+        // Clear
+        self.clear();
+
+        // Draw
+        let (offset_x, offset_y) = self.shake_offset;
+        self.set_color(self.theme.get_foreground());
+
+        for [x, y, width, height] in get_net(world) {
+            self.fill_rect([x + offset_x, y + offset_y, width, height]);
+        }
+
+        for ball in world.get_balls() {
+            self.fill_circle([ball.get_x() + offset_x, ball.get_y() + offset_y, ball.get_radius()]);
+        }
+
+        let p1 = world.get_p1();
+        self.set_color(self.theme.get_paddle_color(self.flash[0]));
+        self.fill_rect([p1.get_x() + offset_x, p1.get_y() + offset_y, p1.get_width(), p1.get_height()]);
+
+        let p2 = world.get_p2();
+        self.set_color(self.theme.get_paddle_color(self.flash[1]));
+        self.fill_rect([p2.get_x() + offset_x, p2.get_y() + offset_y, p2.get_width(), p2.get_height()]);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::effects::particles::Particle;
+use crate::effects::trail::Trail;
+use crate::world::World;
+
+pub mod font;
+pub mod overlay;
+pub mod post;
+pub mod theme;
+#[cfg(feature = "web")]
+pub(crate) mod canvas;
+#[cfg(feature = "web")]
+pub(crate) mod webgl;
+#[cfg(feature = "native")]
+pub mod software;
+#[cfg(feature = "tui")]
+pub mod terminal;
+
+use overlay::Overlay;
+use post::PostEffects;
+use theme::Theme;
+
+// Dashes of the net down the middle of the arena
+const NET_DASHES: u32 = 20;
+
+pub trait Render {
+    fn render(&mut self, world: &World);
+}
+
+/// What the browser game draws with, whichever kind of context the canvas has. Everything is drawn in world
+/// coordinates, for a world of `win_width` by `win_height`.
+pub trait RenderBackend: Render {
+    /// Like `WebGL 2` or `Canvas 2D`.
+    fn get_version(&self) -> &'static str;
+
+    fn set_theme(&mut self, theme: Theme);
+
+    /// Turns on the passes run over every finished frame. Backends without post processing leave frames as they are.
+    fn set_post_effects(&mut self, _effects: PostEffects) {}
+
+    /// Starts drawing a frame.
+    fn begin_frame(&mut self) {}
+
+    /// Puts the frame drawn since `begin_frame` on the canvas.
+    fn end_frame(&mut self) {}
+
+    /// Sets everything up again after the context was lost and came back.
+    fn restore(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Replaces the sources of the shader program called `name`, which keeps drawing with the old ones if the new
+    /// ones do not compile.
+    fn reload_shader(&mut self, name: &str, _vert_shader_src: &str, _frag_shader_src: &str) -> Result<(), String> {
+        Err(format!("No shader called {:?}, {} draws without shaders", name, self.get_version()))
+    }
+
+    /// The vertex and fragment shader sources of the program called `name`.
+    fn get_shader_source(&self, _name: &str) -> Option<(&str, &str)> {
+        None
+    }
+
+    /// Moves the ball, paddles, trail and particles by `x` and `y` from now on.
+    fn set_shake(&mut self, x: f32, y: f32);
+
+    /// Draws each paddle that much of the way from the foreground colour to the highlight, from 0 to 1.
+    fn set_flash(&mut self, p1: f32, p2: f32);
+
+    /// Fills the canvas with the background colour.
+    fn clear(&mut self);
+
+    /// Draws `overlay` on top of whatever was drawn last.
+    fn render_overlay(&mut self, overlay: &Overlay, win_width: f32, win_height: f32);
+
+    /// Draws `particles` on top of whatever was drawn last, fading out as they age.
+    fn render_particles(&mut self, particles: &[Particle], win_width: f32, win_height: f32);
+
+    /// Draws `trail` on top of whatever was drawn last, as copies of a ball of `radius` shrinking and fading out
+    /// behind the ball.
+    fn render_trail(&mut self, trail: &Trail, radius: f32, win_width: f32, win_height: f32);
+}
+
+/// The dashes of the net down the middle of `world`, half as wide as a paddle, as centre and size.
+pub fn get_net(world: &World) -> Vec<[f32; 4]> {
+    let dash_width = world.get_p1().get_width() / 2.0;
+    let dash_height = world.get_height() / NET_DASHES as f32 / 2.0;
+    (0..NET_DASHES)
+        .map(|i| [world.get_width() / 2.0, (i as f32 * 2.0 + 1.0) * dash_height, dash_width, dash_height])
+        .collect()
+}
@@ -0,0 +1,87 @@
+use crate::render::font::{GLYPH_HEIGHT, GLYPH_WIDTH, glyph};
+
+/// A filled rectangle of an overlay, centred on `x` and `y`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverlayRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Drawn in the theme's highlight colour instead of the foreground one.
+    pub highlight: bool
+}
+
+/// Shapes drawn over the world, like menus, in world coordinates with y pointing up. Text is built out of
+/// rectangles too, so a renderer only has to fill rectangles to draw an overlay.
+pub struct Overlay {
+    rects: Vec<OverlayRect>
+}
+
+impl Overlay {
+    pub fn new() -> Overlay {
+        Overlay {
+            rects: Vec::new()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, highlight: bool) {
+        self.rects.push(OverlayRect { x, y, width, height, highlight });
+    }
+
+    /// Writes `text` centred on `x` and `y`, with every font pixel `size` wide.
+    pub fn text(&mut self, text: &str, x: f32, y: f32, size: f32, highlight: bool) {
+        let left = x - Self::text_width(text, size)/2.0;
+        let top = y + (GLYPH_HEIGHT as f32)*size/2.0;
+
+        for (i, letter) in text.chars().enumerate() {
+            let glyph_left = left + (i * (GLYPH_WIDTH + 1)) as f32 * size;
+            for (row, bits) in glyph(letter).iter().enumerate() {
+                let row_y = top - (row as f32 + 0.5)*size;
+
+                // Every run of lit pixels in a row is one rectangle
+                let mut col = 0;
+                while col < GLYPH_WIDTH {
+                    if bits & (0x10 >> col) == 0 {
+                        col += 1;
+                        continue;
+                    }
+                    let start = col;
+                    while col < GLYPH_WIDTH && bits & (0x10 >> col) != 0 {
+                        col += 1;
+                    }
+                    let run = (col - start) as f32;
+                    self.fill_rect(glyph_left + (start as f32 + run/2.0)*size, row_y, run*size, size, highlight);
+                }
+            }
+        }
+    }
+
+    /// Width of `text` written with font pixels `size` wide.
+    pub fn text_width(text: &str, size: f32) -> f32 {
+        let len = text.chars().count();
+        if len == 0 {
+            0.0
+        }
+        else {
+            (len * (GLYPH_WIDTH + 1) - 1) as f32 * size
+        }
+    }
+
+    pub fn get_rects(&self) -> &[OverlayRect] {
+        &self.rects
+    }
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// The passes run over a finished frame for the look of an old CRT monitor, each turned on or off on its own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostEffects {
+    /// Dark lines between the rows of the screen.
+    pub scanlines: bool,
+    /// Bright things glow.
+    pub bloom: bool,
+    /// The screen bulges like curved glass.
+    pub barrel: bool,
+    /// Red and blue split apart towards the edges.
+    pub chromatic: bool
+}
+
+impl PostEffects {
+    pub const NAMES: [&'static str; 4] = ["scanlines", "bloom", "barrel", "chromatic"];
+
+    /// Every effect.
+    pub fn all() -> PostEffects {
+        PostEffects {
+            scanlines: true,
+            bloom: true,
+            barrel: true,
+            chromatic: true
+        }
+    }
+
+    /// Whether any pass runs at all.
+    pub fn any(self) -> bool {
+        self.scanlines || self.bloom || self.barrel || self.chromatic
+    }
+
+    /// Turns the effect called `name`, one of `NAMES`, on or off.
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        match name {
+            "scanlines" => self.scanlines = enabled,
+            "bloom" => self.bloom = enabled,
+            "barrel" => self.barrel = enabled,
+            "chromatic" => self.chromatic = enabled,
+            _ => return Err(format!("Unknown effect {:?}, expected one of {}", name, Self::NAMES.join(", ")))
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use crate::render::Render;
+use crate::world::World;
+
+const BACKGROUND: u32 = 0x00_00_00_00;
+const FOREGROUND: u32 = 0x00_ff_ff_ff;
+
+/// Rasterizes the world into a `0RGB` pixel buffer, row by row from the top left corner.
+pub struct SoftwareRenderer {
+    width: usize,
+    height: usize,
+    buffer: Vec<u32>
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: usize, height: usize) -> SoftwareRenderer {
+        SoftwareRenderer {
+            width,
+            height,
+            buffer: vec![BACKGROUND; width * height]
+        }
+    }
+
+    pub fn get_buffer(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let x0 = (x - width/2.0).max(0.0) as usize;
+        let x1 = ((x + width/2.0).max(0.0) as usize).min(self.width);
+        let y0 = (y - height/2.0).max(0.0) as usize;
+        let y1 = ((y + height/2.0).max(0.0) as usize).min(self.height);
+
+        for row in y0..y1 {
+            // World coordinates have y pointing up, the buffer has y pointing down
+            let start = (self.height - 1 - row) * self.width;
+            for pixel in &mut self.buffer[start + x0..start + x1.max(x0)] {
+                *pixel = FOREGROUND;
+            }
+        }
+    }
+
+    fn fill_circle(&mut self, x: f32, y: f32, radius: f32) {
+        let x0 = (x - radius).max(0.0) as usize;
+        let x1 = ((x + radius).max(0.0).ceil() as usize).min(self.width);
+        let y0 = (y - radius).max(0.0) as usize;
+        let y1 = ((y + radius).max(0.0).ceil() as usize).min(self.height);
+
+        for row in y0..y1 {
+            let start = (self.height - 1 - row) * self.width;
+            for col in x0..x1 {
+                let dx = col as f32 + 0.5 - x;
+                let dy = row as f32 + 0.5 - y;
+                if dx*dx + dy*dy <= radius*radius {
+                    self.buffer[start + col] = FOREGROUND;
+                }
+            }
+        }
+    }
+}
+
+impl Render for SoftwareRenderer {
+    fn render(&mut self, world: &World) {
+        // Clear
+        for pixel in self.buffer.iter_mut() {
+            *pixel = BACKGROUND;
+        }
+
+        // Draw
+        for ball in world.get_balls() {
+            self.fill_circle(ball.get_x(), ball.get_y(), ball.get_radius());
+        }
+
+        let p1 = world.get_p1();
+        self.fill_rect(p1.get_x(), p1.get_y(), p1.get_width(), p1.get_height());
+
+        let p2 = world.get_p2();
+        self.fill_rect(p2.get_x(), p2.get_y(), p2.get_width(), p2.get_height());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::render::Render;
+use crate::world::World;
+
+/// Rasterizes the world into a grid of half-block characters framed by a box.
+///
+/// Every terminal cell holds two vertically stacked pixels, so the drawing area is `cols` pixels wide
+/// and `2*rows` pixels tall. The world is scaled to fit that area regardless of its own size.
+pub struct TerminalRenderer {
+    cols: usize,
+    rows: usize,
+    pixels: Vec<bool>,
+    frame: String
+}
+
+impl TerminalRenderer {
+    /// `cols` and `rows` is the full size of the frame in cells, border included.
+    pub fn new(cols: usize, rows: usize) -> TerminalRenderer {
+        let cols = cols.max(3) - 2;
+        let rows = rows.max(3) - 2;
+
+        TerminalRenderer {
+            cols,
+            rows,
+            pixels: vec![false; cols * rows * 2],
+            frame: String::new()
+        }
+    }
+
+    /// The last rendered frame, with lines separated by `\r\n` so that it can be written to a raw mode terminal.
+    pub fn get_frame(&self) -> &str {
+        &self.frame
+    }
+
+    fn set_pixel(&mut self, col: usize, row: usize) {
+        // World coordinates have y pointing up, the frame has y pointing down
+        let row = self.rows*2 - 1 - row;
+        self.pixels[row * self.cols + col] = true;
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let max_col = self.cols - 1;
+        let max_row = self.rows*2 - 1;
+        let col0 = ((x - width/2.0).max(0.0) as usize).min(max_col);
+        let col1 = ((x + width/2.0).max(0.0) as usize).min(max_col);
+        let row0 = ((y - height/2.0).max(0.0) as usize).min(max_row);
+        let row1 = ((y + height/2.0).max(0.0) as usize).min(max_row);
+
+        for row in row0..=row1 {
+            for col in col0..=col1 {
+                self.set_pixel(col, row);
+            }
+        }
+    }
+
+    fn fill_circle(&mut self, x: f32, y: f32, radius: f32) {
+        // At terminal resolution the ball is rarely bigger than a pixel, so the centre is always lit
+        if x >= 0.0 && y >= 0.0 && (x as usize) < self.cols && (y as usize) < self.rows*2 {
+            self.set_pixel(x as usize, y as usize);
+        }
+
+        let max_col = self.cols - 1;
+        let max_row = self.rows*2 - 1;
+        let col0 = ((x - radius).max(0.0) as usize).min(max_col);
+        let col1 = ((x + radius).max(0.0) as usize).min(max_col);
+        let row0 = ((y - radius).max(0.0) as usize).min(max_row);
+        let row1 = ((y + radius).max(0.0) as usize).min(max_row);
+
+        for row in row0..=row1 {
+            for col in col0..=col1 {
+                let dx = col as f32 + 0.5 - x;
+                let dy = row as f32 + 0.5 - y;
+                if dx*dx + dy*dy <= radius*radius {
+                    self.set_pixel(col, row);
+                }
+            }
+        }
+    }
+
+    fn compose_frame(&mut self, scores: (u32, u32)) {
+        self.frame.clear();
+
+        // Top border with the scores in the middle
+        let title = format!(" {} - {} ", scores.0, scores.1);
+        let title_len = title.chars().count().min(self.cols);
+        let left = (self.cols - title_len)/2;
+        self.frame.push('┌');
+        self.frame.push_str(&"─".repeat(left));
+        self.frame.extend(title.chars().take(title_len));
+        self.frame.push_str(&"─".repeat(self.cols - left - title_len));
+        self.frame.push_str("┐\r\n");
+
+        for row in 0..self.rows {
+            self.frame.push('│');
+            for col in 0..self.cols {
+                let upper = self.pixels[(row*2) * self.cols + col];
+                let lower = self.pixels[(row*2 + 1) * self.cols + col];
+                self.frame.push(match (upper, lower) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' '
+                });
+            }
+            self.frame.push_str("│\r\n");
+        }
+
+        self.frame.push('└');
+        self.frame.push_str(&"─".repeat(self.cols));
+        self.frame.push('┘');
+    }
+}
+
+impl Render for TerminalRenderer {
+    fn render(&mut self, world: &World) {
+        // Clear
+        for pixel in self.pixels.iter_mut() {
+            *pixel = false;
+        }
+
+        // Draw, scaling the world down to the pixel grid
+        let scale_x = self.cols as f32 / world.get_width();
+        let scale_y = (self.rows*2) as f32 / world.get_height();
+
+        for ball in world.get_balls() {
+            self.fill_circle(ball.get_x()*scale_x, ball.get_y()*scale_y, ball.get_radius()*scale_x.min(scale_y));
+        }
+
+        let p1 = world.get_p1();
+        self.fill_rect(p1.get_x()*scale_x, p1.get_y()*scale_y, p1.get_width()*scale_x, p1.get_height()*scale_y);
+
+        let p2 = world.get_p2();
+        self.fill_rect(p2.get_x()*scale_x, p2.get_y()*scale_y, p2.get_width()*scale_x, p2.get_height()*scale_y);
+
+        self.compose_frame(world.get_scores());
+    }
+}
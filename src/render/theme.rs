@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::effects::EffectsConfig;
+
+/// The colours everything is drawn in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    /// White on black.
+    #[default]
+    Classic,
+    /// Amber monochrome monitor.
+    Amber,
+    /// Green phosphor monitor.
+    Phosphor,
+    /// Black on white.
+    Paper
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Classic, Theme::Amber, Theme::Phosphor, Theme::Paper];
+
+    /// The theme after this one in `ALL`, or before it when `forwards` is false, wrapping around.
+    pub fn cycle(self, forwards: bool) -> Theme {
+        let index = Self::ALL.iter().position(|theme| *theme == self).unwrap_or(0);
+        let next = if forwards { index + 1 } else { index + Self::ALL.len() - 1 };
+        Self::ALL[next % Self::ALL.len()]
+    }
+
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Theme::Classic => "Classic",
+            Theme::Amber => "Amber",
+            Theme::Phosphor => "Phosphor",
+            Theme::Paper => "Paper"
+        }
+    }
+
+    /// Red, green and blue from 0 to 1.
+    pub fn get_background(self) -> [f32; 3] {
+        match self {
+            Theme::Classic => [0.0, 0.0, 0.0],
+            Theme::Amber => [0.08, 0.04, 0.0],
+            Theme::Phosphor => [0.0, 0.06, 0.02],
+            Theme::Paper => [0.96, 0.94, 0.88]
+        }
+    }
+
+    pub fn get_foreground(self) -> [f32; 3] {
+        match self {
+            Theme::Classic => [1.0, 1.0, 1.0],
+            Theme::Amber => [1.0, 0.69, 0.0],
+            Theme::Phosphor => [0.2, 1.0, 0.4],
+            Theme::Paper => [0.1, 0.1, 0.1]
+        }
+    }
+
+    /// For whatever is selected in a menu.
+    pub fn get_highlight(self) -> [f32; 3] {
+        match self {
+            Theme::Classic => [1.0, 0.85, 0.2],
+            Theme::Amber => [1.0, 0.9, 0.6],
+            Theme::Phosphor => [0.8, 1.0, 0.8],
+            Theme::Paper => [0.8, 0.1, 0.1]
+        }
+    }
+
+    /// A paddle `flash` of the way from the foreground colour to the highlight, from 0 to 1, as it flashes when hit.
+    pub fn get_paddle_color(self, flash: f32) -> [f32; 3] {
+        let foreground = self.get_foreground();
+        let highlight = self.get_highlight();
+        [0, 1, 2].map(|i| foreground[i] + (highlight[i] - foreground[i]) * flash)
+    }
+
+    /// How much goes on around the ball: the monitor themes leave a long afterglow, paper neither trails nor shakes.
+    pub fn get_effects(self) -> EffectsConfig {
+        match self {
+            Theme::Classic => EffectsConfig { particles: true, trail_length: 8, shake: 1.0, flash_ms: 120.0 },
+            Theme::Amber | Theme::Phosphor => EffectsConfig { particles: true, trail_length: 16, shake: 1.5, flash_ms: 200.0 },
+            Theme::Paper => EffectsConfig { particles: true, flash_ms: 120.0, ..EffectsConfig::none() }
+        }
+    }
+}
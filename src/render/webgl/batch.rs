@@ -0,0 +1,83 @@
+use web_sys::{WebGlBuffer, WebGlRenderingContext};
+
+// Indices are 16 bit, so one draw call can only reach this many quads
+const MAX_QUADS: usize = 65536 / 4;
+
+/// Any number of quads drawn together, with as few draw calls as 16 bit indices allow.
+pub struct QuadBatch {
+    vertices: Vec<f32>,
+    idxs: Vec<u16>,
+    vbo: Option<WebGlBuffer>,
+    ebo: Option<WebGlBuffer>
+}
+
+impl QuadBatch {
+    pub fn new() -> QuadBatch {
+        QuadBatch {
+            vertices: Vec::new(),
+            idxs: Vec::with_capacity(MAX_QUADS * 6),
+            vbo: None,
+            ebo: None
+        }
+    }
+
+    pub fn init_gl(&mut self, context: &WebGlRenderingContext) {
+        // Indices, the same two triangles for every quad
+        self.idxs.clear();
+        for quad in 0..MAX_QUADS as u16 {
+            let first = quad * 4;
+            self.idxs.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        // Create VBO and EBO
+        self.vbo = context.create_buffer();
+        self.ebo = context.create_buffer();
+
+        // Bind and set EBO
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, self.ebo.as_ref());
+        unsafe {
+            let ebo_array = js_sys::Uint16Array::view(&self.idxs);
+            context.buffer_data_with_array_buffer_view(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, &ebo_array, WebGlRenderingContext::STATIC_DRAW);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Adds a quad centred on `x` and `y`, in world coordinates.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push(&mut self, x: f32, y: f32, width: f32, height: f32, win_width: f32, win_height: f32) {
+        let left = (2.0*(x - width/2.0)/win_width) - 1.0;
+        let right = (2.0*(x + width/2.0)/win_width) - 1.0;
+        let bottom = (2.0*(y - height/2.0)/win_height) - 1.0;
+        let top = (2.0*(y + height/2.0)/win_height) - 1.0;
+        self.vertices.extend_from_slice(&[right, bottom, left, bottom, left, top, right, top]);
+    }
+
+    /// Draws every quad added since the last `clear`.
+    pub fn draw(&self, context: &WebGlRenderingContext) {
+        // Bind EBO
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, self.ebo.as_ref());
+
+        for chunk in self.vertices.chunks(MAX_QUADS * 4 * 2) {
+            // Bind and set VBO
+            context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.vbo.as_ref());
+            unsafe {
+                let vbo_array = js_sys::Float32Array::view(chunk);
+                context.buffer_data_with_array_buffer_view(WebGlRenderingContext::ARRAY_BUFFER, &vbo_array, WebGlRenderingContext::DYNAMIC_DRAW);
+            }
+
+            // Vertex position
+            context.vertex_attrib_pointer_with_i32(0, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+            context.enable_vertex_attrib_array(0);
+
+            // Unbind VBO
+            context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, None);
+
+            // Draw
+            let quads = chunk.len() / (4 * 2);
+            context.draw_elements_with_i32(WebGlRenderingContext::TRIANGLES, (quads * 6) as i32, WebGlRenderingContext::UNSIGNED_SHORT, 0);
+        }
+    }
+}
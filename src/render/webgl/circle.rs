@@ -0,0 +1,97 @@
+use web_sys::{WebGlBuffer, WebGlRenderingContext};
+
+const NUM_VERT: usize = 32;
+
+pub struct CircleMesh {
+    vertices: [f32; (NUM_VERT + 1) * 2],
+    idxs: [u16; NUM_VERT * 3],
+    vbo: Option<WebGlBuffer>,
+    ebo: Option<WebGlBuffer>
+}
+
+impl CircleMesh {
+    pub fn new() -> CircleMesh {
+        CircleMesh {
+            vertices: [0.0; (NUM_VERT + 1) * 2],
+            idxs: [0; NUM_VERT * 3],
+            vbo: None,
+            ebo: None
+        }
+    }
+
+    fn update_vertices(&mut self, x: f32, y: f32, radius: f32, win_width: f32, win_height: f32) {
+        use std::f32::consts::PI;
+
+        // Origin of the circle
+        self.vertices[0] = 2.0*(x / win_width) - 1.0;
+        self.vertices[1] = 2.0*(y / win_height) - 1.0;
+
+        for i in (2..self.vertices.len()).step_by(2) {
+            let theta = (((i-2) as f32)/((self.vertices.len()-2) as f32)) * 2.0 * PI;
+            self.vertices[i] = (2.0*(radius * theta.cos() + x) / win_width) - 1.0;
+            self.vertices[i+1] = (2.0*(radius * theta.sin() + y) / win_height) - 1.0;
+        }
+    }
+
+    pub fn init_gl(&mut self, context: &WebGlRenderingContext) {
+        // Indices
+        for i in (0..self.idxs.len()-3).step_by(3) {
+            self.idxs[i] = 0;
+            self.idxs[i+1] = (i/3 + 1) as u16;
+            self.idxs[i+2] = (i/3 + 2) as u16;
+        }
+        self.idxs[self.idxs.len()-3] = 0;
+        self.idxs[self.idxs.len()-2] = (self.idxs.len() as u16)/3;
+        self.idxs[self.idxs.len()-1] = 1;
+
+        // Create VBO and EBO
+        self.vbo = context.create_buffer();
+        self.ebo = context.create_buffer();
+
+        // Bind and set VBO
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.vbo.as_ref());
+        unsafe {
+            let vbo_array = js_sys::Float32Array::view(&self.vertices);
+            context.buffer_data_with_array_buffer_view(WebGlRenderingContext::ARRAY_BUFFER, &vbo_array, WebGlRenderingContext::DYNAMIC_DRAW);
+        }
+
+        // Bind and set EBO
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, self.ebo.as_ref());
+        unsafe {
+            let ebo_array = js_sys::Uint16Array::view(&self.idxs);
+            context.buffer_data_with_array_buffer_view(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, &ebo_array, WebGlRenderingContext::STATIC_DRAW);
+        }
+
+        // Vertex position
+        context.vertex_attrib_pointer_with_i32(0, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+        context.enable_vertex_attrib_array(0);
+
+        // Unbind VBO
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, None);
+    }
+
+    pub fn draw(&mut self, context: &WebGlRenderingContext, x: f32, y: f32, radius: f32, win_width: f32, win_height: f32) {
+        // Vertices
+        self.update_vertices(x, y, radius, win_width, win_height);
+
+        // Bind and set VBO
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.vbo.as_ref());
+        unsafe {
+            let vbo_array = js_sys::Float32Array::view(&self.vertices);
+            context.buffer_sub_data_with_i32_and_array_buffer_view(WebGlRenderingContext::ARRAY_BUFFER, 0, &vbo_array);
+        }
+
+        // Bind EBO
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, self.ebo.as_ref());
+
+        // Vertex position
+        context.vertex_attrib_pointer_with_i32(0, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+        context.enable_vertex_attrib_array(0);
+
+        // Unbind VBO
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, None);
+
+        // Draw
+        context.draw_elements_with_i32(WebGlRenderingContext::TRIANGLES, self.idxs.len() as i32, WebGlRenderingContext::UNSIGNED_SHORT, 0);
+    }
+}
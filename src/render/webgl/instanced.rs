@@ -0,0 +1,158 @@
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlVertexArrayObject};
+
+use super::shader::ShaderManager;
+
+/// The program circles and quads are drawn with in WebGL 2, with `color` and `world_size` uniforms.
+pub const INSTANCED_PROGRAM: &str = "instanced";
+
+const NUM_VERT: usize = 32;
+
+// Each instance is a centre and a size in world coordinates, which stretch a shape of size 1 around the origin
+const VERT_SHADER_SRC: &str = r#"#version 300 es
+    in vec2 attr_position;
+    in vec4 attr_instance;
+    uniform vec2 world_size;
+    void main() {
+        vec2 position = attr_instance.xy + attr_position * attr_instance.zw;
+        gl_Position = vec4(position / world_size * 2.0 - 1.0, 0.0, 1.0);
+    }
+"#;
+
+const FRAG_SHADER_SRC: &str = r#"#version 300 es
+    precision mediump float;
+    uniform vec4 color;
+    out vec4 frag_color;
+    void main() {
+        frag_color = color;
+    }
+"#;
+
+/// Any number of circles or quads of one colour in a single instanced draw call, with a vertex array object for
+/// each shape.
+pub struct InstancedShapes {
+    instances: Vec<f32>,
+    quad_vao: Option<WebGlVertexArrayObject>,
+    circle_vao: Option<WebGlVertexArrayObject>,
+    quad_vbo: Option<WebGlBuffer>,
+    circle_vbo: Option<WebGlBuffer>,
+    instance_vbo: Option<WebGlBuffer>
+}
+
+impl InstancedShapes {
+    pub fn new() -> InstancedShapes {
+        InstancedShapes {
+            instances: Vec::new(),
+            quad_vao: None,
+            circle_vao: None,
+            quad_vbo: None,
+            circle_vbo: None,
+            instance_vbo: None
+        }
+    }
+
+    /// Adds the program the shapes are drawn with to `shaders`.
+    pub fn load_shaders(shaders: &mut ShaderManager) -> Result<(), String> {
+        shaders.load(INSTANCED_PROGRAM, VERT_SHADER_SRC, FRAG_SHADER_SRC)
+    }
+
+    pub fn init_gl(&mut self, context: &WebGl2RenderingContext) {
+        use std::f32::consts::PI;
+
+        // A quad of size 1, as a strip
+        let quad = [-0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, 0.5];
+
+        // A circle of radius 1, as a fan from the middle that goes all the way round
+        let mut circle = vec![0.0, 0.0];
+        for i in 0..=NUM_VERT {
+            let theta = i as f32 / NUM_VERT as f32 * 2.0 * PI;
+            circle.extend_from_slice(&[theta.cos(), theta.sin()]);
+        }
+
+        // Create VBOs
+        self.instance_vbo = context.create_buffer();
+        self.quad_vbo = Self::static_buffer(context, &quad);
+        self.circle_vbo = Self::static_buffer(context, &circle);
+
+        // Create VAOs
+        self.quad_vao = self.vertex_array(context, self.quad_vbo.as_ref());
+        self.circle_vao = self.vertex_array(context, self.circle_vbo.as_ref());
+    }
+
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    /// Adds a shape centred on `x` and `y`, `width` by `height` for quads and with those radiuses for circles, in
+    /// world coordinates.
+    pub fn push(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.instances.extend_from_slice(&[x, y, width, height]);
+    }
+
+    /// Draws every shape added since the last `clear` as a quad.
+    pub fn draw_quads(&self, context: &WebGl2RenderingContext) {
+        self.draw(context, self.quad_vao.as_ref(), WebGl2RenderingContext::TRIANGLE_STRIP, 4);
+    }
+
+    /// Draws every shape added since the last `clear` as a circle.
+    pub fn draw_circles(&self, context: &WebGl2RenderingContext) {
+        self.draw(context, self.circle_vao.as_ref(), WebGl2RenderingContext::TRIANGLE_FAN, NUM_VERT as i32 + 2);
+    }
+
+    fn draw(&self, context: &WebGl2RenderingContext, vao: Option<&WebGlVertexArrayObject>, mode: u32, vertices: i32) {
+        if self.instances.is_empty() {
+            return;
+        }
+
+        // Set instances
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, self.instance_vbo.as_ref());
+        unsafe {
+            let instance_array = js_sys::Float32Array::view(&self.instances);
+            context.buffer_data_with_array_buffer_view(WebGl2RenderingContext::ARRAY_BUFFER, &instance_array, WebGl2RenderingContext::DYNAMIC_DRAW);
+        }
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+
+        // Draw, leaving the default VAO bound for everything else
+        context.bind_vertex_array(vao);
+        context.draw_arrays_instanced(mode, 0, vertices, (self.instances.len() / 4) as i32);
+        context.bind_vertex_array(None);
+    }
+
+    fn static_buffer(context: &WebGl2RenderingContext, vertices: &[f32]) -> Option<WebGlBuffer> {
+        let vbo = context.create_buffer();
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, vbo.as_ref());
+        unsafe {
+            let vbo_array = js_sys::Float32Array::view(vertices);
+            context.buffer_data_with_array_buffer_view(WebGl2RenderingContext::ARRAY_BUFFER, &vbo_array, WebGl2RenderingContext::STATIC_DRAW);
+        }
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+        vbo
+    }
+
+    // Vertices of the shape at location 0, and one instance after the other at location 1
+    fn vertex_array(&self, context: &WebGl2RenderingContext, vbo: Option<&WebGlBuffer>) -> Option<WebGlVertexArrayObject> {
+        let vao = context.create_vertex_array();
+        context.bind_vertex_array(vao.as_ref());
+
+        // Vertex position
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, vbo);
+        context.vertex_attrib_pointer_with_i32(0, 2, WebGl2RenderingContext::FLOAT, false, 0, 0);
+        context.enable_vertex_attrib_array(0);
+
+        // Instance centre and size
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, self.instance_vbo.as_ref());
+        context.vertex_attrib_pointer_with_i32(1, 4, WebGl2RenderingContext::FLOAT, false, 0, 0);
+        context.enable_vertex_attrib_array(1);
+        context.vertex_attrib_divisor(1, 1);
+
+        // Unbind
+        context.bind_vertex_array(None);
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+        vao
+    }
+}
+
+impl Default for InstancedShapes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,345 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{WebGl2RenderingContext, WebGlRenderingContext};
+
+use crate::effects::particles::Particle;
+use crate::effects::trail::Trail;
+use crate::render::{Render, RenderBackend, get_net};
+use crate::render::overlay::Overlay;
+use crate::render::post::PostEffects;
+use crate::render::theme::Theme;
+use crate::world::World;
+
+pub mod batch;
+pub mod circle;
+pub mod instanced;
+pub mod post;
+pub mod shader;
+use batch::QuadBatch;
+use circle::CircleMesh;
+use instanced::{INSTANCED_PROGRAM, InstancedShapes};
+use post::PostPipeline;
+use shader::{ShaderManager, Uniform};
+
+/// The program everything but the post processing is drawn with in WebGL 1, with a `color` uniform.
+pub const COLOR_PROGRAM: &str = "color";
+
+// Particles fade out in this many steps of transparency
+const FADE_LEVELS: u32 = 8;
+
+/// A WebGL 2 context where the browser has one, or else a WebGL 1 context.
+pub enum GlContext {
+    WebGl1(WebGlRenderingContext),
+    WebGl2(WebGl2RenderingContext)
+}
+
+impl GlContext {
+    /// The best context `canvas` has, if it has WebGL at all.
+    pub fn from_canvas(canvas: &web_sys::HtmlCanvasElement) -> Result<Option<GlContext>, JsValue> {
+        if let Some(context) = canvas.get_context("webgl2")? {
+            return Ok(Some(GlContext::WebGl2(context.dyn_into()?)));
+        }
+        match canvas.get_context("webgl")? {
+            Some(context) => Ok(Some(GlContext::WebGl1(context.dyn_into()?))),
+            None => Ok(None)
+        }
+    }
+
+    /// The context through the functions of WebGL 1, which WebGL 2 has all of.
+    pub fn get_webgl1(&self) -> WebGlRenderingContext {
+        match self {
+            GlContext::WebGl1(context) => context.clone(),
+            GlContext::WebGl2(context) => context.clone().unchecked_into()
+        }
+    }
+}
+
+// How circles and quads get drawn
+enum Shapes {
+    // One draw call for every circle, and quads in batches
+    WebGl1 {
+        ball_mesh: Box<CircleMesh>,
+        quad_batch: QuadBatch
+    },
+    // One instanced draw call for each colour
+    WebGl2 {
+        context: WebGl2RenderingContext,
+        shapes: InstancedShapes
+    }
+}
+
+pub struct WebGlRenderer {
+    // Only through the functions of WebGL 1, whatever the version
+    context: WebGlRenderingContext,
+    shapes: Shapes,
+    shaders: ShaderManager,
+    // Of the program shapes are drawn with, looked up again every frame in case it was reloaded
+    color_uniform: Uniform<[f32; 4]>,
+    world_size_uniform: Uniform<[f32; 2]>,
+    theme: Theme,
+    // Everything in the arena moves this far while the screen shakes
+    shake_offset: (f32, f32),
+    // How much of the flash of each paddle is left, from 0 to 1
+    flash: [f32; 2],
+
+    post: PostPipeline
+}
+
+impl WebGlRenderer {
+    /// Draws with instancing in WebGL 2, or with `vert_shader_src` and `frag_shader_src` as the `COLOR_PROGRAM` in
+    /// WebGL 1.
+    pub fn new(context: GlContext, vert_shader_src: &str, frag_shader_src: &str) -> Result<WebGlRenderer, String> {
+        let webgl1 = context.get_webgl1();
+        let mut shaders = ShaderManager::new(webgl1.clone());
+        let shapes = match context {
+            GlContext::WebGl1(context) => {
+                shaders.load(COLOR_PROGRAM, vert_shader_src, frag_shader_src)?;
+                let mut ball_mesh = Box::new(CircleMesh::new());
+                ball_mesh.init_gl(&context);
+                let mut quad_batch = QuadBatch::new();
+                quad_batch.init_gl(&context);
+                Shapes::WebGl1 { ball_mesh, quad_batch }
+            },
+            GlContext::WebGl2(context) => {
+                InstancedShapes::load_shaders(&mut shaders)?;
+                let mut shapes = InstancedShapes::new();
+                shapes.init_gl(&context);
+                Shapes::WebGl2 { context, shapes }
+            }
+        };
+
+        // The game is still playable without the CRT effects
+        let mut post = PostPipeline::new();
+        PostPipeline::load_shaders(&mut shaders)
+            .and_then(|_| post.init_gl(&webgl1, webgl1.drawing_buffer_width(), webgl1.drawing_buffer_height()))
+            .unwrap_or_else(|err| web_sys::console::warn_1(&format!("CRT effects are unavailable: {}", err).into()));
+
+        Ok(WebGlRenderer {
+            context: webgl1,
+            shapes,
+            shaders,
+            color_uniform: Uniform::none(),
+            world_size_uniform: Uniform::none(),
+            theme: Theme::default(),
+            shake_offset: (0.0, 0.0),
+            flash: [0.0, 0.0],
+
+            post
+        })
+    }
+
+    fn shapes_program(&self) -> &'static str {
+        match self.shapes {
+            Shapes::WebGl1 { .. } => COLOR_PROGRAM,
+            Shapes::WebGl2 { .. } => INSTANCED_PROGRAM
+        }
+    }
+
+    // Quads as centre and size, in the current colour
+    fn fill_rects(&mut self, rects: &[[f32; 4]], win_width: f32, win_height: f32) {
+        if rects.is_empty() {
+            return;
+        }
+        match &mut self.shapes {
+            Shapes::WebGl1 { quad_batch, .. } => {
+                quad_batch.clear();
+                for [x, y, width, height] in rects {
+                    quad_batch.push(*x, *y, *width, *height, win_width, win_height);
+                }
+                quad_batch.draw(&self.context);
+            },
+            Shapes::WebGl2 { context, shapes } => {
+                self.world_size_uniform.set(&self.context, [win_width, win_height]);
+                shapes.clear();
+                for [x, y, width, height] in rects {
+                    shapes.push(*x, *y, *width, *height);
+                }
+                shapes.draw_quads(context);
+            }
+        }
+    }
+
+    // Circles as centre and radius, in the current colour
+    fn fill_circles(&mut self, circles: &[[f32; 3]], win_width: f32, win_height: f32) {
+        match &mut self.shapes {
+            Shapes::WebGl1 { ball_mesh, .. } => {
+                for [x, y, radius] in circles {
+                    ball_mesh.draw(&self.context, *x, *y, *radius, win_width, win_height);
+                }
+            },
+            Shapes::WebGl2 { context, shapes } => {
+                self.world_size_uniform.set(&self.context, [win_width, win_height]);
+                shapes.clear();
+                for [x, y, radius] in circles {
+                    shapes.push(*x, *y, *radius, *radius);
+                }
+                shapes.draw_circles(context);
+            }
+        }
+    }
+
+    fn set_color(&self, color: [f32; 3]) {
+        self.set_color_alpha(color, 1.0);
+    }
+
+    fn set_color_alpha(&self, [red, green, blue]: [f32; 3], alpha: f32) {
+        self.color_uniform.set(&self.context, [red, green, blue, alpha]);
+    }
+}
+
+impl Render for WebGlRenderer {
+    fn render(&mut self, world: &World) {
+        let win_width = world.get_width();
+        let win_height = world.get_height();
+
+        // Clear
+        self.clear();
+
+        // Draw
+        let (offset_x, offset_y) = self.shake_offset;
+        self.set_color(self.theme.get_foreground());
+
+        let net: Vec<[f32; 4]> = get_net(world).iter()
+            .map(|[x, y, width, height]| [x + offset_x, y + offset_y, *width, *height])
+            .collect();
+        self.fill_rects(&net, win_width, win_height);
+
+        let balls: Vec<[f32; 3]> = world.get_balls().iter()
+            .map(|ball| [ball.get_x() + offset_x, ball.get_y() + offset_y, ball.get_radius()])
+            .collect();
+        self.fill_circles(&balls, win_width, win_height);
+
+        let p1 = world.get_p1();
+        self.set_color(self.theme.get_paddle_color(self.flash[0]));
+        self.fill_rects(&[[p1.get_x() + offset_x, p1.get_y() + offset_y, p1.get_width(), p1.get_height()]], win_width, win_height);
+
+        let p2 = world.get_p2();
+        self.set_color(self.theme.get_paddle_color(self.flash[1]));
+        self.fill_rects(&[[p2.get_x() + offset_x, p2.get_y() + offset_y, p2.get_width(), p2.get_height()]], win_width, win_height);
+    }
+}
+
+impl RenderBackend for WebGlRenderer {
+    fn get_version(&self) -> &'static str {
+        match self.shapes {
+            Shapes::WebGl1 { .. } => "WebGL 1",
+            Shapes::WebGl2 { .. } => "WebGL 2"
+        }
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn set_post_effects(&mut self, effects: PostEffects) {
+        self.post.set_effects(effects);
+    }
+
+    // Into the post processing pipeline if any of its effects are on
+    fn begin_frame(&mut self) {
+        self.post.begin_frame(&self.context, &self.shaders);
+        if let Some(program) = self.shaders.use_program(self.shapes_program()) {
+            self.color_uniform = program.uniform(&self.context, "color");
+            self.world_size_uniform = program.uniform(&self.context, "world_size");
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.post.end_frame(&self.context, &self.shaders, self.theme.get_background());
+    }
+
+    // On the GPU, with the programs it had
+    fn restore(&mut self) -> Result<(), String> {
+        match &mut self.shapes {
+            Shapes::WebGl1 { ball_mesh, quad_batch } => {
+                ball_mesh.init_gl(&self.context);
+                quad_batch.init_gl(&self.context);
+            },
+            Shapes::WebGl2 { context, shapes } => shapes.init_gl(context)
+        }
+        // The game is still playable without the CRT effects, but not without the program shapes are drawn with
+        let restored = self.shaders.restore();
+        self.post.init_gl(&self.context, self.context.drawing_buffer_width(), self.context.drawing_buffer_height())
+            .unwrap_or_else(|err| web_sys::console::warn_1(&format!("CRT effects are unavailable: {}", err).into()));
+        match self.shaders.get(self.shapes_program()) {
+            Some(_) => {
+                restored.unwrap_or_else(|err| web_sys::console::warn_1(&err.into()));
+                Ok(())
+            },
+            None => restored
+        }
+    }
+
+    fn reload_shader(&mut self, name: &str, vert_shader_src: &str, frag_shader_src: &str) -> Result<(), String> {
+        if self.shaders.get(name).is_none() {
+            return Err(format!("No shader called {:?}, expected one of {}", name, self.shaders.get_names().join(", ")));
+        }
+        self.shaders.load(name, vert_shader_src, frag_shader_src)
+    }
+
+    fn get_shader_source(&self, name: &str) -> Option<(&str, &str)> {
+        self.shaders.get(name).map(|program| (program.get_vert_shader_src(), program.get_frag_shader_src()))
+    }
+
+    fn set_shake(&mut self, x: f32, y: f32) {
+        self.shake_offset = (x, y);
+    }
+
+    fn set_flash(&mut self, p1: f32, p2: f32) {
+        self.flash = [p1.clamp(0.0, 1.0), p2.clamp(0.0, 1.0)];
+    }
+
+    fn clear(&mut self) {
+        let [red, green, blue] = self.theme.get_background();
+        self.context.clear_color(red, green, blue, 1.0);
+        self.context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    }
+
+    fn render_overlay(&mut self, overlay: &Overlay, win_width: f32, win_height: f32) {
+        for (highlight, color) in [(false, self.theme.get_foreground()), (true, self.theme.get_highlight())].iter() {
+            let rects: Vec<[f32; 4]> = overlay.get_rects().iter()
+                .filter(|rect| rect.highlight == *highlight)
+                .map(|rect| [rect.x, rect.y, rect.width, rect.height])
+                .collect();
+            self.set_color(*color);
+            self.fill_rects(&rects, win_width, win_height);
+        }
+    }
+
+    // Together, one draw call for each step of the fade
+    fn render_particles(&mut self, particles: &[Particle], win_width: f32, win_height: f32) {
+        if particles.is_empty() {
+            return;
+        }
+
+        self.context.enable(WebGlRenderingContext::BLEND);
+        self.context.blend_func(WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA);
+        let (offset_x, offset_y) = self.shake_offset;
+        for level in 1..=FADE_LEVELS {
+            let rects: Vec<[f32; 4]> = particles.iter()
+                .filter(|particle| (particle.get_fade() * FADE_LEVELS as f32).ceil() as u32 == level)
+                .map(|particle| [particle.x + offset_x, particle.y + offset_y, particle.size, particle.size])
+                .collect();
+            self.set_color_alpha(self.theme.get_highlight(), level as f32 / FADE_LEVELS as f32);
+            self.fill_rects(&rects, win_width, win_height);
+        }
+        self.context.disable(WebGlRenderingContext::BLEND);
+    }
+
+    fn render_trail(&mut self, trail: &Trail, radius: f32, win_width: f32, win_height: f32) {
+        // The newest point is where the ball already is
+        if trail.len() < 2 {
+            return;
+        }
+
+        self.context.enable(WebGlRenderingContext::BLEND);
+        self.context.blend_func(WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA);
+        let (offset_x, offset_y) = self.shake_offset;
+        let count = trail.len() as f32;
+        for (i, (x, y)) in trail.get_points().enumerate().skip(1) {
+            let fade = 1.0 - i as f32 / count;
+            self.set_color_alpha(self.theme.get_foreground(), fade * 0.5);
+            self.fill_circles(&[[x + offset_x, y + offset_y, radius * (0.5 + fade / 2.0)]], win_width, win_height);
+        }
+        self.context.disable(WebGlRenderingContext::BLEND);
+    }
+}
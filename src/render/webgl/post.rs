@@ -0,0 +1,303 @@
+use web_sys::{WebGlBuffer, WebGlFramebuffer, WebGlRenderingContext, WebGlTexture};
+
+use crate::render::post::PostEffects;
+
+use super::shader::ShaderManager;
+
+/// The program that blurs the bloom.
+pub const BLUR_PROGRAM: &str = "blur";
+
+/// The program that puts frames on the canvas.
+pub const CRT_PROGRAM: &str = "crt";
+
+// The glow is blurred at this fraction of the size of the canvas, which is cheaper and spreads it further
+const BLOOM_SCALE: i32 = 2;
+
+// Only what is brighter than this glows
+const BLOOM_THRESHOLD: f32 = 0.5;
+const BLOOM_STRENGTH: f32 = 0.8;
+
+// How dark the dark rows of the scanlines get, from 0 to 1
+const SCANLINE_STRENGTH: f32 = 0.35;
+
+// How far the corners bulge, as a fraction of the distance to the middle
+const BARREL_STRENGTH: f32 = 0.08;
+
+// How many pixels red and blue split apart in the corners
+const CHROMATIC_PIXELS: f32 = 3.0;
+
+// A rectangle covering the whole canvas, with its texture coordinates worked out from the positions
+const VERT_SHADER_SRC: &str = r#"#version 100
+    attribute vec2 attr_position;
+    varying vec2 uv;
+    void main() {
+        uv = attr_position * 0.5 + 0.5;
+        gl_Position = vec4(attr_position, 0.0, 1.0);
+    }
+"#;
+
+// Gaussian blur along `direction`, one texel long, of whatever is brighter than `threshold`
+const BLUR_SHADER_SRC: &str = r#"#version 100
+    precision mediump float;
+    uniform sampler2D source;
+    uniform vec2 direction;
+    uniform float threshold;
+    varying vec2 uv;
+
+    vec3 bright(vec2 at) {
+        vec3 color = texture2D(source, at).rgb;
+        return max(color - threshold, 0.0) / (1.0 - threshold);
+    }
+
+    void main() {
+        vec3 sum = bright(uv) * 0.2270;
+        sum += (bright(uv + direction) + bright(uv - direction)) * 0.1946;
+        sum += (bright(uv + 2.0 * direction) + bright(uv - 2.0 * direction)) * 0.1216;
+        sum += (bright(uv + 3.0 * direction) + bright(uv - 3.0 * direction)) * 0.0541;
+        sum += (bright(uv + 4.0 * direction) + bright(uv - 4.0 * direction)) * 0.0162;
+        gl_FragColor = vec4(sum, 1.0);
+    }
+"#;
+
+// Puts the frame on the screen through the curved glass of a CRT, with the glow on top and the scanlines over it
+const CRT_SHADER_SRC: &str = r#"#version 100
+    precision mediump float;
+    uniform sampler2D scene;
+    uniform sampler2D bloom;
+    uniform vec2 resolution;
+    uniform vec3 border;
+    uniform float scanlines;
+    uniform float bloom_strength;
+    uniform float barrel;
+    uniform float chromatic;
+    varying vec2 uv;
+
+    void main() {
+        vec2 centered = uv * 2.0 - 1.0;
+        vec2 at = centered * (1.0 + barrel * dot(centered, centered)) * 0.5 + 0.5;
+        if (at.x < 0.0 || at.x > 1.0 || at.y < 0.0 || at.y > 1.0) {
+            gl_FragColor = vec4(border, 1.0);
+            return;
+        }
+
+        vec2 split = (at - 0.5) * 2.0 * chromatic / resolution;
+        vec3 color = vec3(texture2D(scene, at + split).r, texture2D(scene, at).g, texture2D(scene, at - split).b);
+        color += texture2D(bloom, at).rgb * bloom_strength;
+        float row = 0.5 + 0.5 * cos(at.y * resolution.y * 3.14159265);
+        color *= 1.0 - scanlines * (1.0 - row);
+        gl_FragColor = vec4(color, 1.0);
+    }
+"#;
+
+/// A texture that can be drawn into.
+struct RenderTarget {
+    framebuffer: Option<WebGlFramebuffer>,
+    texture: Option<WebGlTexture>,
+    width: i32,
+    height: i32
+}
+
+impl RenderTarget {
+    fn new(context: &WebGlRenderingContext, width: i32, height: i32) -> Result<RenderTarget, String> {
+        let texture = context.create_texture();
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, texture.as_ref());
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGlRenderingContext::TEXTURE_2D, 0, WebGlRenderingContext::RGBA as i32, width, height, 0,
+            WebGlRenderingContext::RGBA, WebGlRenderingContext::UNSIGNED_BYTE, None
+        ).map_err(|_| String::from("Unable to create a texture to draw into"))?;
+        // Textures that are not a power of two in size can only be filtered and wrapped like this in WebGL 1
+        for (parameter, value) in [
+            (WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::LINEAR),
+            (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::LINEAR),
+            (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+            (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE)
+        ] {
+            context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, parameter, value as i32);
+        }
+
+        let framebuffer = context.create_framebuffer();
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, framebuffer.as_ref());
+        context.framebuffer_texture_2d(WebGlRenderingContext::FRAMEBUFFER, WebGlRenderingContext::COLOR_ATTACHMENT0, WebGlRenderingContext::TEXTURE_2D, texture.as_ref(), 0);
+        let status = context.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+
+        // Unbind
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, None);
+
+        if status != WebGlRenderingContext::FRAMEBUFFER_COMPLETE {
+            return Err(format!("Unable to draw into a texture (framebuffer status {:#x})", status));
+        }
+        Ok(RenderTarget { framebuffer, texture, width, height })
+    }
+
+    fn delete(&self, context: &WebGlRenderingContext) {
+        context.delete_framebuffer(self.framebuffer.as_ref());
+        context.delete_texture(self.texture.as_ref());
+    }
+
+    // Draws into the target from now on
+    fn bind(&self, context: &WebGlRenderingContext) {
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, self.framebuffer.as_ref());
+        context.viewport(0, 0, self.width, self.height);
+    }
+}
+
+/// Draws frames into a texture instead of the canvas, then puts them on the canvas through full screen passes: a
+/// two pass blur for the bloom, then one pass for everything else. Without any effects on, frames are drawn
+/// straight onto the canvas.
+pub struct PostPipeline {
+    effects: PostEffects,
+    width: i32,
+    height: i32,
+    quad: Option<WebGlBuffer>,
+    scene: Option<RenderTarget>,
+    // Ping pong between the two halves of the blur
+    bloom: Vec<RenderTarget>
+}
+
+impl PostPipeline {
+    pub fn new() -> PostPipeline {
+        PostPipeline {
+            effects: PostEffects::default(),
+            width: 0,
+            height: 0,
+            quad: None,
+            scene: None,
+            bloom: Vec::new()
+        }
+    }
+
+    /// Adds the programs of the pipeline to `shaders`.
+    pub fn load_shaders(shaders: &mut ShaderManager) -> Result<(), String> {
+        shaders.load(BLUR_PROGRAM, VERT_SHADER_SRC, BLUR_SHADER_SRC)?;
+        shaders.load(CRT_PROGRAM, VERT_SHADER_SRC, CRT_SHADER_SRC)
+    }
+
+    /// Sets up the targets for a canvas of `width` by `height` pixels. The pipeline stays off if it fails.
+    pub fn init_gl(&mut self, context: &WebGlRenderingContext, width: i32, height: i32) -> Result<(), String> {
+        self.scene = None;
+        self.width = width;
+        self.height = height;
+
+        // Two triangles covering the canvas
+        self.quad = context.create_buffer();
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.quad.as_ref());
+        unsafe {
+            let vbo_array = js_sys::Float32Array::view(&[-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0]);
+            context.buffer_data_with_array_buffer_view(WebGlRenderingContext::ARRAY_BUFFER, &vbo_array, WebGlRenderingContext::STATIC_DRAW);
+        }
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, None);
+
+        let (bloom_width, bloom_height) = ((width / BLOOM_SCALE).max(1), (height / BLOOM_SCALE).max(1));
+        self.bloom = vec![RenderTarget::new(context, bloom_width, bloom_height)?, RenderTarget::new(context, bloom_width, bloom_height)?];
+        // Only a complete pipeline is used
+        self.scene = Some(RenderTarget::new(context, width, height)?);
+        Ok(())
+    }
+
+    pub fn set_effects(&mut self, effects: PostEffects) {
+        self.effects = effects;
+    }
+
+    /// Whether frames go through the passes, which needs an effect on and everything set up.
+    pub fn is_active(&self, shaders: &ShaderManager) -> bool {
+        self.effects.any() && self.scene.is_some() && shaders.get(CRT_PROGRAM).is_some()
+    }
+
+    /// Starts a frame: everything drawn until `end_frame` goes into the pipeline when it is active, or straight onto
+    /// the canvas. The targets are set up again for a canvas that changed size.
+    pub fn begin_frame(&mut self, context: &WebGlRenderingContext, shaders: &ShaderManager) {
+        let (width, height) = (context.drawing_buffer_width(), context.drawing_buffer_height());
+        if (width, height) != (self.width, self.height) {
+            self.delete(context);
+            self.init_gl(context, width, height)
+                .unwrap_or_else(|err| web_sys::console::warn_1(&format!("CRT effects are unavailable: {}", err).into()));
+        }
+
+        match self.scene.as_ref().filter(|_| self.is_active(shaders)) {
+            Some(scene) => scene.bind(context),
+            None => {
+                context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+                context.viewport(0, 0, self.width, self.height);
+            }
+        }
+    }
+
+    /// Puts the frame on the canvas through the passes, with the programs in `shaders`, on a `border` coloured
+    /// background where the screen bulges away from the edges. Leaves another program in use.
+    pub fn end_frame(&self, context: &WebGlRenderingContext, shaders: &ShaderManager, border: [f32; 3]) {
+        let scene = match self.scene.as_ref().filter(|_| self.is_active(shaders)) {
+            Some(scene) => scene,
+            None => return
+        };
+
+        // Bloom, blurred across then up
+        if let Some(blur) = shaders.use_program(BLUR_PROGRAM).filter(|_| self.effects.bloom) {
+            blur.uniform::<i32>(context, "source").set(context, 0);
+            let direction = blur.uniform::<[f32; 2]>(context, "direction");
+            let threshold = blur.uniform::<f32>(context, "threshold");
+            let passes = [
+                (scene, &self.bloom[0], [1.0 / scene.width as f32, 0.0], BLOOM_THRESHOLD),
+                (&self.bloom[0], &self.bloom[1], [0.0, 1.0 / self.bloom[0].height as f32], 0.0)
+            ];
+            for (source, target, step, cutoff) in passes.iter() {
+                target.bind(context);
+                context.active_texture(WebGlRenderingContext::TEXTURE0);
+                context.bind_texture(WebGlRenderingContext::TEXTURE_2D, source.texture.as_ref());
+                direction.set(context, *step);
+                threshold.set(context, *cutoff);
+                self.draw_quad(context);
+            }
+        }
+
+        // CRT
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        context.viewport(0, 0, self.width, self.height);
+        let crt = match shaders.use_program(CRT_PROGRAM) {
+            Some(crt) => crt,
+            None => return
+        };
+        context.active_texture(WebGlRenderingContext::TEXTURE0);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, scene.texture.as_ref());
+        context.active_texture(WebGlRenderingContext::TEXTURE1);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, self.bloom[1].texture.as_ref());
+
+        let strength = |on: bool, value: f32| if on { value } else { 0.0 };
+        crt.uniform::<i32>(context, "scene").set(context, 0);
+        crt.uniform::<i32>(context, "bloom").set(context, 1);
+        crt.uniform::<[f32; 2]>(context, "resolution").set(context, [self.width as f32, self.height as f32]);
+        crt.uniform::<[f32; 3]>(context, "border").set(context, border);
+        crt.uniform::<f32>(context, "scanlines").set(context, strength(self.effects.scanlines, SCANLINE_STRENGTH));
+        crt.uniform::<f32>(context, "bloom_strength").set(context, strength(self.effects.bloom, BLOOM_STRENGTH));
+        crt.uniform::<f32>(context, "barrel").set(context, strength(self.effects.barrel, BARREL_STRENGTH));
+        crt.uniform::<f32>(context, "chromatic").set(context, strength(self.effects.chromatic, CHROMATIC_PIXELS));
+        self.draw_quad(context);
+
+        // Unbind
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, None);
+        context.active_texture(WebGlRenderingContext::TEXTURE0);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, None);
+    }
+
+    // Frees the buffer and targets on the GPU, before making new ones
+    fn delete(&mut self, context: &WebGlRenderingContext) {
+        context.delete_buffer(self.quad.take().as_ref());
+        for target in self.scene.take().into_iter().chain(self.bloom.drain(..)) {
+            target.delete(context);
+        }
+    }
+
+    fn draw_quad(&self, context: &WebGlRenderingContext) {
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.quad.as_ref());
+        context.vertex_attrib_pointer_with_i32(0, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+        context.enable_vertex_attrib_array(0);
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, None);
+        context.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+    }
+}
+
+impl Default for PostPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use web_sys::{WebGlProgram, WebGlRenderingContext, WebGlShader, WebGlUniformLocation};
+
+/// A type that a uniform can hold.
+pub trait UniformValue: Copy {
+    fn set(context: &WebGlRenderingContext, location: Option<&WebGlUniformLocation>, value: Self);
+}
+
+impl UniformValue for i32 {
+    fn set(context: &WebGlRenderingContext, location: Option<&WebGlUniformLocation>, value: i32) {
+        context.uniform1i(location, value);
+    }
+}
+
+impl UniformValue for f32 {
+    fn set(context: &WebGlRenderingContext, location: Option<&WebGlUniformLocation>, value: f32) {
+        context.uniform1f(location, value);
+    }
+}
+
+impl UniformValue for [f32; 2] {
+    fn set(context: &WebGlRenderingContext, location: Option<&WebGlUniformLocation>, [x, y]: [f32; 2]) {
+        context.uniform2f(location, x, y);
+    }
+}
+
+impl UniformValue for [f32; 3] {
+    fn set(context: &WebGlRenderingContext, location: Option<&WebGlUniformLocation>, [x, y, z]: [f32; 3]) {
+        context.uniform3f(location, x, y, z);
+    }
+}
+
+impl UniformValue for [f32; 4] {
+    fn set(context: &WebGlRenderingContext, location: Option<&WebGlUniformLocation>, [x, y, z, w]: [f32; 4]) {
+        context.uniform4f(location, x, y, z, w);
+    }
+}
+
+/// A uniform of a program that only takes values of type `T`. It belongs to the program it was looked up in, and has
+/// to be looked up again when the program is reloaded.
+pub struct Uniform<T> {
+    location: Option<WebGlUniformLocation>,
+    value_type: PhantomData<T>
+}
+
+impl<T: UniformValue> Uniform<T> {
+    /// A uniform that setting does nothing to, like the ones left out of a program when compiling because they do not
+    /// change what is drawn.
+    pub fn none() -> Uniform<T> {
+        Uniform {
+            location: None,
+            value_type: PhantomData
+        }
+    }
+
+    /// Sets the uniform of the program in use.
+    pub fn set(&self, context: &WebGlRenderingContext, value: T) {
+        T::set(context, self.location.as_ref(), value);
+    }
+}
+
+/// A linked program and the sources it was compiled from.
+pub struct ShaderProgram {
+    program: WebGlProgram,
+    vert_shader_src: String,
+    frag_shader_src: String
+}
+
+impl ShaderProgram {
+    pub fn get_vert_shader_src(&self) -> &str {
+        &self.vert_shader_src
+    }
+
+    pub fn get_frag_shader_src(&self) -> &str {
+        &self.frag_shader_src
+    }
+
+    pub fn uniform<T: UniformValue>(&self, context: &WebGlRenderingContext, name: &str) -> Uniform<T> {
+        Uniform {
+            location: context.get_uniform_location(&self.program, name),
+            value_type: PhantomData
+        }
+    }
+
+    /// The location of the attribute called `name`, if the program uses it.
+    pub fn attribute(&self, context: &WebGlRenderingContext, name: &str) -> Option<u32> {
+        u32::try_from(context.get_attrib_location(&self.program, name)).ok()
+    }
+}
+
+/// Every program the renderer draws with, by name. Programs can be replaced while the game runs, and a program that
+/// fails to compile keeps the one before it drawing.
+pub struct ShaderManager {
+    context: WebGlRenderingContext,
+    programs: HashMap<String, ShaderProgram>
+}
+
+impl ShaderManager {
+    pub fn new(context: WebGlRenderingContext) -> ShaderManager {
+        ShaderManager {
+            context,
+            programs: HashMap::new()
+        }
+    }
+
+    /// Compiles and links a program under `name`, replacing the one already there only if it works. Compile errors
+    /// come with the lines of the source they are about.
+    pub fn load(&mut self, name: &str, vert_shader_src: &str, frag_shader_src: &str) -> Result<(), String> {
+        let compile = |shader_type, kind, source| {
+            compile_shader(&self.context, shader_type, source)
+                .map_err(|log| format!("Could not compile the {} shader of {:?}:\n{}", kind, name, annotate_log(source, &log)))
+        };
+        let vert_shader = compile(WebGlRenderingContext::VERTEX_SHADER, "vertex", vert_shader_src)?;
+        let frag_shader = compile(WebGlRenderingContext::FRAGMENT_SHADER, "fragment", frag_shader_src)?;
+        let program = link_program(&self.context, &vert_shader, &frag_shader)
+            .map_err(|log| format!("Could not link {:?}:\n{}", name, log));
+        self.context.delete_shader(Some(&vert_shader));
+        self.context.delete_shader(Some(&frag_shader));
+
+        let program = ShaderProgram {
+            program: program?,
+            vert_shader_src: String::from(vert_shader_src),
+            frag_shader_src: String::from(frag_shader_src)
+        };
+        // Every mesh puts its vertices there
+        if program.attribute(&self.context, "attr_position") != Some(0) {
+            self.context.delete_program(Some(&program.program));
+            return Err(format!("{:?} has no attr_position attribute for its vertices", name));
+        }
+        if let Some(old) = self.programs.insert(String::from(name), program) {
+            self.context.delete_program(Some(&old.program));
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ShaderProgram> {
+        self.programs.get(name)
+    }
+
+    /// Draws with the program called `name` from now on.
+    pub fn use_program(&self, name: &str) -> Option<&ShaderProgram> {
+        let program = self.programs.get(name)?;
+        self.context.use_program(Some(&program.program));
+        Some(program)
+    }
+
+    /// Compiles every program again from its sources, like when the context they belong to was lost. Programs that
+    /// fail are dropped.
+    pub fn restore(&mut self) -> Result<(), String> {
+        let sources: Vec<(String, String, String)> = self.programs.drain()
+            .map(|(name, program)| (name, program.vert_shader_src, program.frag_shader_src))
+            .collect();
+        let errors: Vec<String> = sources.iter()
+            .filter_map(|(name, vert_shader_src, frag_shader_src)| self.load(name, vert_shader_src, frag_shader_src).err())
+            .collect();
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+    }
+
+    /// In alphabetical order.
+    pub fn get_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.programs.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+pub fn compile_shader(context: &WebGlRenderingContext, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
+    let shader = context.create_shader(shader_type).ok_or_else(|| String::from("Unable to create shader object"))?;
+    context.shader_source(&shader, source);
+    context.compile_shader(&shader);
+
+    if context.get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS).as_bool().unwrap_or(false) {
+        Ok(shader)
+    }
+    else {
+        let log = context.get_shader_info_log(&shader).unwrap_or_else(|| String::from("Unknown error creating shader"));
+        context.delete_shader(Some(&shader));
+        Err(log)
+    }
+}
+
+/// Links a program with its `attr_position` attribute at location 0, where every mesh puts its vertices, and its
+/// `attr_instance` attribute, if it draws instances, at location 1.
+pub fn link_program(context: &WebGlRenderingContext, vert_shader: &WebGlShader, frag_shader: &WebGlShader) -> Result<WebGlProgram, String> {
+    let program = context.create_program().ok_or_else(|| String::from("Unable to create program object"))?;
+    context.attach_shader(&program, vert_shader);
+    context.attach_shader(&program, frag_shader);
+    context.bind_attrib_location(&program, 0, "attr_position");
+    context.bind_attrib_location(&program, 1, "attr_instance");
+    context.link_program(&program);
+
+    if context.get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS).as_bool().unwrap_or(false) {
+        Ok(program)
+    }
+    else {
+        let log = context.get_program_info_log(&program).unwrap_or_else(|| String::from("Unknown error creating program"));
+        context.delete_program(Some(&program));
+        Err(log)
+    }
+}
+
+/// Follows every message of a compile log like `ERROR: 0:12: 'x' : undeclared identifier` with the line of `source`
+/// it is about, marked with `>`, and the lines around it.
+pub fn annotate_log(source: &str, log: &str) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut annotated = String::new();
+    for message in log.lines().map(str::trim).filter(|message| !message.is_empty()) {
+        annotated.push_str(message);
+        annotated.push('\n');
+        if let Some(line) = line_number(message).filter(|line| (1..=lines.len()).contains(line)) {
+            for number in (line - 1).max(1)..=(line + 1).min(lines.len()) {
+                let marker = if number == line { '>' } else { ' ' };
+                annotated.push_str(&format!("{} {:4} | {}\n", marker, number, lines[number - 1]));
+            }
+        }
+    }
+    annotated
+}
+
+// The line of a message like `ERROR: 0:12: ...`, after the number of the source string
+fn line_number(message: &str) -> Option<usize> {
+    let (_, location) = message.split_once(": ")?;
+    let mut parts = location.splitn(3, ':');
+    parts.next()?.trim().parse::<u32>().ok()?;
+    parts.next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fourteen lines, each saying which it is
+    fn source() -> String {
+        (1..=14).map(|line| format!("line {};\n", line)).collect()
+    }
+
+    #[test]
+    fn reads_the_line_a_message_is_about() {
+        assert_eq!(line_number("ERROR: 0:12: 'x' : undeclared identifier"), Some(12));
+        assert_eq!(line_number("WARNING: 1:3: extension not supported"), Some(3));
+        assert_eq!(line_number("ERROR: too many errors"), None);
+        assert_eq!(line_number("ERROR: 0:: syntax error"), None);
+        assert_eq!(line_number("syntax error"), None);
+    }
+
+    #[test]
+    fn marks_the_line_of_a_message_and_its_neighbours() {
+        let annotated = annotate_log(&source(), "ERROR: 0:12: 'x' : undeclared identifier\n");
+        assert_eq!(annotated, concat!(
+            "ERROR: 0:12: 'x' : undeclared identifier\n",
+            "    11 | line 11;\n",
+            ">   12 | line 12;\n",
+            "    13 | line 13;\n"
+        ));
+    }
+
+    #[test]
+    fn stops_at_the_first_and_last_lines() {
+        let annotated = annotate_log(&source(), "ERROR: 0:1: first\nERROR: 0:14: last");
+        assert_eq!(annotated, concat!(
+            "ERROR: 0:1: first\n",
+            ">    1 | line 1;\n",
+            "     2 | line 2;\n",
+            "ERROR: 0:14: last\n",
+            "    13 | line 13;\n",
+            ">   14 | line 14;\n"
+        ));
+    }
+
+    #[test]
+    fn passes_on_messages_without_a_line_of_the_source() {
+        let log = "\n  ERROR: too many errors  \nERROR: 0:15: past the end\n";
+        assert_eq!(annotate_log(&source(), log), "ERROR: too many errors\nERROR: 0:15: past the end\n");
+    }
+}
@@ -0,0 +1,309 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::Reader;
+use crate::world::input::TickInput;
+
+pub mod player;
+
+/// Version of the replay format written by this build. Bump it whenever the encoding or the simulation changes
+/// in a way that would make older replays play out differently.
+pub const REPLAY_VERSION: u16 = 2;
+
+// Replays from before multi-ball, which play out the same with one ball
+const SINGLE_BALL_VERSION: u16 = 1;
+
+const MAGIC: &[u8; 4] = b"PONG";
+
+// Replays of arenas outside these sizes are rejected, as online matches are
+const ARENA_SIZES: RangeInclusive<f32> = 100.0..=4000.0;
+
+/// A consecutive stretch of ticks that all had the same input.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputRun {
+    pub ticks: u32,
+    pub input: TickInput
+}
+
+/// Everything needed to play a match back exactly: the seed, the size of the arena, the hits between extra balls
+/// in multi-ball and the inputs of both paddles for every tick, run-length encoded.
+///
+/// The binary encoding is, all little endian:
+/// `"PONG"`, version (`u16`), seed (`u64`), width (`f32`), height (`f32`), hits between extra balls (`u32`), run
+/// count (`u32`), then every run as its tick count (LEB128) followed by one byte holding `p1 + 1` in its low two bits
+/// and `p2 + 1` in the next two. Version 1 has no hits between extra balls, and plays with one ball.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    version: u16,
+    seed: u64,
+    width: f32,
+    height: f32,
+    #[serde(default)]
+    multi_ball: u32,
+    runs: Vec<InputRun>
+}
+
+impl Replay {
+    pub fn new(seed: u64, width: f32, height: f32) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            width,
+            height,
+            multi_ball: 0,
+            runs: Vec::new()
+        }
+    }
+
+    /// For a match with an extra ball every `hits` paddle hits, see `World::set_multi_ball`.
+    pub fn set_multi_ball(&mut self, hits: u32) {
+        self.multi_ball = hits;
+    }
+
+    pub fn get_multi_ball(&self) -> u32 {
+        self.multi_ball
+    }
+
+    pub fn push(&mut self, input: TickInput) {
+        match self.runs.last_mut() {
+            Some(run) if run.input == input && run.ticks < u32::MAX => run.ticks += 1,
+            _ => self.runs.push(InputRun { ticks: 1, input })
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn get_runs(&self) -> &[InputRun] {
+        &self.runs
+    }
+
+    /// Total number of recorded ticks.
+    pub fn len(&self) -> u64 {
+        self.runs.iter().map(|run| run.ticks as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(30 + self.runs.len() * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        if self.version != SINGLE_BALL_VERSION {
+            bytes.extend_from_slice(&self.multi_ball.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.runs.len() as u32).to_le_bytes());
+
+        for run in self.runs.iter() {
+            let mut ticks = run.ticks;
+            while ticks >= 0x80 {
+                bytes.push((ticks as u8 & 0x7f) | 0x80);
+                ticks >>= 7;
+            }
+            bytes.push(ticks as u8);
+            bytes.push(((run.input.p1 + 1) as u8) | (((run.input.p2 + 1) as u8) << 2));
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let mut reader = Reader::new(bytes, "Replay file");
+
+        if reader.take(4)? != MAGIC {
+            return Err(String::from("Not a replay file"));
+        }
+        let version = u16::from_le_bytes(reader.take_array()?);
+        Self::check_version(version)?;
+        let seed = u64::from_le_bytes(reader.take_array()?);
+        let width = f32::from_le_bytes(reader.take_array()?);
+        let height = f32::from_le_bytes(reader.take_array()?);
+        Self::check_arena(width, height)?;
+        let multi_ball = if version == SINGLE_BALL_VERSION { 0 } else { u32::from_le_bytes(reader.take_array()?) };
+        let num_runs = u32::from_le_bytes(reader.take_array()?);
+
+        let mut runs = Vec::new();
+        for _ in 0..num_runs {
+            let mut ticks: u32 = 0;
+            let mut shift = 0;
+            loop {
+                let byte = reader.take(1)?[0];
+                if shift > 28 || (shift == 28 && byte & 0x70 != 0) {
+                    return Err(String::from("Replay run is too long"));
+                }
+                ticks |= ((byte & 0x7f) as u32) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+
+            if ticks == 0 {
+                return Err(String::from("Empty run in replay"));
+            }
+
+            let packed = reader.take(1)?[0];
+            let p1 = (packed & 0b11) as i8 - 1;
+            let p2 = ((packed >> 2) & 0b11) as i8 - 1;
+            if packed >> 4 != 0 || p1 > 1 || p2 > 1 {
+                return Err(format!("Invalid input {:#04x} in replay", packed));
+            }
+            runs.push(InputRun { ticks, input: TickInput::new(p1, p2) });
+        }
+        if !reader.take_rest().is_empty() {
+            return Err(String::from("Replay file has trailing data"));
+        }
+
+        Ok(Replay {
+            version,
+            seed,
+            width,
+            height,
+            multi_ball,
+            runs
+        })
+    }
+
+    /// A human readable form of the replay, meant for debugging.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Replays are always representable as JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<Replay, String> {
+        let replay: Replay = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        Self::check_version(replay.version)?;
+        Self::check_arena(replay.width, replay.height)?;
+        if replay.version == SINGLE_BALL_VERSION && replay.multi_ball != 0 {
+            return Err(String::from("Replay version 1 has only one ball"));
+        }
+        if replay.runs.iter().any(|run| run.ticks == 0) {
+            return Err(String::from("Empty run in replay"));
+        }
+        if replay.runs.iter().any(|run| !(-1..=1).contains(&run.input.p1) || !(-1..=1).contains(&run.input.p2)) {
+            return Err(String::from("Invalid input in replay"));
+        }
+        Ok(replay)
+    }
+
+    // The world would not even set up in an arena that is not finite and a sensible size
+    fn check_arena(width: f32, height: f32) -> Result<(), String> {
+        if !ARENA_SIZES.contains(&width) || !ARENA_SIZES.contains(&height) {
+            return Err(format!("Replay arena of {}x{} is not between 100x100 and 4000x4000", width, height));
+        }
+        Ok(())
+    }
+
+    fn check_version(version: u16) -> Result<(), String> {
+        if version == REPLAY_VERSION || version == SINGLE_BALL_VERSION {
+            Ok(())
+        }
+        else {
+            Err(format!("Unsupported replay version {} (expected {})", version, REPLAY_VERSION))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_pcg::Pcg32;
+
+    use super::*;
+    use crate::replay::player::ReplayPlayer;
+    use crate::session::{LiveSession, mode::Mode};
+    use crate::world::ai::Difficulty;
+
+    fn with_arena(width: f32, height: f32) -> Vec<u8> {
+        let mut replay = Replay::new(3, 960.0, 600.0);
+        replay.push(TickInput::new(1, -1));
+        let mut bytes = replay.to_bytes();
+        // Width and height follow the magic, version and seed
+        bytes[14..18].copy_from_slice(&width.to_le_bytes());
+        bytes[18..22].copy_from_slice(&height.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let replay = Replay::from_bytes(&with_arena(100.0, 4000.0)).unwrap();
+        assert_eq!((replay.get_width(), replay.get_height()), (100.0, 4000.0));
+        assert_eq!(replay.len(), 1);
+    }
+
+    #[test]
+    fn rejects_arenas_no_world_could_have() {
+        for (width, height) in [(f32::NAN, 600.0), (960.0, f32::INFINITY), (0.0, 600.0), (960.0, -600.0), (99.0, 600.0), (960.0, 4001.0)] {
+            assert!(Replay::from_bytes(&with_arena(width, height)).is_err(), "Accepted an arena of {}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_data() {
+        let mut bytes = with_arena(960.0, 600.0);
+        assert!(Replay::from_bytes(&bytes).is_ok());
+        bytes.push(0);
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+
+    // Plays a match of `mode` with player 1 handed back and forth between an AI and random presses, returning it
+    // along with the most balls that were ever in play at once
+    fn record(mode: Mode) -> (LiveSession, usize) {
+        let mut live = LiveSession::new(960.0, 600.0, 21);
+        live.set_mode(mode);
+        let mut rng = Pcg32::seed_from_u64(4);
+        let mut most_balls = 0;
+        for tick in 0..6000 {
+            if tick % 1500 < 1000 {
+                live.set_p1_ai(Some(Difficulty::Hard));
+            }
+            else {
+                live.set_p1_ai(None);
+                if rng.gen_bool(0.02) {
+                    live.set_p1_dir(rng.gen_range(-1, 2));
+                }
+            }
+            live.tick();
+            most_balls = most_balls.max(live.get_world().get_balls().len());
+        }
+        (live, most_balls)
+    }
+
+    // The recording of `live`, written out, read back and played to the end
+    fn play_back(live: &LiveSession) -> ReplayPlayer {
+        let bytes = live.get_replay().to_bytes();
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), REPLAY_VERSION);
+        let mut player = ReplayPlayer::new(Replay::from_bytes(&bytes).unwrap());
+        player.seek(u64::MAX);
+        assert!(player.is_finished());
+        player
+    }
+
+    #[test]
+    fn plays_back_exactly_what_was_recorded() {
+        let (live, _) = record(Mode::Versus);
+        assert!(live.get_world().get_scores() != (0, 0), "Nothing happened worth replaying");
+        assert_eq!(play_back(&live).get_world().to_bytes(), live.get_world().to_bytes());
+    }
+
+    #[test]
+    fn plays_back_multi_ball_exactly_what_was_recorded() {
+        let (live, most_balls) = record(Mode::MultiBall);
+        assert!(most_balls > 1, "No extra ball was ever served");
+        assert_eq!(play_back(&live).get_world().to_bytes(), live.get_world().to_bytes());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::replay::Replay;
+use crate::world::{World, TICK_MS, clock::FixedClock};
+
+pub const MIN_SPEED: f32 = 0.125;
+pub const MAX_SPEED: f32 = 8.0;
+
+/// How far the playback controls jump when seeking, in ticks.
+pub const SEEK_STEP: u64 = (5000.0 / TICK_MS) as u64;
+
+/// Plays a `Replay` back through the simulation, with pause, seek and speed control.
+pub struct ReplayPlayer {
+    replay: Replay,
+    world: World,
+    clock: FixedClock,
+
+    // Position in the replay: index of the current run and how many of its ticks have already been played
+    run: usize,
+    run_tick: u32,
+
+    speed: f32,
+    paused: bool
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let world = Self::first_world(&replay);
+
+        ReplayPlayer {
+            replay,
+            world,
+            clock: FixedClock::new(),
+
+            run: 0,
+            run_tick: 0,
+
+            speed: 1.0,
+            paused: false
+        }
+    }
+
+    // The world before the first tick of `replay`
+    fn first_world(replay: &Replay) -> World {
+        let mut world = World::new(replay.get_width(), replay.get_height(), replay.get_seed());
+        world.set_multi_ball(replay.get_multi_ball());
+        world
+    }
+
+    /// Plays as many ticks as `elapsed_ms` of wall clock time covers at the current speed.
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        if self.paused {
+            return;
+        }
+
+        for _ in 0..self.clock.ticks(elapsed_ms * self.speed) {
+            if !self.step() {
+                break;
+            }
+        }
+    }
+
+    /// Jumps to `tick`, clamped to the length of the replay. Seeking backwards re-simulates from the start.
+    pub fn seek(&mut self, tick: u64) {
+        if tick < self.world.get_tick() {
+            self.world = Self::first_world(&self.replay);
+            self.run = 0;
+            self.run_tick = 0;
+        }
+
+        while self.world.get_tick() < tick && self.step() {}
+    }
+
+    /// Seeks `SEEK_STEP` ticks forwards or backwards.
+    pub fn skip(&mut self, forwards: bool) {
+        if forwards {
+            self.seek(self.get_tick() + SEEK_STEP);
+        }
+        else {
+            self.seek(self.get_tick().saturating_sub(SEEK_STEP));
+        }
+    }
+
+    fn step(&mut self) -> bool {
+        let run = match self.replay.get_runs().get(self.run) {
+            Some(run) => *run,
+            None => return false
+        };
+
+        self.world.tick(run.input);
+        self.run_tick += 1;
+        if self.run_tick >= run.ticks {
+            self.run += 1;
+            self.run_tick = 0;
+        }
+
+        true
+    }
+
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.world.get_tick()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.run >= self.replay.get_runs().len()
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Values below one play in slow motion, values above one fast-forward.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+}
@@ -0,0 +1,143 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use crate::rollback::{PeerMessage, Transport};
+
+/// How bad the connection simulated by a `LoopbackTransport` is.
+#[derive(Clone, Copy, Debug)]
+pub struct LinkConditions {
+    /// One way delay in milliseconds.
+    pub latency_ms: f32,
+    /// Up to this many extra milliseconds are added to every message at random, which also reorders them.
+    pub jitter_ms: f32,
+    /// Chance of any message being dropped, from 0 to 1.
+    pub loss: f32
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        LinkConditions {
+            latency_ms: 0.0,
+            jitter_ms: 0.0,
+            loss: 0.0
+        }
+    }
+}
+
+struct Link {
+    conditions: LinkConditions,
+    rng: Pcg32,
+    // Messages in flight towards each end, with the time they arrive
+    queues: [VecDeque<(f32, PeerMessage)>; 2]
+}
+
+/// One end of an in-process connection with artificial latency, jitter and packet loss, for testing rollback.
+///
+/// Both ends read the time from a shared clock in milliseconds, which the test drives however it likes.
+pub struct LoopbackTransport {
+    link: Rc<RefCell<Link>>,
+    clock: Rc<Cell<f32>>,
+    end: usize
+}
+
+impl LoopbackTransport {
+    /// The random losses and delays are drawn from `seed`, so a test run can be repeated exactly.
+    pub fn pair(conditions: LinkConditions, clock: Rc<Cell<f32>>, seed: u64) -> (LoopbackTransport, LoopbackTransport) {
+        let link = Rc::new(RefCell::new(Link {
+            conditions,
+            rng: Pcg32::seed_from_u64(seed),
+            queues: [VecDeque::new(), VecDeque::new()]
+        }));
+
+        let a = LoopbackTransport { link: link.clone(), clock: clock.clone(), end: 0 };
+        let b = LoopbackTransport { link, clock, end: 1 };
+        (a, b)
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, message: &PeerMessage) {
+        let mut link = self.link.borrow_mut();
+        let conditions = link.conditions;
+        if link.rng.gen::<f32>() < conditions.loss {
+            return;
+        }
+
+        let delay = conditions.latency_ms + link.rng.gen::<f32>() * conditions.jitter_ms;
+        let arrival = self.clock.get() + delay;
+        link.queues[1 - self.end].push_back((arrival, message.clone()));
+    }
+
+    fn receive(&mut self) -> Option<PeerMessage> {
+        let now = self.clock.get();
+        let mut link = self.link.borrow_mut();
+        let queue = &mut link.queues[self.end];
+
+        let i = queue.iter().position(|(arrival, _)| *arrival <= now)?;
+        queue.remove(i).map(|(_, message)| message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollback::RollbackSession;
+    use crate::world::{Side, TICK_MS};
+
+    const TICKS: u32 = 3000;
+
+    #[test]
+    fn peers_agree_over_a_bad_link() {
+        let clock = Rc::new(Cell::new(0.0));
+        let conditions = LinkConditions { latency_ms: 40.0, jitter_ms: 30.0, loss: 0.2 };
+        let (a, b) = LoopbackTransport::pair(conditions, clock.clone(), 5);
+        let mut peers = [
+            RollbackSession::new(Side::P1, 960.0, 600.0, 9, Box::new(a)),
+            RollbackSession::new(Side::P2, 960.0, 600.0, 9, Box::new(b))
+        ];
+
+        // Both players change direction often, so predictions keep turning out wrong
+        let mut rng = Pcg32::seed_from_u64(13);
+        for _ in 0..TICKS {
+            clock.set(clock.get() + TICK_MS);
+            for peer in peers.iter_mut() {
+                if rng.gen_bool(0.05) {
+                    peer.set_dir(rng.gen_range(-1, 2));
+                }
+                peer.tick();
+            }
+        }
+
+        // Hold still until every input has arrived and both worlds are at the same tick
+        for peer in peers.iter_mut() {
+            peer.set_dir(0);
+        }
+        let settled = |peers: &[RollbackSession; 2]| {
+            let tick = peers[0].get_world().get_tick();
+            peers.iter().all(|peer| peer.get_world().get_tick() == tick && peer.confirmed_tick() >= tick)
+        };
+        let mut waited = 0;
+        while !settled(&peers) {
+            assert!(waited < TICKS, "Peers never caught up with each other");
+            waited += 1;
+            clock.set(clock.get() + TICK_MS);
+            let ahead = peers.iter().map(|peer| peer.get_world().get_tick()).max().unwrap();
+            for peer in peers.iter_mut() {
+                if peer.get_world().get_tick() < ahead {
+                    peer.tick();
+                }
+                else {
+                    peer.sync();
+                }
+            }
+        }
+
+        assert!(peers.iter().all(|peer| peer.get_rollback_stats().0 > 0), "The link was too good to test rollback");
+        assert_eq!(peers[0].get_world().snapshot(), peers[1].get_world().snapshot());
+        assert_eq!(peers[0].get_world().to_bytes(), peers[1].get_world().to_bytes());
+    }
+}
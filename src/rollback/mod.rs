@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::{Side, World, clock::FixedClock, input::TickInput};
+
+pub mod loopback;
+pub mod udp;
+
+/// Ticks between reading an input and simulating it. A little delay hides most of the latency without any
+/// rollback at all.
+pub const DEFAULT_INPUT_DELAY: u64 = 2;
+
+/// How far ahead of the last confirmed remote input the simulation may run before it waits for the peer.
+pub const MAX_PREDICTION: u64 = 60;
+
+// Longest run of inputs put in a single message
+const MAX_INPUTS_PER_MESSAGE: usize = 128;
+
+/// Exchanged between peers every tick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerMessage {
+    /// The sender's inputs for consecutive ticks, starting at `first_tick`.
+    pub first_tick: u64,
+    pub dirs: Vec<i8>,
+    /// How many of the receiver's inputs the sender has, so it knows where to resume.
+    pub ack: u64
+}
+
+/// Carries messages between two peers. Messages may be lost, duplicated or reordered; the session copes.
+pub trait Transport {
+    fn send(&mut self, message: &PeerMessage);
+    fn receive(&mut self) -> Option<PeerMessage>;
+}
+
+/// A peer to peer match with GGPO style rollback.
+///
+/// Both peers simulate the whole match. Remote inputs that have not arrived yet are predicted to be the same as the
+/// last one received, and when the real input turns out to be different the world is restored to the snapshot from
+/// before that tick and simulated forward again with the corrected inputs.
+///
+/// Every list below is indexed by tick, starting at `base`.
+pub struct RollbackSession {
+    side: Side,
+    world: World,
+    transport: Box<dyn Transport>,
+    clock: FixedClock,
+    dir: i8,
+
+    base: u64,
+    // The world before each simulated tick
+    states: VecDeque<World>,
+    local_inputs: VecDeque<i8>,
+    remote_inputs: VecDeque<i8>,
+    // The remote input each simulated tick used, predicted or not
+    used_remote_inputs: VecDeque<i8>,
+    // How many of our inputs the peer has
+    peer_ack: u64,
+
+    rollbacks: u32,
+    max_rollback: u64
+}
+
+impl RollbackSession {
+    pub fn new(side: Side, width: f32, height: f32, seed: u64, transport: Box<dyn Transport>) -> RollbackSession {
+        let mut session = RollbackSession {
+            side,
+            world: World::new(width, height, seed),
+            transport,
+            clock: FixedClock::new(),
+            dir: 0,
+
+            base: 0,
+            states: VecDeque::new(),
+            local_inputs: VecDeque::new(),
+            remote_inputs: VecDeque::new(),
+            used_remote_inputs: VecDeque::new(),
+            peer_ack: 0,
+
+            rollbacks: 0,
+            max_rollback: 0
+        };
+        session.set_input_delay(DEFAULT_INPUT_DELAY);
+        session
+    }
+
+    /// Only takes effect before the first tick; both peers should use the same delay.
+    pub fn set_input_delay(&mut self, input_delay: u64) {
+        if self.world.get_tick() == 0 {
+            self.local_inputs = vec![0; input_delay as usize].into();
+        }
+    }
+
+    pub fn set_dir(&mut self, dir: i8) {
+        self.dir = dir;
+    }
+
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        for _ in 0..self.clock.ticks(elapsed_ms) {
+            self.tick();
+        }
+    }
+
+    /// Exchanges inputs with the peer and simulates a single tick, for hosts that keep their own fixed rate. The
+    /// tick is skipped when the peer has fallen too far behind.
+    pub fn tick(&mut self) {
+        self.receive();
+
+        if self.world.get_tick().saturating_sub(self.confirmed_tick()) < MAX_PREDICTION {
+            self.local_inputs.push_back(self.dir);
+            self.simulate();
+        }
+
+        self.send();
+        self.trim();
+    }
+
+    /// Exchanges inputs with the peer without simulating, rolling back if a prediction turns out wrong.
+    pub fn sync(&mut self) {
+        self.receive();
+        self.send();
+    }
+
+    fn receive(&mut self) {
+        let mut mispredicted: Option<u64> = None;
+
+        while let Some(message) = self.transport.receive() {
+            self.peer_ack = self.peer_ack.max(message.ack);
+
+            for (tick, dir) in (message.first_tick..).zip(message.dirs) {
+                // Inputs always arrive as a continuation of what we have, so anything else is a duplicate
+                if tick != self.confirmed_tick() {
+                    continue;
+                }
+                let dir = dir.clamp(-1, 1);
+                self.remote_inputs.push_back(dir);
+
+                let i = (tick - self.base) as usize;
+                if mispredicted.is_none() && self.used_remote_inputs.get(i).is_some_and(|used| *used != dir) {
+                    mispredicted = Some(tick);
+                }
+            }
+        }
+
+        if let Some(tick) = mispredicted {
+            self.rollback(tick);
+        }
+    }
+
+    fn rollback(&mut self, tick: u64) {
+        let current = self.world.get_tick();
+        let i = (tick - self.base) as usize;
+
+        self.world = self.states[i].clone();
+        self.states.truncate(i);
+        self.used_remote_inputs.truncate(i);
+        while self.world.get_tick() < current {
+            self.simulate();
+        }
+
+        self.rollbacks += 1;
+        self.max_rollback = self.max_rollback.max(current - tick);
+    }
+
+    fn simulate(&mut self) {
+        let i = (self.world.get_tick() - self.base) as usize;
+        let local = self.local_inputs[i];
+        let remote = match self.remote_inputs.get(i) {
+            Some(dir) => *dir,
+            None => self.last_remote_input()
+        };
+
+        self.states.push_back(self.world.clone());
+        self.used_remote_inputs.push_back(remote);
+        self.world.tick(match self.side {
+            Side::P1 => TickInput::new(local, remote),
+            Side::P2 => TickInput::new(remote, local)
+        });
+    }
+
+    fn send(&mut self) {
+        let first_tick = self.peer_ack.max(self.base);
+        let dirs: Vec<i8> = self.local_inputs.iter().skip((first_tick - self.base) as usize).take(MAX_INPUTS_PER_MESSAGE).copied().collect();
+        let ack = self.confirmed_tick();
+        self.transport.send(&PeerMessage { first_tick, dirs, ack });
+    }
+
+    /// Forgets everything that can no longer be rolled back to or resent.
+    fn trim(&mut self) {
+        let new_base = self.confirmed_tick().min(self.peer_ack).min(self.world.get_tick());
+        // The last remote input is still needed for predictions
+        let drop = (new_base - self.base).min(self.remote_inputs.len().saturating_sub(1) as u64) as usize;
+
+        self.states.drain(..drop);
+        self.local_inputs.drain(..drop);
+        self.remote_inputs.drain(..drop);
+        self.used_remote_inputs.drain(..drop);
+        self.base += drop as u64;
+    }
+
+    fn last_remote_input(&self) -> i8 {
+        self.remote_inputs.back().copied().unwrap_or(0)
+    }
+
+    /// The first tick whose remote input has not arrived yet. Everything before it is final.
+    pub fn confirmed_tick(&self) -> u64 {
+        self.base + self.remote_inputs.len() as u64
+    }
+
+    pub fn get_side(&self) -> Side {
+        self.side
+    }
+
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
+
+    /// How many times the session rolled back, and the most ticks it ever had to simulate again.
+    pub fn get_rollback_stats(&self) -> (u32, u64) {
+        (self.rollbacks, self.max_rollback)
+    }
+}
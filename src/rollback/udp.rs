@@ -0,0 +1,39 @@
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use crate::rollback::{PeerMessage, Transport};
+
+/// Sends peer messages as JSON datagrams. UDP loses and reorders packets, which rollback is built to handle.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    buffer: Vec<u8>
+}
+
+impl UdpTransport {
+    pub fn new(bind: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<UdpTransport> {
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(peer)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(UdpTransport {
+            socket,
+            buffer: vec![0; 65536]
+        })
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, message: &PeerMessage) {
+        // Lost packets are resent with the next message anyway, and the peer may simply not be up yet
+        let _ = self.socket.send(&serde_json::to_vec(message).expect("Messages are always representable as JSON"));
+    }
+
+    fn receive(&mut self) -> Option<PeerMessage> {
+        loop {
+            let len = self.socket.recv(&mut self.buffer).ok()?;
+            if let Ok(message) = serde_json::from_slice(&self.buffer[..len]) {
+                return Some(message);
+            }
+        }
+    }
+}
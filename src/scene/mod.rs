@@ -0,0 +1,240 @@
+/// Input for the scene on top, whichever device it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    /// A key went down, named like the `key` of a DOM keyboard event (`ArrowUp`, `Enter`, `a`...).
+    KeyDown(String),
+    KeyUp(String),
+    /// The pointer moved to a position in world coordinates.
+    Point(f32, f32),
+    /// A click or tap at a position in world coordinates.
+    Click(f32, f32)
+}
+
+/// What a scene asks of the stack after an input or an update.
+pub enum Transition<C> {
+    Stay,
+    /// Puts a scene on top of this one.
+    Push(Box<dyn Scene<C>>),
+    /// Removes this scene, uncovering the one below.
+    Pop,
+    /// Removes this scene and puts another in its place.
+    Replace(Box<dyn Scene<C>>)
+}
+
+/// One state of the game, like a match, a menu or the high score entry. Scenes share the context `C`, which holds
+/// whatever outlives them, like the session and the renderer.
+pub trait Scene<C> {
+    /// Called when the scene is put on the stack.
+    fn enter(&mut self, _context: &mut C) {}
+
+    /// Called when the scene is removed from the stack.
+    fn exit(&mut self, _context: &mut C) {}
+
+    /// Called when the scene is on top again, after the one above it was removed.
+    fn resume(&mut self, _context: &mut C) {}
+
+    /// Only the scene on top gets input.
+    fn input(&mut self, context: &mut C, input: &Input) -> Transition<C>;
+
+    /// Only the scene on top is updated, so the ones below are on hold. `dt` is in milliseconds.
+    fn update(&mut self, context: &mut C, dt: f32) -> Transition<C>;
+
+    /// Draws the scene over the ones below it.
+    fn render(&mut self, context: &mut C);
+
+    /// Whether the scene hides the ones below it, which then are not drawn.
+    fn is_opaque(&self, _context: &C) -> bool {
+        true
+    }
+}
+
+/// The scenes of the game, the one on top being the one played.
+pub struct SceneStack<C> {
+    scenes: Vec<Box<dyn Scene<C>>>
+}
+
+impl<C> SceneStack<C> {
+    pub fn new() -> SceneStack<C> {
+        SceneStack {
+            scenes: Vec::new()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn push(&mut self, context: &mut C, mut scene: Box<dyn Scene<C>>) {
+        scene.enter(context);
+        self.scenes.push(scene);
+    }
+
+    pub fn pop(&mut self, context: &mut C) {
+        if let Some(mut scene) = self.scenes.pop() {
+            scene.exit(context);
+            if let Some(top) = self.scenes.last_mut() {
+                top.resume(context);
+            }
+        }
+    }
+
+    /// Removes scenes from the top until only `len` are left.
+    pub fn truncate(&mut self, context: &mut C, len: usize) {
+        while self.scenes.len() > len {
+            self.pop(context);
+        }
+    }
+
+    pub fn input(&mut self, context: &mut C, input: &Input) {
+        if let Some(top) = self.scenes.last_mut() {
+            let transition = top.input(context, input);
+            self.apply(context, transition);
+        }
+    }
+
+    pub fn update(&mut self, context: &mut C, dt: f32) {
+        if let Some(top) = self.scenes.last_mut() {
+            let transition = top.update(context, dt);
+            self.apply(context, transition);
+        }
+    }
+
+    /// Draws the scenes from the top one that is opaque upwards.
+    pub fn render(&mut self, context: &mut C) {
+        let bottom = self.scenes.iter().rposition(|scene| scene.is_opaque(context)).unwrap_or(0);
+        for scene in self.scenes[bottom..].iter_mut() {
+            scene.render(context);
+        }
+    }
+
+    fn apply(&mut self, context: &mut C, transition: Transition<C>) {
+        match transition {
+            Transition::Stay => {},
+            Transition::Push(scene) => self.push(context, scene),
+            Transition::Pop => self.pop(context),
+            Transition::Replace(mut scene) => {
+                if let Some(mut old) = self.scenes.pop() {
+                    old.exit(context);
+                }
+                scene.enter(context);
+                self.scenes.push(scene);
+            }
+        }
+    }
+}
+
+impl<C> Default for SceneStack<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes down what happens to it in the context, and does what the keys pressed on it name
+    struct Logged {
+        name: &'static str,
+        opaque: bool
+    }
+
+    impl Logged {
+        fn new(name: &'static str, opaque: bool) -> Box<Logged> {
+            Box::new(Logged { name, opaque })
+        }
+    }
+
+    impl Scene<Vec<String>> for Logged {
+        fn enter(&mut self, log: &mut Vec<String>) {
+            log.push(format!("enter {}", self.name));
+        }
+
+        fn exit(&mut self, log: &mut Vec<String>) {
+            log.push(format!("exit {}", self.name));
+        }
+
+        fn resume(&mut self, log: &mut Vec<String>) {
+            log.push(format!("resume {}", self.name));
+        }
+
+        fn input(&mut self, _log: &mut Vec<String>, input: &Input) -> Transition<Vec<String>> {
+            match input {
+                Input::KeyDown(key) if key == "push" => Transition::Push(Logged::new("pushed", true)),
+                Input::KeyDown(key) if key == "replace" => Transition::Replace(Logged::new("replacement", true)),
+                Input::KeyDown(key) if key == "pop" => Transition::Pop,
+                _ => Transition::Stay
+            }
+        }
+
+        fn update(&mut self, log: &mut Vec<String>, _dt: f32) -> Transition<Vec<String>> {
+            log.push(format!("update {}", self.name));
+            Transition::Stay
+        }
+
+        fn render(&mut self, log: &mut Vec<String>) {
+            log.push(format!("render {}", self.name));
+        }
+
+        fn is_opaque(&self, _log: &Vec<String>) -> bool {
+            self.opaque
+        }
+    }
+
+    fn key(name: &str) -> Input {
+        Input::KeyDown(String::from(name))
+    }
+
+    #[test]
+    fn enters_exits_and_resumes_scenes_in_order() {
+        let mut log = Vec::new();
+        let mut scenes = SceneStack::new();
+        scenes.push(&mut log, Logged::new("a", true));
+        scenes.push(&mut log, Logged::new("b", true));
+        scenes.pop(&mut log);
+        scenes.push(&mut log, Logged::new("c", true));
+        scenes.push(&mut log, Logged::new("d", true));
+        scenes.truncate(&mut log, 1);
+        assert_eq!(log, ["enter a", "enter b", "exit b", "resume a", "enter c", "enter d", "exit d", "resume c", "exit c", "resume a"]);
+        assert_eq!(scenes.len(), 1);
+    }
+
+    #[test]
+    fn applies_the_transitions_of_the_scene_on_top() {
+        let mut log = Vec::new();
+        let mut scenes = SceneStack::new();
+        scenes.push(&mut log, Logged::new("a", true));
+        scenes.input(&mut log, &key("push"));
+        scenes.input(&mut log, &key("replace"));
+        // Only the scene on top is updated
+        scenes.update(&mut log, 1.0);
+        scenes.input(&mut log, &key("pop"));
+        assert_eq!(log, ["enter a", "enter pushed", "exit pushed", "enter replacement", "update replacement", "exit replacement", "resume a"]);
+        assert_eq!(scenes.len(), 1);
+    }
+
+    #[test]
+    fn renders_from_the_top_opaque_scene_up() {
+        let mut log = Vec::new();
+        let mut scenes = SceneStack::new();
+        scenes.push(&mut log, Logged::new("a", true));
+        scenes.push(&mut log, Logged::new("b", true));
+        scenes.push(&mut log, Logged::new("c", false));
+        scenes.push(&mut log, Logged::new("d", false));
+        log.clear();
+        scenes.render(&mut log);
+        assert_eq!(log, ["render b", "render c", "render d"]);
+
+        // With nothing opaque, everything is drawn
+        let mut scenes = SceneStack::new();
+        scenes.push(&mut log, Logged::new("a", false));
+        scenes.push(&mut log, Logged::new("b", false));
+        log.clear();
+        scenes.render(&mut log);
+        assert_eq!(log, ["render a", "render b"]);
+    }
+}
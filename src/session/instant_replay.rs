@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+
+use crate::world::{World, TICK_MS};
+
+/// How much of the match leading up to a point is shown again.
+pub const INSTANT_REPLAY_LENGTH_MS: f32 = 2000.0;
+/// Playback speed of the instant replay.
+pub const INSTANT_REPLAY_SPEED: f32 = 0.4;
+
+// A snapshot every other tick is plenty even in slow motion
+const SNAPSHOT_INTERVAL: u32 = 2;
+const SNAPSHOT_MS: f32 = SNAPSHOT_INTERVAL as f32 * TICK_MS;
+const NUM_SNAPSHOTS: usize = (INSTANT_REPLAY_LENGTH_MS / SNAPSHOT_MS) as usize;
+
+/// Rolling buffer of the most recent world snapshots.
+pub struct SnapshotBuffer {
+    snapshots: VecDeque<World>,
+    ticks_since_snapshot: u32
+}
+
+impl SnapshotBuffer {
+    pub fn new() -> SnapshotBuffer {
+        SnapshotBuffer {
+            snapshots: VecDeque::with_capacity(NUM_SNAPSHOTS),
+            ticks_since_snapshot: 0
+        }
+    }
+
+    /// To be called after every tick; only every `SNAPSHOT_INTERVAL`th world is kept.
+    pub fn record(&mut self, world: &World) {
+        self.ticks_since_snapshot += 1;
+        if self.ticks_since_snapshot < SNAPSHOT_INTERVAL {
+            return;
+        }
+        self.ticks_since_snapshot = 0;
+
+        if self.snapshots.len() == NUM_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(world.clone());
+    }
+
+    /// Empties the buffer into an instant replay of everything it held.
+    pub fn take_replay(&mut self) -> Option<InstantReplay> {
+        self.ticks_since_snapshot = 0;
+        if self.snapshots.is_empty() {
+            return None;
+        }
+
+        Some(InstantReplay {
+            frames: self.snapshots.drain(..).collect(),
+            position_ms: 0.0
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.ticks_since_snapshot = 0;
+    }
+}
+
+impl Default for SnapshotBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Slow motion playback of the snapshots taken before a point.
+pub struct InstantReplay {
+    frames: Vec<World>,
+    position_ms: f32
+}
+
+impl InstantReplay {
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        self.position_ms += elapsed_ms * INSTANT_REPLAY_SPEED;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame_index() >= self.frames.len()
+    }
+
+    /// The snapshot to show right now.
+    pub fn get_frame(&self) -> &World {
+        &self.frames[self.frame_index().min(self.frames.len() - 1)]
+    }
+
+    fn frame_index(&self) -> usize {
+        (self.position_ms / SNAPSHOT_MS) as usize
+    }
+}
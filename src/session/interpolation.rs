@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+
+use crate::world::snapshot::Snapshot;
+
+/// How far behind the newest server state the world is drawn, in ticks, so that there is almost always a later
+/// state to interpolate towards.
+pub const INTERPOLATION_DELAY: f64 = 24.0;
+
+const MAX_STATES: usize = 64;
+
+/// Smooths out the states a server sends every few ticks by drawing the world between the two around
+/// `INTERPOLATION_DELAY` ticks in the past.
+pub struct Interpolation {
+    states: VecDeque<Snapshot>,
+    render_tick: f64
+}
+
+impl Interpolation {
+    pub fn new() -> Interpolation {
+        Interpolation {
+            states: VecDeque::with_capacity(MAX_STATES),
+            render_tick: 0.0
+        }
+    }
+
+    /// Moves the drawn moment forward by a tick, without passing the newest state.
+    pub fn tick(&mut self) {
+        if let Some(latest) = self.states.back() {
+            self.render_tick = (self.render_tick + 1.0).min(latest.tick as f64);
+        }
+    }
+
+    /// Keeps a state from the server, ignoring any that arrive out of order.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.states.back().is_some_and(|latest| latest.tick >= snapshot.tick) {
+            return;
+        }
+        if self.states.len() == MAX_STATES {
+            self.states.pop_front();
+        }
+        self.states.push_back(snapshot);
+
+        // Resync when the interpolation drifted too far from the server (or has not started yet)
+        let target = snapshot.tick as f64 - INTERPOLATION_DELAY;
+        if self.states.len() == 1 || (self.render_tick - target).abs() > INTERPOLATION_DELAY {
+            self.render_tick = target.max(self.states[0].tick as f64);
+        }
+    }
+
+    /// The state to draw, or `None` before the first one has arrived.
+    pub fn current(&mut self) -> Option<Snapshot> {
+        // Forget the states we have already interpolated past
+        while self.states.len() > 2 && (self.states[1].tick as f64) <= self.render_tick {
+            self.states.pop_front();
+        }
+
+        match (self.states.front(), self.states.get(1)) {
+            (Some(from), Some(to)) => {
+                let t = (self.render_tick - from.tick as f64) / (to.tick - from.tick) as f64;
+                Some(from.lerp(to, t.clamp(0.0, 1.0) as f32))
+            },
+            (Some(only), None) => Some(*only),
+            _ => None
+        }
+    }
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,511 @@
+use std::cmp::Ordering;
+
+use crate::encoding::Reader;
+use crate::net::ServerMessage;
+use crate::replay::{Replay, player::ReplayPlayer};
+use crate::rollback::RollbackSession;
+use crate::stats::Stats;
+use crate::world::{Event, Side, World, TICK_MS, ai::Difficulty, clock::FixedClock, input::TickInput};
+
+pub mod instant_replay;
+pub mod interpolation;
+pub mod mode;
+pub mod online;
+pub mod spectator;
+use instant_replay::{InstantReplay, SnapshotBuffer};
+use mode::Mode;
+use online::OnlineSession;
+use spectator::SpectatorSession;
+
+/// How many events a `LiveSession` keeps for `take_events`.
+pub const MAX_EVENTS: usize = 64;
+
+/// A match being played right now: player 1 is driven by the host, player 2 by the AI, and every tick is
+/// recorded into a replay.
+///
+/// How the match ends depends on its mode: a versus or multi-ball match ends when either side reaches the points to
+/// win, or goes on forever when that is 0. Stats are counted as it goes, for the host to collect with `take_stats`.
+///
+/// With instant replays turned on, the last moments before every point are shown again in slow motion while the
+/// match waits to serve.
+pub struct LiveSession {
+    world: World,
+    replay: Replay,
+    clock: FixedClock,
+    p1_dir: i8,
+    // Plays player 1 instead of `p1_dir` when set
+    p1_ai: Option<Difficulty>,
+    difficulty: Difficulty,
+    mode: Mode,
+    points_to_win: u32,
+    over: bool,
+
+    snapshots: Option<SnapshotBuffer>,
+    instant_replay: Option<InstantReplay>,
+
+    // Paddle hits since the last point
+    rally: u32,
+    // Counted since the last `take_stats`
+    stats: Stats,
+    // Happened since the last `take_events`
+    events: Vec<Event>
+}
+
+impl LiveSession {
+    pub fn new(width: f32, height: f32, seed: u64) -> LiveSession {
+        let mode = Mode::default();
+        let (world, replay) = Self::new_match(width, height, seed, mode);
+        LiveSession {
+            world,
+            replay,
+            clock: FixedClock::new(),
+            p1_dir: 0,
+            p1_ai: None,
+            difficulty: Difficulty::default(),
+            mode,
+            points_to_win: 0,
+            over: false,
+
+            snapshots: None,
+            instant_replay: None,
+
+            rally: 0,
+            stats: Stats::new(),
+            events: Vec::new()
+        }
+    }
+
+    /// Starts a new match with `seed` in the same arena and with the same settings.
+    pub fn rematch(&mut self, seed: u64) {
+        let (width, height) = (self.world.get_width(), self.world.get_height());
+        let (world, replay) = Self::new_match(width, height, seed, self.mode);
+        self.world = world;
+        self.replay = replay;
+        self.clock = FixedClock::new();
+        self.over = false;
+        self.instant_replay = None;
+        self.set_instant_replay(self.snapshots.is_some());
+        self.rally = 0;
+    }
+
+    // The world and replay of a match of `mode` that has not started yet
+    fn new_match(width: f32, height: f32, seed: u64, mode: Mode) -> (World, Replay) {
+        let mut world = World::new(width, height, seed);
+        world.set_multi_ball(mode.get_multi_ball());
+        let mut replay = Replay::new(seed, width, height);
+        replay.set_multi_ball(mode.get_multi_ball());
+        (world, replay)
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+    }
+
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Meant to be set before the match starts, or followed by a `rematch`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        // A match that has not started yet is played with the balls of the new mode
+        if self.world.get_tick() == 0 {
+            self.world.set_multi_ball(mode.get_multi_ball());
+            self.replay.set_multi_ball(mode.get_multi_ball());
+        }
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Player 1's score for the leaderboard, see `Mode::get_score`.
+    pub fn get_score(&self) -> u32 {
+        self.mode.get_score(&self.world)
+    }
+
+    /// Only used by versus matches. 0 plays forever.
+    pub fn set_points_to_win(&mut self, points_to_win: u32) {
+        self.points_to_win = points_to_win;
+    }
+
+    pub fn get_points_to_win(&self) -> u32 {
+        self.points_to_win
+    }
+
+    /// The side that reached the points to win ahead of the other, once a versus or multi-ball match is over. The
+    /// other modes have no winner.
+    pub fn get_winner(&self) -> Option<Side> {
+        if !self.over || self.mode.has_leaderboard() {
+            return None;
+        }
+
+        let (p1_score, p2_score) = self.world.get_scores();
+        match p1_score.cmp(&p2_score) {
+            Ordering::Greater => Some(Side::P1),
+            Ordering::Less => Some(Side::P2),
+            Ordering::Equal => None
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// Paddle hits since the last point.
+    pub fn get_rally(&self) -> u32 {
+        self.rally
+    }
+
+    /// The stats counted since the last call.
+    pub fn take_stats(&mut self) -> Stats {
+        std::mem::take(&mut self.stats)
+    }
+
+    /// What happened in the world since the last call, for hosts that react to it with sounds or effects. Only the
+    /// last `MAX_EVENTS` are kept for hosts that never call it.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn set_instant_replay(&mut self, enabled: bool) {
+        self.snapshots = if enabled { Some(SnapshotBuffer::new()) } else { None };
+    }
+
+    pub fn is_instant_replay(&self) -> bool {
+        self.instant_replay.is_some()
+    }
+
+    /// Cuts the running instant replay short and goes straight to the serve.
+    pub fn skip_instant_replay(&mut self) {
+        self.instant_replay = None;
+    }
+
+    pub fn set_p1_dir(&mut self, dir: i8) {
+        self.p1_dir = dir;
+    }
+
+    /// Hands player 1 to an AI of `difficulty`, like player 2, or back to `set_p1_dir` with `None`.
+    pub fn set_p1_ai(&mut self, difficulty: Option<Difficulty>) {
+        self.p1_ai = difficulty;
+    }
+
+    pub fn get_p1_ai(&self) -> Option<Difficulty> {
+        self.p1_ai
+    }
+
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        if self.instant_replay.is_some() {
+            self.advance_instant_replay(elapsed_ms);
+            return;
+        }
+
+        for _ in 0..self.clock.ticks(elapsed_ms) {
+            self.tick();
+            if self.instant_replay.is_some() {
+                break;
+            }
+        }
+    }
+
+    /// Plays and records a single tick, for hosts that keep their own fixed rate. While an instant replay is
+    /// showing the match is on hold, and the tick goes to the instant replay instead. Once the match is over
+    /// nothing moves anymore.
+    pub fn tick(&mut self) {
+        if self.instant_replay.is_some() {
+            self.advance_instant_replay(TICK_MS);
+            return;
+        }
+        if self.over {
+            return;
+        }
+
+        let scores = self.world.get_scores();
+        let p1_dir = self.p1_ai.map_or(self.p1_dir, |difficulty| self.world.ai_dir(Side::P1, difficulty));
+        let input = TickInput::new(p1_dir, self.world.ai_dir(Side::P2, self.difficulty));
+        self.replay.push(input);
+        self.world.tick(input);
+        self.count_events();
+
+        if let Some(snapshots) = &mut self.snapshots {
+            if self.world.get_scores() != scores {
+                self.instant_replay = snapshots.take_replay();
+            }
+            else {
+                snapshots.record(&self.world);
+            }
+        }
+    }
+
+    fn count_events(&mut self) {
+        self.events.extend_from_slice(self.world.get_events());
+        if self.events.len() > MAX_EVENTS {
+            self.events.drain(..self.events.len() - MAX_EVENTS);
+        }
+
+        for event in self.world.get_events() {
+            match event {
+                Event::PaddleHit(_) => {
+                    self.rally += 1;
+                    self.stats.longest_rally = self.stats.longest_rally.max(self.rally);
+                    for ball in self.world.get_balls() {
+                        self.stats.fastest_ball = self.stats.fastest_ball.max(ball.get_speed() * 1000.0);
+                    }
+                },
+                Event::WallBounce | Event::Served => {},
+                Event::Scored(side) => {
+                    self.rally = 0;
+                    match side {
+                        Side::P1 => self.stats.p2_points_conceded += 1,
+                        Side::P2 => self.stats.p1_points_conceded += 1
+                    }
+
+                    self.over = self.mode.is_over(&self.world, self.points_to_win);
+                }
+            }
+        }
+
+        if self.over {
+            self.stats.matches_played += 1;
+            if let Some(winner) = self.get_winner() {
+                let record = self.stats.records.entry(self.difficulty).or_default();
+                match winner {
+                    Side::P1 => record.wins += 1,
+                    Side::P2 => record.losses += 1
+                }
+            }
+        }
+    }
+
+    fn advance_instant_replay(&mut self, elapsed_ms: f32) {
+        if let Some(instant_replay) = &mut self.instant_replay {
+            instant_replay.advance(elapsed_ms);
+            if instant_replay.is_finished() {
+                self.instant_replay = None;
+            }
+        }
+    }
+
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
+
+    /// The world to draw: the live one, or a snapshot from the past while an instant replay is showing.
+    pub fn get_displayed_world(&self) -> &World {
+        match &self.instant_replay {
+            Some(instant_replay) => instant_replay.get_frame(),
+            None => &self.world
+        }
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Suspends the match, to be resumed with `load_state`.
+    ///
+    /// The binary encoding is the world (see `World::to_bytes`), one byte that is 1 when instant replays are on,
+    /// one for the difficulty (0 to 2, easiest first), one for the mode (0 to 3, in the order of `Mode`), the points
+    /// to win and the length of the current rally (`u32`, little endian), then the replay of the match so far (see
+    /// `Replay::to_bytes`).
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut bytes = self.world.to_bytes();
+        bytes.push(self.snapshots.is_some() as u8);
+        bytes.push(self.difficulty as u8);
+        bytes.push(self.mode as u8);
+        bytes.extend_from_slice(&self.points_to_win.to_le_bytes());
+        bytes.extend_from_slice(&self.rally.to_le_bytes());
+        bytes.extend_from_slice(&self.replay.to_bytes());
+        bytes
+    }
+
+    /// Resumes a match suspended with `save_state`. An instant replay that was showing is skipped.
+    pub fn load_state(bytes: &[u8]) -> Result<LiveSession, String> {
+        let mut reader = Reader::new(bytes, "Saved state");
+        let world = World::read(&mut reader)?;
+        let instant_replay = match reader.take(1)?[0] {
+            0 => false,
+            1 => true,
+            flag => return Err(format!("Invalid instant replay flag {} in saved state", flag))
+        };
+        let difficulty = match reader.take(1)?[0] {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Normal,
+            2 => Difficulty::Hard,
+            difficulty => return Err(format!("Invalid difficulty {} in saved state", difficulty))
+        };
+        let mode = match reader.take(1)?[0] {
+            0 => Mode::Versus,
+            1 => Mode::Endless,
+            2 => Mode::Survival,
+            3 => Mode::MultiBall,
+            mode => return Err(format!("Invalid mode {} in saved state", mode))
+        };
+        let points_to_win = u32::from_le_bytes(reader.take_array()?);
+        let rally = u32::from_le_bytes(reader.take_array()?);
+        let replay = Replay::from_bytes(reader.take_rest())?;
+
+        if replay.get_seed() != world.get_seed() || replay.get_multi_ball() != world.get_multi_ball() || replay.len() != world.get_tick() {
+            return Err(String::from("Saved state has a replay of another match"));
+        }
+
+        let over = mode.is_over(&world, points_to_win);
+        let mut live = LiveSession {
+            world,
+            replay,
+            clock: FixedClock::new(),
+            p1_dir: 0,
+            p1_ai: None,
+            difficulty,
+            mode,
+            points_to_win,
+            over,
+
+            snapshots: None,
+            instant_replay: None,
+
+            rally,
+            stats: Stats::new(),
+            events: Vec::new()
+        };
+        live.set_instant_replay(instant_replay);
+        Ok(live)
+    }
+}
+
+/// Whatever the host is currently showing.
+pub enum Session {
+    Live(LiveSession),
+    Playback(ReplayPlayer),
+    Online(OnlineSession),
+    Rollback(RollbackSession),
+    Spectator(SpectatorSession)
+}
+
+impl Session {
+    /// Moves the paddle of the local player, if there is one.
+    pub fn set_dir(&mut self, dir: i8) {
+        match self {
+            Session::Live(live) => live.set_p1_dir(dir),
+            Session::Online(online) => online.set_dir(dir),
+            Session::Rollback(rollback) => rollback.set_dir(dir),
+            Session::Playback(_) | Session::Spectator(_) => {}
+        }
+    }
+
+    /// Passes on a message from a match server, switching over when the match to play or watch starts.
+    pub fn receive(&mut self, message: ServerMessage) {
+        match self {
+            Session::Online(online) => online.receive(message),
+            Session::Spectator(spectator) => spectator.receive(message),
+            session => {
+                if let Some(online) = OnlineSession::from_welcome(&message) {
+                    *session = Session::Online(online);
+                }
+                else if let Some(spectator) = SpectatorSession::from_spectating(&message) {
+                    *session = Session::Spectator(spectator);
+                }
+            }
+        }
+    }
+
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        match self {
+            Session::Live(live) => live.advance(elapsed_ms),
+            Session::Playback(player) => player.advance(elapsed_ms),
+            Session::Online(online) => online.advance(elapsed_ms),
+            Session::Rollback(rollback) => rollback.advance(elapsed_ms),
+            Session::Spectator(spectator) => spectator.advance(elapsed_ms)
+        }
+    }
+
+    pub fn get_world(&self) -> &World {
+        match self {
+            Session::Live(live) => live.get_world(),
+            Session::Playback(player) => player.get_world(),
+            Session::Online(online) => online.get_world(),
+            Session::Rollback(rollback) => rollback.get_world(),
+            Session::Spectator(spectator) => spectator.get_world()
+        }
+    }
+
+    pub fn get_displayed_world(&self) -> &World {
+        match self {
+            Session::Live(live) => live.get_displayed_world(),
+            Session::Playback(player) => player.get_world(),
+            Session::Online(online) => online.get_world(),
+            Session::Rollback(rollback) => rollback.get_world(),
+            Session::Spectator(spectator) => spectator.get_world()
+        }
+    }
+
+    pub fn is_instant_replay(&self) -> bool {
+        match self {
+            Session::Live(live) => live.is_instant_replay(),
+            Session::Playback(_) | Session::Online(_) | Session::Rollback(_) | Session::Spectator(_) => false
+        }
+    }
+
+    /// Online, peer to peer and watched matches are not recorded, so they have no replay.
+    pub fn get_replay(&self) -> Option<&Replay> {
+        match self {
+            Session::Live(live) => Some(live.get_replay()),
+            Session::Playback(player) => Some(player.get_replay()),
+            Session::Online(_) | Session::Rollback(_) | Session::Spectator(_) => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::session::mode::MULTI_BALL_HITS;
+
+    // A multi-ball match to 3 at 2 all, with a ball about to go out on either side
+    fn tied_match() -> LiveSession {
+        let mut world = World::new(960.0, 600.0, 6);
+        world.set_multi_ball(MULTI_BALL_HITS);
+        let mut json: serde_json::Value = serde_json::from_str(&world.to_json()).unwrap();
+        json["p1_score"] = json!(2);
+        json["p2_score"] = json!(2);
+        json["balls"] = json!([
+            { "radius": 6.0, "x": -5.0, "y": 50.0, "velo_x": -0.6, "velo_y": 0.0 },
+            { "radius": 6.0, "x": 965.0, "y": 50.0, "velo_x": 0.6, "velo_y": 0.0 }
+        ]);
+        let world = World::from_json(&json.to_string()).unwrap();
+
+        let mut replay = Replay::new(6, 960.0, 600.0);
+        replay.set_multi_ball(MULTI_BALL_HITS);
+        let mut bytes = world.to_bytes();
+        bytes.extend_from_slice(&[0, Difficulty::Normal as u8, Mode::MultiBall as u8]);
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&replay.to_bytes());
+        LiveSession::load_state(&bytes).unwrap()
+    }
+
+    #[test]
+    fn plays_on_when_both_sides_reach_the_points_to_win_at_once() {
+        let mut live = tied_match();
+        live.tick();
+        assert_eq!(live.get_world().get_scores(), (3, 3));
+        assert!(!live.is_over());
+        assert_eq!(live.get_winner(), None);
+        assert_eq!(live.take_stats().matches_played, 0);
+
+        // The next point decides it
+        while !live.is_over() {
+            assert!(live.get_world().get_tick() < 1_000_000, "The match never ended");
+            live.tick();
+        }
+        let (p1_score, p2_score) = live.get_world().get_scores();
+        let winner = live.get_winner().expect("A match that is over has a winner");
+        assert_eq!(winner, if p1_score > p2_score { Side::P1 } else { Side::P2 });
+        assert_ne!(p1_score, p2_score);
+
+        let record = live.take_stats().get_record(Difficulty::Normal);
+        assert_eq!((record.wins, record.losses), if winner == Side::P1 { (1, 0) } else { (0, 1) });
+    }
+}
@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::{TICK_MS, World};
+
+/// Points the AI has to score to end an endless match.
+pub const ENDLESS_LIVES: u32 = 3;
+
+/// Paddle hits between extra balls in multi-ball matches.
+pub const MULTI_BALL_HITS: u32 = 4;
+
+/// What a local match against the AI is played for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Mode {
+    /// First to the points to win, or forever when that is 0.
+    #[default]
+    Versus,
+    /// Scoring as many points as possible before the AI scores `ENDLESS_LIVES`.
+    Endless,
+    /// Lasting as long as possible without the AI scoring once. The score is in whole seconds.
+    Survival,
+    /// Versus with an extra ball served every `MULTI_BALL_HITS` paddle hits. Every ball scores on its own, so both
+    /// sides can reach the points to win at once, and then the match goes on until one of them is ahead.
+    MultiBall
+}
+
+impl Mode {
+    /// Versus and multi-ball matches are won or lost rather than scored, so they have no leaderboard.
+    pub fn has_leaderboard(self) -> bool {
+        !matches!(self, Mode::Versus | Mode::MultiBall)
+    }
+
+    /// Paddle hits between extra balls in matches of this mode, see `World::set_multi_ball`.
+    pub fn get_multi_ball(self) -> u32 {
+        match self {
+            Mode::MultiBall => MULTI_BALL_HITS,
+            Mode::Versus | Mode::Endless | Mode::Survival => 0
+        }
+    }
+
+    /// Player 1's score in a match of this mode.
+    pub fn get_score(self, world: &World) -> u32 {
+        match self {
+            Mode::Versus | Mode::Endless | Mode::MultiBall => world.get_scores().0,
+            Mode::Survival => (world.get_tick() as f64 * TICK_MS as f64 / 1000.0) as u32
+        }
+    }
+
+    pub fn is_over(self, world: &World, points_to_win: u32) -> bool {
+        let (p1_score, p2_score) = world.get_scores();
+        match self {
+            Mode::Versus | Mode::MultiBall => points_to_win > 0 && p1_score.max(p2_score) >= points_to_win && p1_score != p2_score,
+            Mode::Endless => p2_score >= ENDLESS_LIVES,
+            Mode::Survival => p2_score >= 1
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    /// Reads `versus`, `endless`, `survival` or `multiball`.
+    fn from_str(name: &str) -> Result<Mode, String> {
+        match name {
+            "versus" => Ok(Mode::Versus),
+            "endless" => Ok(Mode::Endless),
+            "survival" => Ok(Mode::Survival),
+            "multiball" => Ok(Mode::MultiBall),
+            name => Err(format!("Unknown mode {} (expected versus, endless, survival or multiball)", name))
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use crate::game_object::paddle::PADDLE_SPEED;
+use crate::net::{ClientMessage, ServerMessage};
+use crate::session::interpolation::Interpolation;
+use crate::world::{Side, World, TICK_MS, clock::FixedClock};
+
+/// Inputs are sent to the server in batches of this many ticks.
+pub const INPUT_BATCH: usize = 4;
+
+/// A match simulated by a server, seen from one of its players.
+///
+/// The player's own paddle is predicted: it moves as soon as input is given, and is corrected when the server
+/// state comes back by replaying the inputs the server has not applied yet on top of it. The ball and the
+/// opponent are interpolated between the server states.
+///
+/// The session never touches the network itself: the host feeds it what the server sends through `receive` and
+/// sends what `take_outgoing` returns.
+pub struct OnlineSession {
+    side: Side,
+    world: World,
+    clock: FixedClock,
+    dir: i8,
+
+    // Inputs sent but not applied by the server yet, and the batch waiting to be sent
+    seq: u64,
+    pending: VecDeque<(u64, i8)>,
+    batch: Vec<i8>,
+    outgoing: Vec<ClientMessage>,
+
+    interpolation: Interpolation,
+    own_y: f32,
+    opponent_left: bool,
+    match_over: bool
+}
+
+impl OnlineSession {
+    pub fn new(side: Side, seed: u64, width: f32, height: f32) -> OnlineSession {
+        let world = World::new(width, height, seed);
+        let own_y = world.get_paddle(side).get_y();
+
+        OnlineSession {
+            side,
+            world,
+            clock: FixedClock::new(),
+            dir: 0,
+
+            seq: 0,
+            pending: VecDeque::new(),
+            batch: Vec::with_capacity(INPUT_BATCH),
+            outgoing: Vec::new(),
+
+            interpolation: Interpolation::new(),
+            own_y,
+            opponent_left: false,
+            match_over: false
+        }
+    }
+
+    /// Starts a session from the server's `Welcome`, or returns `None` for any other message.
+    pub fn from_welcome(message: &ServerMessage) -> Option<OnlineSession> {
+        match message {
+            ServerMessage::Welcome { side, seed, width, height } => Some(OnlineSession::new(*side, *seed, *width, *height)),
+            _ => None
+        }
+    }
+
+    pub fn set_dir(&mut self, dir: i8) {
+        self.dir = dir;
+    }
+
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        for _ in 0..self.clock.ticks(elapsed_ms) {
+            self.tick();
+        }
+    }
+
+    /// Predicts and queues up a single tick of input, for hosts that keep their own fixed rate.
+    pub fn tick(&mut self) {
+        if self.is_over() {
+            return;
+        }
+
+        self.seq += 1;
+        self.pending.push_back((self.seq, self.dir));
+        self.own_y += self.dir as f32 * PADDLE_SPEED * TICK_MS;
+
+        self.batch.push(self.dir);
+        if self.batch.len() == INPUT_BATCH {
+            let first_seq = self.seq + 1 - self.batch.len() as u64;
+            self.outgoing.push(ClientMessage::Inputs { first_seq, dirs: std::mem::take(&mut self.batch) });
+        }
+
+        self.interpolation.tick();
+        self.update_world();
+    }
+
+    pub fn receive(&mut self, message: ServerMessage) {
+        match message {
+            ServerMessage::State { snapshot, ack } => {
+                // Reconcile the prediction with the server
+                while self.pending.front().is_some_and(|(seq, _)| *seq <= ack) {
+                    self.pending.pop_front();
+                }
+                let server_y = match self.side {
+                    Side::P1 => snapshot.p1_y,
+                    Side::P2 => snapshot.p2_y
+                };
+                self.own_y = server_y + self.pending.iter().map(|(_, dir)| *dir as f32).sum::<f32>() * PADDLE_SPEED * TICK_MS;
+
+                self.interpolation.push(snapshot);
+                self.update_world();
+            },
+            ServerMessage::OpponentLeft => self.opponent_left = true,
+            ServerMessage::MatchOver => self.match_over = true,
+            _ => {}
+        }
+    }
+
+    /// Messages for the server, in the order they should be sent.
+    pub fn take_outgoing(&mut self) -> Vec<ClientMessage> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    pub fn get_side(&self) -> Side {
+        self.side
+    }
+
+    pub fn has_opponent_left(&self) -> bool {
+        self.opponent_left
+    }
+
+    /// Whether the match has ended, because the opponent left or someone reached the points to win.
+    pub fn is_over(&self) -> bool {
+        self.opponent_left || self.match_over
+    }
+
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
+
+    fn update_world(&mut self) {
+        let mut snapshot = match self.interpolation.current() {
+            Some(snapshot) => snapshot,
+            None => return
+        };
+
+        match self.side {
+            Side::P1 => snapshot.p1_y = self.own_y,
+            Side::P2 => snapshot.p2_y = self.own_y
+        }
+        self.world.apply_snapshot(&snapshot);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::net::ServerMessage;
+use crate::session::interpolation::Interpolation;
+use crate::world::{World, clock::FixedClock};
+
+/// A match simulated by a server, watched without playing in it.
+///
+/// The server sends the states of the match a broadcast delay after they happened, and they are drawn smoothed
+/// out the same way as the opponent in an online match. Like `OnlineSession`, the session never touches the
+/// network itself.
+pub struct SpectatorSession {
+    world: World,
+    clock: FixedClock,
+    interpolation: Interpolation,
+    delay: u64,
+    over: bool
+}
+
+impl SpectatorSession {
+    pub fn new(width: f32, height: f32, delay: u64) -> SpectatorSession {
+        SpectatorSession {
+            // The seed does not matter since the world only ever shows states from the server
+            world: World::new(width, height, 0),
+            clock: FixedClock::new(),
+            interpolation: Interpolation::new(),
+            delay,
+            over: false
+        }
+    }
+
+    /// Starts a session from the server's `Spectating`, or returns `None` for any other message.
+    pub fn from_spectating(message: &ServerMessage) -> Option<SpectatorSession> {
+        match message {
+            ServerMessage::Spectating { width, height, delay } => Some(SpectatorSession::new(*width, *height, *delay)),
+            _ => None
+        }
+    }
+
+    pub fn advance(&mut self, elapsed_ms: f32) {
+        for _ in 0..self.clock.ticks(elapsed_ms) {
+            self.tick();
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.interpolation.tick();
+        self.update_world();
+    }
+
+    pub fn receive(&mut self, message: ServerMessage) {
+        match message {
+            ServerMessage::State { snapshot, .. } => {
+                self.interpolation.push(snapshot);
+                self.update_world();
+            },
+            ServerMessage::MatchOver => self.over = true,
+            _ => {}
+        }
+    }
+
+    /// How many ticks behind the match the server shows it.
+    pub fn get_delay(&self) -> u64 {
+        self.delay
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
+
+    fn update_world(&mut self) {
+        if let Some(snapshot) = self.interpolation.current() {
+            self.world.apply_snapshot(&snapshot);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+use crate::world::ai::Difficulty;
+
+/// Where `StatsBook` keeps the lifetime stats.
+pub const STATS_KEY: &str = "pong-stats";
+
+/// Matches won and lost against one difficulty of AI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32
+}
+
+/// What happened over some number of matches against the AI. Wins and losses are player 1's.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Matches played to the end. Matches played forever, with 0 points to win, never count.
+    pub matches_played: u32,
+    pub records: BTreeMap<Difficulty, Record>,
+    /// Most paddle hits in a row before a point.
+    pub longest_rally: u32,
+    /// In pixels per second.
+    pub fastest_ball: f32,
+    pub p1_points_conceded: u32,
+    pub p2_points_conceded: u32
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Adds up the counts of `other` and keeps the best of both records.
+    pub fn merge(&mut self, other: &Stats) {
+        self.matches_played += other.matches_played;
+        for (difficulty, other_record) in other.records.iter() {
+            let record = self.records.entry(*difficulty).or_default();
+            record.wins += other_record.wins;
+            record.losses += other_record.losses;
+        }
+        self.longest_rally = self.longest_rally.max(other.longest_rally);
+        self.fastest_ball = self.fastest_ball.max(other.fastest_ball);
+        self.p1_points_conceded += other.p1_points_conceded;
+        self.p2_points_conceded += other.p2_points_conceded;
+    }
+
+    pub fn get_record(&self, difficulty: Difficulty) -> Record {
+        self.records.get(&difficulty).copied().unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Stats::default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Stats are always representable as JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<Stats, String> {
+        let stats: Stats = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        if !stats.fastest_ball.is_finite() {
+            return Err(String::from("Stats hold a ball speed that is not finite"));
+        }
+        Ok(stats)
+    }
+}
+
+/// The stats of this session, since the book was opened, and of every session before it, kept in `storage`.
+pub struct StatsBook {
+    storage: Box<dyn Storage>,
+    session: Stats,
+    lifetime: Stats,
+    // Whether the lifetime stats changed since they were last saved
+    unsaved: bool
+}
+
+impl StatsBook {
+    /// Stats that cannot be read are started over rather than getting in the way of playing.
+    pub fn new(storage: Box<dyn Storage>) -> StatsBook {
+        let lifetime = match storage.load(STATS_KEY) {
+            Ok(Some(json)) => Stats::from_json(&json).unwrap_or_default(),
+            _ => Stats::new()
+        };
+
+        StatsBook {
+            storage,
+            session: Stats::new(),
+            lifetime,
+            unsaved: false
+        }
+    }
+
+    /// Adds what happened since the last call, which is meant to be every tick. The lifetime stats are only saved
+    /// when a point or a match ended, so call `save` before exiting to keep the rest.
+    pub fn record(&mut self, stats: &Stats) -> Result<(), String> {
+        if stats.is_empty() {
+            return Ok(());
+        }
+        self.session.merge(stats);
+        self.lifetime.merge(stats);
+        self.unsaved = true;
+
+        let point_ended = stats.matches_played > 0 || stats.p1_points_conceded > 0 || stats.p2_points_conceded > 0;
+        if point_ended {
+            self.save()
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Saves the lifetime stats, if anything was recorded since they last were.
+    pub fn save(&mut self) -> Result<(), String> {
+        if !self.unsaved {
+            return Ok(());
+        }
+        self.storage.save(STATS_KEY, &self.lifetime.to_json())?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// Forgets the stats of this session and of every one before it.
+    pub fn reset(&mut self) -> Result<(), String> {
+        self.session = Stats::new();
+        self.lifetime = Stats::new();
+        self.unsaved = false;
+        self.storage.remove(STATS_KEY)
+    }
+
+    pub fn get_session(&self) -> &Stats {
+        &self.session
+    }
+
+    pub fn get_lifetime(&self) -> &Stats {
+        &self.lifetime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    // Keeps only the last value saved, and counts the saves
+    #[derive(Clone, Default)]
+    struct CountingStorage {
+        saved: Rc<RefCell<(u32, Option<String>)>>
+    }
+
+    impl Storage for CountingStorage {
+        fn load(&self, _: &str) -> Result<Option<String>, String> {
+            Ok(self.saved.borrow().1.clone())
+        }
+
+        fn save(&mut self, _: &str, value: &str) -> Result<(), String> {
+            let mut saved = self.saved.borrow_mut();
+            saved.0 += 1;
+            saved.1 = Some(value.to_string());
+            Ok(())
+        }
+
+        fn remove(&mut self, _: &str) -> Result<(), String> {
+            self.saved.borrow_mut().1 = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn saves_when_a_point_ends_or_when_asked() {
+        let storage = CountingStorage::default();
+        let mut book = StatsBook::new(Box::new(storage.clone()));
+
+        // Hits during a rally are only counted
+        for rally in 1..=10 {
+            book.record(&Stats { longest_rally: rally, fastest_ball: 300.0, ..Stats::new() }).unwrap();
+        }
+        book.record(&Stats::new()).unwrap();
+        assert_eq!(storage.saved.borrow().0, 0);
+        assert_eq!(book.get_session().longest_rally, 10);
+
+        book.record(&Stats { p2_points_conceded: 1, ..Stats::new() }).unwrap();
+        assert_eq!(storage.saved.borrow().0, 1);
+
+        book.record(&Stats { longest_rally: 12, ..Stats::new() }).unwrap();
+        book.save().unwrap();
+        book.save().unwrap();
+        assert_eq!(storage.saved.borrow().0, 2);
+
+        let reopened = StatsBook::new(Box::new(storage));
+        assert_eq!(reopened.get_lifetime(), book.get_lifetime());
+        assert_eq!(reopened.get_lifetime().longest_rally, 12);
+    }
+}
@@ -0,0 +1,55 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::storage::Storage;
+
+/// Keeps every key in its own `<key>.json` file in a directory, which is created on the first save.
+pub struct FileStorage {
+    dir: PathBuf
+}
+
+impl FileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> FileStorage {
+        FileStorage {
+            dir: dir.into()
+        }
+    }
+
+    /// `~/.pong`, or `.pong` in the working directory when there is no home directory.
+    pub fn in_home_dir() -> FileStorage {
+        Self::new(env::var_os("HOME").map(PathBuf::from).unwrap_or_default().join(".pong"))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self, key: &str) -> Result<Option<String>, String> {
+        match fs::read_to_string(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(format!("Could not read {}: {}", self.path(key).display(), err))
+        }
+    }
+
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|err| format!("Could not create {}: {}", self.dir.display(), err))?;
+
+        // Written next to the real file first, so that a crash halfway through leaves the old value intact
+        let path = self.path(key);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, value).map_err(|err| format!("Could not write {}: {}", temp_path.display(), err))?;
+        fs::rename(&temp_path, &path).map_err(|err| format!("Could not write {}: {}", path.display(), err))
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), String> {
+        match fs::remove_file(self.path(key)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(format!("Could not remove {}: {}", self.path(key).display(), err)),
+            _ => Ok(())
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use wasm_bindgen::JsValue;
+
+use crate::storage::Storage;
+
+/// The browser's `localStorage`, which lasts until the user clears the site's data.
+pub struct LocalStorage {
+    storage: web_sys::Storage
+}
+
+impl LocalStorage {
+    pub fn new() -> Result<LocalStorage, String> {
+        let storage = web_sys::window()
+            .ok_or_else(|| String::from("no global `window` exists"))?
+            .local_storage()
+            .map_err(describe)?
+            .ok_or_else(|| String::from("localStorage is not available"))?;
+
+        Ok(LocalStorage {
+            storage
+        })
+    }
+}
+
+impl Storage for LocalStorage {
+    fn load(&self, key: &str) -> Result<Option<String>, String> {
+        self.storage.get_item(key).map_err(describe)
+    }
+
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String> {
+        // Fails when the storage quota is used up
+        self.storage.set_item(key, value).map_err(describe)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), String> {
+        self.storage.remove_item(key).map_err(describe)
+    }
+}
+
+fn describe(err: JsValue) -> String {
+    err.as_string().unwrap_or_else(|| format!("{:?}", err))
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+pub mod file;
+#[cfg(feature = "web")]
+pub mod local;
+
+/// Somewhere to keep small pieces of text between runs, by key.
+pub trait Storage {
+    fn load(&self, key: &str) -> Result<Option<String>, String>;
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String>;
+    fn remove(&mut self, key: &str) -> Result<(), String>;
+}
+
+/// Forgets everything when dropped, for hosts with nowhere to keep anything.
+pub struct MemoryStorage {
+    values: HashMap<String, String>
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage {
+            values: HashMap::new()
+        }
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self, key: &str) -> Result<Option<String>, String> {
+        Ok(self.values.get(key).cloned())
+    }
+
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.values.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), String> {
+        self.values.remove(key);
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+// use std::panic;
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+// extern crate console_error_panic_hook;
+
+use crate::game_manager::GameManager;
+use crate::net::{ClientMessage, GameConfig, lobby_url, spectate_url};
+use crate::render::{RenderBackend, canvas::Canvas2dRenderer, webgl::{GlContext, WebGlRenderer}};
+use crate::replay::{Replay, player::ReplayPlayer};
+use crate::session::{LiveSession, Session, mode::Mode};
+use crate::storage::{Storage, local::LocalStorage};
+use crate::world::ai::Difficulty;
+
+// Where `save_state` keeps the suspended match
+const STATE_KEY: &str = "pong-state";
+
+thread_local! {
+    // The running game, for the functions exported to the host page
+    static GAME: RefCell<Option<GameManager>> = const { RefCell::new(None) };
+}
+
+static VERT_SHADER_SRC: &str = r#"#version 100
+    attribute vec2 attr_position;
+    void main() {
+        gl_Position = vec4(attr_position, 0.0, 1.0);
+    }
+"#;
+
+static FRAG_SHADER_SRC: &str = r#"#version 100
+    precision mediump float;
+    uniform vec4 color;
+    void main() {
+        gl_FragColor = color;
+    }
+"#;
+
+#[wasm_bindgen(start)]
+pub fn main() -> Result<(), JsValue> {
+    // panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("`window` does not have a `document`");
+    let body = document.body().expect("`document` does not have a `body`");
+
+    let win_size: (u32, u32) = (window.inner_width()?.as_f64().unwrap() as u32, window.inner_height()?.as_f64().unwrap() as u32);
+
+    let (canvas, renderer) = create_renderer(&document, win_size)?;
+    body.append_child(canvas.as_ref())?;
+
+    // The seed can be pinned with `?seed=<u64>` to replay a match
+    let search = window.location().search()?;
+    let seed = web_sys::UrlSearchParams::new_with_str(&search)?.get("seed").and_then(|seed| seed.parse().ok()).unwrap_or_else(rand::random);
+    web_sys::console::log_1(&format!("Match seed: {}", seed).into());
+
+    let mut gm = GameManager::new(canvas, renderer, win_size.0 as f32, win_size.1 as f32, seed)?;
+    gm.init_event_handlers(&document)?;
+    gm.start_game()?;
+    GAME.with(|game| *game.borrow_mut() = Some(gm));
+
+    Ok(())
+}
+
+// WebGL 2 where the browser has it, then WebGL 1, then the 2D context for browsers without WebGL
+fn create_renderer(document: &web_sys::Document, (width, height): (u32, u32)) -> Result<(web_sys::HtmlCanvasElement, Box<dyn RenderBackend>), JsValue> {
+    let create_canvas = || -> Result<web_sys::HtmlCanvasElement, JsValue> {
+        let canvas = document.create_element("canvas")?.dyn_into::<web_sys::HtmlCanvasElement>()?;
+        canvas.set_width(width);
+        canvas.set_height(height);
+        Ok(canvas)
+    };
+
+    let canvas = create_canvas()?;
+    let canvas = match GlContext::from_canvas(&canvas)? {
+        Some(context) => match WebGlRenderer::new(context, VERT_SHADER_SRC, FRAG_SHADER_SRC) {
+            Ok(renderer) => return Ok((canvas, Box::new(renderer))),
+            // Compile errors point at the lines of the shaders they are about, which only fits in the console. A
+            // canvas with a WebGL context cannot have a 2D one, so the fallback gets a new canvas.
+            Err(err) => {
+                web_sys::console::error_1(&err.into());
+                web_sys::console::warn_1(&"WebGL is broken, falling back to the 2D canvas".into());
+                create_canvas()?
+            }
+        },
+        None => {
+            web_sys::console::warn_1(&"Browser does not support webgl, falling back to the 2D canvas".into());
+            canvas
+        }
+    };
+    let renderer = Canvas2dRenderer::new(&canvas)?;
+    Ok((canvas, Box::new(renderer)))
+}
+
+/// Returns the replay of the current match in the compact binary format.
+#[wasm_bindgen]
+pub fn save_replay() -> Result<Vec<u8>, JsValue> {
+    with_session(|session| session.get_replay().map(|replay| replay.to_bytes()).ok_or_else(|| "Online matches have no replay".into()))
+}
+
+/// Returns the replay of the current match as JSON, for debugging.
+#[wasm_bindgen]
+pub fn save_replay_json() -> Result<String, JsValue> {
+    with_session(|session| session.get_replay().map(|replay| replay.to_json()).ok_or_else(|| "Online matches have no replay".into()))
+}
+
+/// Suspends the current match to `localStorage`, to be resumed with `load_state`, even after the page is closed.
+#[wasm_bindgen]
+pub fn save_state() -> Result<(), JsValue> {
+    let bytes = with_session(|session| match session {
+        Session::Live(live) => Ok(live.save_state()),
+        _ => Err(JsValue::from("Only local matches can be saved"))
+    })?;
+
+    // localStorage only holds strings, so the state goes in as base64
+    let window = web_sys::window().expect("no global `window` exists");
+    let encoded = window.btoa(&bytes.iter().map(|byte| *byte as char).collect::<String>())?;
+    LocalStorage::new()?.save(STATE_KEY, &encoded)?;
+    Ok(())
+}
+
+/// Resumes the match suspended by `save_state`, returning false if there is none.
+#[wasm_bindgen]
+pub fn load_state() -> Result<bool, JsValue> {
+    let encoded = match LocalStorage::new()?.load(STATE_KEY)? {
+        Some(encoded) => encoded,
+        None => return Ok(false)
+    };
+    let window = web_sys::window().expect("no global `window` exists");
+    let bytes: Vec<u8> = window.atob(&encoded)?.chars().map(|byte| byte as u8).collect();
+
+    let live = LiveSession::load_state(&bytes)?;
+    with_session(|session| *session = Session::Live(live));
+    close_menu();
+    Ok(true)
+}
+
+/// Turns slow motion replays of every point on or off for the current match.
+#[wasm_bindgen]
+pub fn set_instant_replay(enabled: bool) {
+    with_session(|session| {
+        if let Session::Live(live) = session {
+            live.set_instant_replay(enabled);
+        }
+    });
+}
+
+/// Sets how well the AI plays in local matches: `easy`, `normal` or `hard`.
+#[wasm_bindgen]
+pub fn set_difficulty(difficulty: &str) -> Result<(), JsValue> {
+    let difficulty: Difficulty = difficulty.parse()?;
+    with_session(|session| {
+        if let Session::Live(live) = session {
+            live.set_difficulty(difficulty);
+        }
+    });
+    Ok(())
+}
+
+/// Starts a new local match in `mode`: `versus` against the AI, `endless` to score as many points as possible before
+/// conceding three, `survival` to last as long as possible without conceding one, or `multiball` for versus with an
+/// extra ball every few hits. Endless and survival scores go on the leaderboard.
+#[wasm_bindgen]
+pub fn set_mode(mode: &str) -> Result<(), JsValue> {
+    let mode: Mode = mode.parse()?;
+    with_session(|session| {
+        if let Session::Live(live) = session {
+            let seed = rand::random();
+            web_sys::console::log_1(&format!("Match seed: {}", seed).into());
+            live.set_mode(mode);
+            live.rematch(seed);
+        }
+    });
+    close_menu();
+    Ok(())
+}
+
+/// Ends local versus matches once a side has `points_to_win`, so that they count towards the stats. 0 plays forever.
+#[wasm_bindgen]
+pub fn set_points_to_win(points_to_win: u32) {
+    with_session(|session| {
+        if let Session::Live(live) = session {
+            live.set_points_to_win(points_to_win);
+        }
+    });
+}
+
+/// Sets how many seconds without any input the game waits before the attract mode, where the AI plays itself until
+/// someone presses a key. 0 turns the attract mode off.
+#[wasm_bindgen]
+pub fn set_attract_delay(seconds: f32) {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").set_attract_delay(seconds * 1000.0));
+}
+
+/// Turns one of the CRT effects on or off: `scanlines`, `bloom`, `barrel` (distortion) or `chromatic` (aberration).
+#[wasm_bindgen]
+pub fn set_post_effect(name: &str, enabled: bool) -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").set_post_effect(name, enabled))?;
+    Ok(())
+}
+
+/// Replaces the sources of the shader program called `name` (`color`, `blur` or `crt`) while the game runs, for
+/// iterating on them without reloading the page. If they do not compile, the old program keeps drawing and the error
+/// points at the lines it is about.
+#[wasm_bindgen]
+pub fn reload_shader(name: &str, vertex: &str, fragment: &str) -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").reload_shader(name, vertex, fragment))?;
+    Ok(())
+}
+
+/// Returns the sources of the shader program called `name` as JSON, with `vertex` and `fragment` fields.
+#[wasm_bindgen]
+pub fn get_shader_source(name: &str) -> Result<String, JsValue> {
+    let (vertex, fragment) = GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").get_shader_source(name))
+        .ok_or_else(|| JsValue::from(format!("No shader called {:?}", name)))?;
+    Ok(serde_json::json!({ "vertex": vertex, "fragment": fragment }).to_string())
+}
+
+/// Shows the attract mode straight away.
+#[wasm_bindgen]
+pub fn start_attract() {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").start_attract());
+}
+
+/// Returns the stats of local matches as JSON, both for this page load (`session`) and for every visit so far
+/// (`lifetime`).
+#[wasm_bindgen]
+pub fn get_stats() -> String {
+    let stats = GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").get_stats());
+    let stats = stats.borrow();
+    serde_json::json!({ "session": stats.get_session(), "lifetime": stats.get_lifetime() }).to_string()
+}
+
+/// Forgets every stat, including those of earlier visits.
+#[wasm_bindgen]
+pub fn reset_stats() -> Result<(), JsValue> {
+    let stats = GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").get_stats());
+    let result = stats.borrow_mut().reset();
+    result.map_err(JsValue::from)
+}
+
+/// Returns the best scores of `mode` (`endless` or `survival`) as JSON, best first. Each entry has the player's
+/// initials (`name`), `score`, `mode`, `date` (in milliseconds since the Unix epoch) and the `seed` of the match.
+#[wasm_bindgen]
+pub fn get_leaderboard(mode: &str) -> Result<String, JsValue> {
+    let mode: Mode = mode.parse()?;
+    let leaderboard = GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").get_leaderboard());
+    let leaderboard = leaderboard.borrow();
+    Ok(serde_json::to_string(&leaderboard.get_top(mode)).expect("Entries are always representable as JSON"))
+}
+
+#[wasm_bindgen]
+pub fn clear_leaderboard() -> Result<(), JsValue> {
+    let leaderboard = GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").get_leaderboard());
+    let result = leaderboard.borrow_mut().clear();
+    result.map_err(JsValue::from)
+}
+
+/// Connects to a match server at `url` (like `ws://localhost:9001`) to play against another person.
+#[wasm_bindgen]
+pub fn play_online(url: &str) -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow_mut().as_mut().expect("The game has not started yet").connect(url, None))
+}
+
+/// Opens a room in the lobby of the match server at `url`, for a match to `points_to_win` (0 for an endless one).
+/// The room code is logged to the console.
+#[wasm_bindgen]
+pub fn create_room(url: &str, points_to_win: u32) -> Result<(), JsValue> {
+    let config = GameConfig { points_to_win, ..GameConfig::default() };
+    connect_lobby(url, ClientMessage::CreateRoom { config })
+}
+
+/// Joins the room with `code` in the lobby of the match server at `url`.
+#[wasm_bindgen]
+pub fn join_room(url: &str, code: &str) -> Result<(), JsValue> {
+    connect_lobby(url, ClientMessage::JoinRoom { code: code.to_string() })
+}
+
+/// The match starts once both players in the room are ready.
+#[wasm_bindgen]
+pub fn set_ready(ready: bool) -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").send(&ClientMessage::SetReady { ready }))
+}
+
+#[wasm_bindgen]
+pub fn leave_room() -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").send(&ClientMessage::LeaveRoom))
+}
+
+/// Watches the newest match on the match server at `url`, without playing.
+#[wasm_bindgen]
+pub fn spectate(url: &str) -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow_mut().as_mut().expect("The game has not started yet").connect(&spectate_url(url), None))
+}
+
+/// Stops the current match and plays back a replay saved by `save_replay`.
+#[wasm_bindgen]
+pub fn play_replay(bytes: &[u8]) -> Result<(), JsValue> {
+    let replay = Replay::from_bytes(bytes)?;
+    with_session(|session| *session = Session::Playback(ReplayPlayer::new(replay)));
+    Ok(())
+}
+
+/// Stops the current match and plays back a replay saved by `save_replay_json`.
+#[wasm_bindgen]
+pub fn play_replay_json(json: &str) -> Result<(), JsValue> {
+    let replay = Replay::from_json(json)?;
+    with_session(|session| *session = Session::Playback(ReplayPlayer::new(replay)));
+    Ok(())
+}
+
+fn connect_lobby(url: &str, request: ClientMessage) -> Result<(), JsValue> {
+    GAME.with(|game| game.borrow_mut().as_mut().expect("The game has not started yet").connect(&lobby_url(url), Some(request)))
+}
+
+// Matches started from the page go straight to playing
+fn close_menu() {
+    GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").close_scenes());
+}
+
+fn with_session<T>(f: impl FnOnce(&mut Session) -> T) -> T {
+    let session = GAME.with(|game| game.borrow().as_ref().expect("The game has not started yet").get_session());
+    let result = f(&mut session.borrow_mut());
+    result
+}
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_object::ball::Ball;
+use crate::world::{Side, World};
+
+/// How well the AI plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    /// Only goes after the ball when it is coming, slowly, and sometimes aims past the edge of its paddle.
+    Easy,
+    #[default]
+    Normal,
+    /// Aims closer to the middle of its paddle, so it misses less.
+    Hard
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    /// Reads `easy`, `normal` or `hard`.
+    fn from_str(name: &str) -> Result<Difficulty, String> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            name => Err(format!("Unknown difficulty {} (expected easy, normal or hard)", name))
+        }
+    }
+}
+
+impl World {
+    /// The direction the AI playing `side` wants to move in for the next tick. Player 1 aims at the other side of its
+    /// paddle than player 2, half as far from the middle: it draws nothing from the match's randomness, and does not
+    /// play the steepest shots, which player 2 rarely returns. With more than one ball in play it goes after the one
+    /// coming that is closest to getting past it.
+    pub fn ai_dir(&self, side: Side, difficulty: Difficulty) -> i8 {
+        let (paddle, target) = match side {
+            Side::P1 => (&self.p1, -self.p2_target / 2.0),
+            Side::P2 => (&self.p2, self.p2_target)
+        };
+        let coming = |ball: &&Ball| match side {
+            Side::P1 => ball.get_dir() < 0.0,
+            Side::P2 => ball.get_dir() > 0.0
+        };
+        let distance = |ball: &&Ball| (ball.get_x() - paddle.get_x()).abs();
+        let closest_coming = self.balls.iter().filter(coming).min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let moving_away = closest_coming.is_none();
+        let ball = closest_coming.unwrap_or(&self.balls[0]);
+
+        let target = match difficulty {
+            Difficulty::Easy => {
+                // Sits out every third tick, which slows it down to two thirds of the player's speed
+                if moving_away || self.tick.is_multiple_of(3) {
+                    return 0;
+                }
+                target * 1.1
+            },
+            Difficulty::Normal => target,
+            Difficulty::Hard => target / 2.0
+        };
+
+        if !Self::within(paddle.get_y() + target, ball.get_y(), paddle.get_height()/50.0) {
+            if paddle.get_y() + target < ball.get_y() {
+                1
+            }
+            else {
+                -1
+            }
+        }
+        else {
+            0
+        }
+    }
+}
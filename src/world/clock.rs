@@ -0,0 +1,25 @@
+use crate::world::TICK_MS;
+
+// Past this, the host was most likely suspended (e.g. a background tab) and catching up would only stall it
+const MAX_ELAPSED_MS: f32 = 1000.0;
+
+/// Turns elapsed wall clock time into a number of whole ticks to simulate, carrying the remainder over.
+#[derive(Default)]
+pub struct FixedClock {
+    leftover: f32
+}
+
+impl FixedClock {
+    pub fn new() -> FixedClock {
+        FixedClock {
+            leftover: 0.0
+        }
+    }
+
+    pub fn ticks(&mut self, elapsed_ms: f32) -> u32 {
+        self.leftover += elapsed_ms.clamp(0.0, MAX_ELAPSED_MS);
+        let ticks = (self.leftover / TICK_MS) as u32;
+        self.leftover -= ticks as f32 * TICK_MS;
+        ticks
+    }
+}
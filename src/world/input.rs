@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// What each paddle is asked to do for one tick: `1` moves up, `-1` moves down and `0` stays still.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickInput {
+    pub p1: i8,
+    pub p2: i8
+}
+
+impl TickInput {
+    pub fn new(p1: i8, p2: i8) -> TickInput {
+        TickInput {
+            p1,
+            p2
+        }
+    }
+}
@@ -0,0 +1,319 @@
+extern crate rand;
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::game_object::{ball::Ball, paddle::Paddle, traits::Step};
+
+pub mod ai;
+pub mod clock;
+pub mod input;
+pub mod snapshot;
+pub mod state;
+use input::TickInput;
+use snapshot::Snapshot;
+
+/// Length of one simulation tick in milliseconds. The world only ever advances by whole ticks so that a match
+/// can be replayed exactly from its inputs.
+pub const TICK_MS: f32 = 1000.0 / 240.0;
+
+/// Most balls in play at once in multi-ball.
+pub const MAX_BALLS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    P1,
+    P2
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::P1 => Side::P2,
+            Side::P2 => Side::P1
+        }
+    }
+}
+
+/// Something that happened during the last tick, for hosts that react to the game with sounds, effects or stats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    PaddleHit(Side),
+    WallBounce,
+    /// A side scored. The ball that scored leaves play if there are others, or else is served again from the middle
+    /// in the same tick.
+    Scored(Side),
+    /// A ball was served from the middle, on the first tick of the match, after a point or as an extra ball in
+    /// multi-ball.
+    Served
+}
+
+/// Everything a match is: serializing a world and restoring it later carries on exactly where it left off.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct World {
+    width: f32,
+    height: f32,
+
+    p1: Paddle,
+    p2: Paddle,
+    // Never empty, the first ball is the one served at the start of the match
+    balls: Vec<Ball>,
+    p1_score: u32,
+    p2_score: u32,
+
+    // An extra ball is served every this many paddle hits, or never when it is 0
+    multi_ball: u32,
+    // Paddle hits since the last extra ball
+    hits: u32,
+
+    p2_target: f32,
+    seed: u64,
+    rng: Pcg32,
+    tick: u64,
+
+    // What happened during the last tick
+    #[serde(skip)]
+    events: Vec<Event>
+}
+
+impl World {
+    /// Every random decision in the match is drawn from `seed`, so two worlds with the same seed fed the same
+    /// inputs play out identically.
+    pub fn new(width: f32, height: f32, seed: u64) -> World {
+        let paddle_width = width.min(height)/50.0;
+        let paddle_height = width.min(height)/5.0;
+
+        let p1 = Paddle::new(paddle_width/2.0, height/2.0, paddle_width, paddle_height);
+        let p2 = Paddle::new(width - paddle_width/2.0, height/2.0, paddle_width, paddle_height);
+        let ball = Ball::new(width/2.0, height/2.0, paddle_width/2.0);
+
+        let mut rng = Pcg32::seed_from_u64(seed);
+        let p2_target = rng.gen_range(-p2.get_height()/2.0, p2.get_height()/2.0);
+
+        World {
+            width,
+            height,
+
+            p1,
+            p2,
+            balls: vec![ball],
+            p1_score: 0,
+            p2_score: 0,
+
+            multi_ball: 0,
+            hits: 0,
+
+            p2_target,
+            seed,
+            rng,
+            tick: 0,
+
+            events: Vec::new()
+        }
+    }
+
+    /// Advances the world by one tick of `TICK_MS`, moving each paddle as `input` asks.
+    pub fn tick(&mut self, input: TickInput) {
+        self.p1.set_dir(input.p1 as f32);
+        self.p2.set_dir(input.p2 as f32);
+        self.events.clear();
+        if self.tick == 0 {
+            self.events.push(Event::Served);
+        }
+        self.step(TICK_MS);
+        self.tick += 1;
+    }
+
+    /// Serves an extra ball every `hits` paddle hits, up to `MAX_BALLS` in play, or never when it is 0. Meant to be
+    /// set before the match starts.
+    pub fn set_multi_ball(&mut self, hits: u32) {
+        self.multi_ball = hits;
+    }
+
+    pub fn get_multi_ball(&self) -> u32 {
+        self.multi_ball
+    }
+
+    fn step(&mut self, dt: f32) {
+        // Step
+        self.p1.step(dt);
+        self.p2.step(dt);
+        for ball in self.balls.iter_mut() {
+            ball.step(dt);
+        }
+
+        // Physics, every ball on its own
+        let mut index = 0;
+        while index < self.balls.len() {
+            if self.collide(index) {
+                index += 1;
+            }
+        }
+
+        // Multi-ball
+        if self.multi_ball > 0 && self.hits >= self.multi_ball {
+            self.hits = 0;
+            if self.balls.len() < MAX_BALLS {
+                self.serve_extra_ball();
+            }
+        }
+    }
+
+    // Scores the ball at `index` once it is out and bounces it off the walls and paddles, returning false if it left
+    // play
+    fn collide(&mut self, index: usize) -> bool {
+        // Out of bounds
+        let ball = &self.balls[index];
+        let scored = if ball.get_dir() < 0.0 && ball.get_x() <= 0.0 - self.p1.get_width()/2.0 {
+            Some(Side::P2)
+        }
+        else if ball.get_dir() > 0.0 && ball.get_x() >= self.width + self.p2.get_width()/2.0 {
+            Some(Side::P1)
+        }
+        else {
+            None
+        };
+        if let Some(side) = scored {
+            match side {
+                Side::P1 => self.p1_score += 1,
+                Side::P2 => self.p2_score += 1
+            }
+            self.events.push(Event::Scored(side));
+            // Only the last ball in play is served again
+            if self.balls.len() > 1 {
+                self.balls.remove(index);
+                return false;
+            }
+            self.balls[index].reset(self.width/2.0, self.height/2.0);
+            self.events.push(Event::Served);
+        }
+
+        let ball = &mut self.balls[index];
+
+        // Walls
+        if ball.get_y_dir() < 0.0 && ball.get_y() <= self.p1.get_width()/2.0 {
+            ball.bounce_y();
+            self.events.push(Event::WallBounce);
+        }
+        if ball.get_y_dir() > 0.0 && ball.get_y() >= self.height - self.p1.get_width()/2.0 {
+            ball.bounce_y();
+            self.events.push(Event::WallBounce);
+        }
+
+        // Player 1 paddle collision
+        if ball.get_dir() < 0.0 && ball.get_x() <= self.p1.get_width()*3.0/2.0 && Self::within(ball.get_y(), self.p1.get_y(), self.p1.get_height()/2.0) {
+            let dy = (ball.get_y() - self.p1.get_y())/self.p1.get_height()/2.0;
+            ball.bounce(dy);
+            self.hits += 1;
+            self.events.push(Event::PaddleHit(Side::P1));
+        }
+        // Player 2 paddle collision
+        else if ball.get_dir() > 0.0 && ball.get_x() >= self.width - self.p2.get_width()*3.0/2.0 && Self::within(ball.get_y(), self.p2.get_y(), self.p2.get_height()/2.0) {
+            let dy = (ball.get_y() - self.p2.get_y())/self.p2.get_height()/2.0;
+            ball.bounce(dy);
+            self.hits += 1;
+            self.p2_target = self.rng.gen_range(-self.p2.get_height()/2.0, self.p2.get_height()/2.0);
+            self.events.push(Event::PaddleHit(Side::P2));
+        }
+        true
+    }
+
+    // From the middle towards either side, at a random angle
+    fn serve_extra_ball(&mut self) {
+        let mut ball = Ball::new(self.width/2.0, self.height/2.0, self.balls[0].get_radius());
+        let (velo_x, _) = ball.get_velocity();
+        let velo_x = if self.rng.gen() { velo_x } else { -velo_x };
+        ball.set_velocity(velo_x, self.rng.gen_range(-0.2, 0.2));
+        self.balls.push(ball);
+        self.events.push(Event::Served);
+    }
+
+    /// Only has the first ball, since online matches are played with one.
+    pub fn snapshot(&self) -> Snapshot {
+        let ball = &self.balls[0];
+        let (ball_velo_x, ball_velo_y) = ball.get_velocity();
+
+        Snapshot {
+            tick: self.tick,
+            ball_x: ball.get_x(),
+            ball_y: ball.get_y(),
+            ball_velo_x,
+            ball_velo_y,
+            p1_y: self.p1.get_y(),
+            p2_y: self.p2.get_y(),
+            p1_score: self.p1_score,
+            p2_score: self.p2_score
+        }
+    }
+
+    /// Moves the ball and paddles to where `snapshot` has them, for hosts that only draw a world simulated
+    /// somewhere else. Any extra balls leave play.
+    pub fn apply_snapshot(&mut self, snapshot: &Snapshot) {
+        self.tick = snapshot.tick;
+        self.balls.truncate(1);
+        self.balls[0].set_position(snapshot.ball_x, snapshot.ball_y);
+        self.balls[0].set_velocity(snapshot.ball_velo_x, snapshot.ball_velo_y);
+        self.p1.set_y(snapshot.p1_y);
+        self.p2.set_y(snapshot.p2_y);
+        self.p1_score = snapshot.p1_score;
+        self.p2_score = snapshot.p2_score;
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of ticks simulated since the start of the match.
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get_p1(&self) -> &Paddle {
+        &self.p1
+    }
+
+    pub fn get_p2(&self) -> &Paddle {
+        &self.p2
+    }
+
+    pub fn get_paddle(&self, side: Side) -> &Paddle {
+        match side {
+            Side::P1 => &self.p1,
+            Side::P2 => &self.p2
+        }
+    }
+
+    /// Every ball in play, at least one.
+    pub fn get_balls(&self) -> &[Ball] {
+        &self.balls
+    }
+
+    /// The ball in play closest to `x` and `y`, like the one that just hit something there.
+    pub fn get_nearest_ball(&self, x: f32, y: f32) -> &Ball {
+        let distance = |ball: &Ball| (ball.get_x() - x).hypot(ball.get_y() - y);
+        self.balls.iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .expect("There is always a ball in play")
+    }
+
+    pub fn get_scores(&self) -> (u32, u32) {
+        (self.p1_score, self.p2_score)
+    }
+
+    /// What happened during the last tick.
+    pub fn get_events(&self) -> &[Event] {
+        &self.events
+    }
+
+    fn within(x: f32, y: f32, tolerance: f32) -> bool {
+        x < y+tolerance && x > y-tolerance
+    }
+}
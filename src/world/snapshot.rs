@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// The visible state of a world at one tick: where the ball and paddles are, and the score.
+///
+/// It leaves out everything only the simulation itself needs (like the AI and its random numbers), so it is
+/// enough to draw a match but not to keep simulating it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub ball_x: f32,
+    pub ball_y: f32,
+    pub ball_velo_x: f32,
+    pub ball_velo_y: f32,
+    pub p1_y: f32,
+    pub p2_y: f32,
+    pub p1_score: u32,
+    pub p2_score: u32
+}
+
+impl Snapshot {
+    /// Blends two snapshots, `t` going from 0 (all `self`) to 1 (all `other`). A point scored in between would
+    /// drag the ball across the whole arena, so the ball jumps to `other` in that case.
+    pub fn lerp(&self, other: &Snapshot, t: f32) -> Snapshot {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let scored = self.p1_score != other.p1_score || self.p2_score != other.p2_score;
+
+        Snapshot {
+            tick: if t < 1.0 { self.tick } else { other.tick },
+            ball_x: if scored { other.ball_x } else { lerp(self.ball_x, other.ball_x) },
+            ball_y: if scored { other.ball_y } else { lerp(self.ball_y, other.ball_y) },
+            ball_velo_x: other.ball_velo_x,
+            ball_velo_y: other.ball_velo_y,
+            p1_y: lerp(self.p1_y, other.p1_y),
+            p2_y: lerp(self.p2_y, other.p2_y),
+            p1_score: other.p1_score,
+            p2_score: other.p2_score
+        }
+    }
+}
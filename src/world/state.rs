@@ -0,0 +1,331 @@
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::Reader;
+use crate::game_object::{ball::Ball, paddle::Paddle};
+use crate::world::{MAX_BALLS, World};
+
+/// Version of the world encoding written by this build. Bump it whenever the encoding or the meaning of a field
+/// changes.
+pub const STATE_VERSION: u16 = 2;
+
+const MAGIC: &[u8; 4] = b"PNGW";
+
+/// Size of a world with one ball in the binary encoding.
+pub const STATE_LEN: usize = 4 + 2 + 8 + 8 + 4*2 + 4*5*2 + 4*3 + BALL_LEN + 4*2 + 4 + 8*2;
+
+/// Size of every ball in the binary encoding.
+pub const BALL_LEN: usize = 4*5;
+
+// rand_pcg only exposes the state of its generators through serde
+#[derive(Serialize, Deserialize)]
+struct PcgState {
+    state: u64,
+    increment: u64
+}
+
+impl World {
+    /// The compact form of the world.
+    ///
+    /// The binary encoding is, all little endian:
+    /// `"PNGW"`, version (`u16`), seed (`u64`), tick (`u64`), width and height (`f32`), both paddles as x, y, width,
+    /// height and direction (`f32`), the hits between extra balls, the hits since the last one and the number of
+    /// balls (`u32`), every ball as radius, x, y and velocity (`f32`), both scores (`u32`), the AI's target (`f32`),
+    /// then the state and increment of the random number generator (`u64`).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(STATE_LEN + (self.balls.len() - 1) * BALL_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&STATE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.tick.to_le_bytes());
+
+        let floats = [
+            self.width, self.height,
+            self.p1.get_x(), self.p1.get_y(), self.p1.get_width(), self.p1.get_height(), self.p1.get_dir(),
+            self.p2.get_x(), self.p2.get_y(), self.p2.get_width(), self.p2.get_height(), self.p2.get_dir()
+        ];
+        for float in floats.iter() {
+            bytes.extend_from_slice(&float.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&self.multi_ball.to_le_bytes());
+        bytes.extend_from_slice(&self.hits.to_le_bytes());
+        bytes.extend_from_slice(&(self.balls.len() as u32).to_le_bytes());
+        for ball in self.balls.iter() {
+            let (velo_x, velo_y) = ball.get_velocity();
+            for float in [ball.get_radius(), ball.get_x(), ball.get_y(), velo_x, velo_y].iter() {
+                bytes.extend_from_slice(&float.to_le_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&self.p1_score.to_le_bytes());
+        bytes.extend_from_slice(&self.p2_score.to_le_bytes());
+        bytes.extend_from_slice(&self.p2_target.to_le_bytes());
+
+        let rng: PcgState = serde_json::to_value(&self.rng).and_then(serde_json::from_value).expect("The generator state is always two integers");
+        bytes.extend_from_slice(&rng.state.to_le_bytes());
+        bytes.extend_from_slice(&rng.increment.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<World, String> {
+        let mut reader = Reader::new(bytes, "Saved state");
+        let world = Self::read(&mut reader)?;
+        if !reader.take_rest().is_empty() {
+            return Err(String::from("Saved state has trailing data"));
+        }
+        Ok(world)
+    }
+
+    /// Reads a world from the binary encoding of `to_bytes`, leaving the reader after it.
+    pub(crate) fn read(reader: &mut Reader) -> Result<World, String> {
+        if reader.take(4)? != MAGIC {
+            return Err(String::from("Not a saved state"));
+        }
+        let version = u16::from_le_bytes(reader.take_array()?);
+        if version != STATE_VERSION {
+            return Err(format!("Unsupported saved state version {} (expected {})", version, STATE_VERSION));
+        }
+        let seed = u64::from_le_bytes(reader.take_array()?);
+        let tick = u64::from_le_bytes(reader.take_array()?);
+        let width = reader.take_f32()?;
+        let height = reader.take_f32()?;
+
+        let mut paddles = Vec::with_capacity(2);
+        for _ in 0..2 {
+            let mut paddle = Paddle::new(reader.take_f32()?, reader.take_f32()?, reader.take_f32()?, reader.take_f32()?);
+            paddle.set_dir(reader.take_f32()?);
+            paddles.push(paddle);
+        }
+        let p2 = paddles.pop().unwrap();
+        let p1 = paddles.pop().unwrap();
+
+        let multi_ball = u32::from_le_bytes(reader.take_array()?);
+        let hits = u32::from_le_bytes(reader.take_array()?);
+        let num_balls = u32::from_le_bytes(reader.take_array()?) as usize;
+        if !(1..=MAX_BALLS).contains(&num_balls) {
+            return Err(format!("Saved state has {} balls, not between 1 and {}", num_balls, MAX_BALLS));
+        }
+        let mut balls = Vec::with_capacity(num_balls);
+        for _ in 0..num_balls {
+            let radius = reader.take_f32()?;
+            let mut ball = Ball::new(reader.take_f32()?, reader.take_f32()?, radius);
+            ball.set_velocity(reader.take_f32()?, reader.take_f32()?);
+            balls.push(ball);
+        }
+
+        let p1_score = u32::from_le_bytes(reader.take_array()?);
+        let p2_score = u32::from_le_bytes(reader.take_array()?);
+        let p2_target = reader.take_f32()?;
+
+        let rng = PcgState {
+            state: u64::from_le_bytes(reader.take_array()?),
+            increment: u64::from_le_bytes(reader.take_array()?)
+        };
+        let rng: Pcg32 = serde_json::to_value(rng).and_then(serde_json::from_value).map_err(|err| err.to_string())?;
+
+        let world = World {
+            width,
+            height,
+
+            p1,
+            p2,
+            balls,
+            p1_score,
+            p2_score,
+
+            multi_ball,
+            hits,
+
+            p2_target,
+            seed,
+            rng,
+            tick,
+
+            events: Vec::new()
+        };
+        world.validate()?;
+        Ok(world)
+    }
+
+    /// A human readable form of the world, meant for debugging.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Worlds are always representable as JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<World, String> {
+        let world: World = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        world.validate()?;
+        Ok(world)
+    }
+
+    /// Rejects worlds no match could have reached, which would misbehave instead of playing on.
+    fn validate(&self) -> Result<(), String> {
+        let mut sizes = vec![self.width, self.height, self.p1.get_width(), self.p1.get_height(), self.p2.get_width(), self.p2.get_height()];
+        sizes.extend(self.balls.iter().map(|ball| ball.get_radius()));
+        if !sizes.iter().all(|size| size.is_finite() && *size > 0.0) {
+            return Err(String::from("Saved state has an arena, paddle or ball that is not a positive size"));
+        }
+
+        if self.balls.is_empty() || self.balls.len() > MAX_BALLS {
+            return Err(format!("Saved state has {} balls, not between 1 and {}", self.balls.len(), MAX_BALLS));
+        }
+
+        let mut floats = vec![
+            self.p1.get_x(), self.p1.get_y(), self.p1.get_dir(),
+            self.p2.get_x(), self.p2.get_y(), self.p2.get_dir(),
+            self.p2_target
+        ];
+        for ball in self.balls.iter() {
+            let (velo_x, velo_y) = ball.get_velocity();
+            floats.extend_from_slice(&[ball.get_x(), ball.get_y(), velo_x, velo_y]);
+        }
+        if floats.iter().any(|float| !float.is_finite()) {
+            return Err(String::from("Saved state holds a number that is not finite"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::LiveSession;
+    use crate::world::input::TickInput;
+
+    // Where each float of a world with one ball starts in the binary encoding
+    const WIDTH: usize = 22;
+    const HEIGHT: usize = 26;
+    const P1: usize = 30;
+    const P2: usize = 50;
+    const BALL: usize = 82;
+    const P2_TARGET: usize = 110;
+
+    // Paddles and balls list their fields in the order they are encoded
+    const X: usize = 0;
+    const Y: usize = 4;
+    const PADDLE_WIDTH: usize = 8;
+    const PADDLE_HEIGHT: usize = 12;
+    const PADDLE_DIR: usize = 16;
+    const RADIUS: usize = 0;
+    const BALL_X: usize = 4;
+    const BALL_VELO_Y: usize = 16;
+
+    fn played_world() -> World {
+        let mut world = World::new(960.0, 600.0, 8);
+        for tick in 0..500 {
+            world.tick(TickInput::new(if tick % 200 < 100 { 1 } else { -1 }, 0));
+        }
+        world
+    }
+
+    fn with_float(at: usize, value: f32) -> Vec<u8> {
+        let mut bytes = played_world().to_bytes();
+        bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let world = played_world();
+        let bytes = world.to_bytes();
+        assert_eq!(bytes.len(), STATE_LEN);
+        assert_eq!(World::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+        assert_eq!(World::from_json(&world.to_json()).unwrap().to_bytes(), bytes);
+    }
+
+    #[test]
+    fn resumes_a_suspended_match() {
+        let mut live = LiveSession::new(960.0, 600.0, 5);
+        live.set_instant_replay(true);
+        live.set_points_to_win(7);
+        for tick in 0..2000 {
+            live.set_p1_dir(if tick % 300 < 150 { 1 } else { -1 });
+            live.tick();
+        }
+
+        // Neither the direction held nor an instant replay showing are saved
+        live.set_p1_dir(0);
+        live.skip_instant_replay();
+
+        let bytes = live.save_state();
+        let mut resumed = LiveSession::load_state(&bytes).unwrap();
+        assert_eq!(resumed.save_state(), bytes);
+
+        // Both go on to play the same match, without the instant replays of points from before the resumed one
+        live.set_instant_replay(false);
+        resumed.set_instant_replay(false);
+        for _ in 0..2000 {
+            live.tick();
+            resumed.tick();
+        }
+        assert_eq!(resumed.get_world().to_bytes(), live.get_world().to_bytes());
+        assert_eq!(resumed.get_replay().to_bytes(), live.get_replay().to_bytes());
+    }
+
+    #[test]
+    fn rejects_sizes_that_are_not_positive() {
+        for at in [WIDTH, HEIGHT, P1 + PADDLE_WIDTH, P1 + PADDLE_HEIGHT, P2 + PADDLE_WIDTH, P2 + PADDLE_HEIGHT, BALL + RADIUS] {
+            for value in [0.0, -4.0, f32::NAN, f32::INFINITY] {
+                assert!(World::from_bytes(&with_float(at, value)).is_err(), "Accepted {} at byte {}", value, at);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_numbers_that_are_not_finite() {
+        for at in [P1 + X, P1 + Y, P1 + PADDLE_DIR, P2 + X, P2 + Y, P2 + PADDLE_DIR, BALL + BALL_X, BALL + BALL_VELO_Y, P2_TARGET] {
+            for value in [f32::NAN, f32::NEG_INFINITY] {
+                assert!(World::from_bytes(&with_float(at, value)).is_err(), "Accepted {} at byte {}", value, at);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_that_are_not_positive_in_json() {
+        let mut world = played_world();
+        world.p2 = Paddle::new(world.p2.get_x(), world.p2.get_y(), world.p2.get_width(), 0.0);
+        assert!(World::from_json(&world.to_json()).is_err());
+
+        let mut world = played_world();
+        world.balls[0] = Ball::new(10.0, 10.0, -1.0);
+        assert!(World::from_json(&world.to_json()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_malformed_encoding() {
+        let bytes = played_world().to_bytes();
+        let corrupt = |at: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + value.len()].copy_from_slice(value);
+            bytes
+        };
+
+        assert!(World::from_bytes(&corrupt(0, b"PNGX")).is_err());
+        assert!(World::from_bytes(&corrupt(4, &(STATE_VERSION + 1).to_le_bytes())).is_err());
+        assert!(World::from_bytes(&corrupt(78, &0u32.to_le_bytes())).is_err());
+        assert!(World::from_bytes(&corrupt(78, &(MAX_BALLS as u32 + 1).to_le_bytes())).is_err());
+        assert!(World::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(World::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_corrupt_suspended_match() {
+        let live = LiveSession::new(960.0, 600.0, 5);
+        let bytes = live.save_state();
+        let corrupt = |at: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[at] = value;
+            bytes
+        };
+
+        // The flag, difficulty and mode follow the world
+        assert!(LiveSession::load_state(&corrupt(STATE_LEN, 2)).is_err());
+        assert!(LiveSession::load_state(&corrupt(STATE_LEN + 1, 3)).is_err());
+        assert!(LiveSession::load_state(&corrupt(STATE_LEN + 2, 4)).is_err());
+        assert!(LiveSession::load_state(&with_float(P1 + PADDLE_HEIGHT, 0.0)).is_err());
+        // A replay of another match
+        assert!(LiveSession::load_state(&corrupt(6, bytes[6].wrapping_add(1))).is_err());
+    }
+}